@@ -0,0 +1,530 @@
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
+use crate::git::{run_git, validate_branch_name};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Branch {
+    // Short name: "main" locally, "origin/main" for a remote branch
+    pub name: String,
+    // Full ref, e.g. refs/remotes/origin/main
+    pub refname: String,
+    // The remote of a remote branch; None for local branches
+    pub remote: Option<String>,
+    pub is_current: bool,
+    // What a local branch tracks, e.g. "origin/main"
+    pub upstream: Option<String>,
+    // The upstream is configured but no longer exists (deleted on the remote)
+    pub upstream_gone: bool,
+    pub ahead: i32,
+    pub behind: i32,
+    pub commit: String,
+    pub subject: String,
+    // Committer time, unix seconds
+    pub commit_time: u64,
+    // Fully contained in the default branch, so deleting it loses nothing
+    pub merged: bool,
+}
+
+impl Branch {
+    pub fn is_remote(&self) -> bool {
+        self.remote.is_some()
+    }
+}
+
+// Fields are NUL separated; %(symref) is only set for aliases like origin/HEAD
+const BRANCH_FORMAT: &str =
+    "%(refname)%00%(refname:short)%00%(HEAD)%00%(upstream:short)%00%(upstream:track,nobracket)%00%(objectname)%00%(committerdate:unix)%00%(symref)%00%(contents:subject)";
+
+// Local and remote branches, local ones first, each sorted by name
+pub fn list_branches(runner: &dyn CommandRunner, path: String, env: Environment) -> Result<Vec<Branch>, KetraError> {
+    list_with_default(runner, &path, env).map(|(branches, _)| branches)
+}
+
+// The branches plus the default branch's full ref, if there is one
+fn list_with_default(runner: &dyn CommandRunner, path: &str, env: Environment) -> Result<(Vec<Branch>, Option<String>), KetraError> {
+    let format = format!("--format={}", BRANCH_FORMAT);
+    let stdout = run_git(runner, env, path, &["for-each-ref", &format, "refs/heads", "refs/remotes"], "Failed to get branches")?;
+    let (mut branches, remote_head) = parse_branches(&stdout);
+    let default = default_branch(&branches, remote_head.as_deref());
+
+    if let Some(default) = &default {
+        let merged_arg = format!("--merged={}", default);
+        let merged = run_git(
+            runner,
+            env,
+            path,
+            &["for-each-ref", &merged_arg, "--format=%(refname)", "refs/heads", "refs/remotes"],
+            "Failed to check merged branches",
+        )?;
+        let merged: HashSet<&str> = merged.lines().collect();
+        for branch in &mut branches {
+            branch.merged = merged.contains(branch.refname.as_str());
+        }
+    }
+
+    branches.sort_by(|a, b| (a.is_remote(), &a.name).cmp(&(b.is_remote(), &b.name)));
+    Ok((branches, default))
+}
+
+// What deleting a branch would throw away, shown before asking to confirm
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DeletePreview {
+    pub name: String,
+    pub remote: Option<String>,
+    // The tip the preview was made against; confirming passes it back
+    pub commit: String,
+    pub merged: bool,
+    // "abc1234 Subject" for commits the default branch doesn't have, newest first
+    pub unmerged_commits: Vec<String>,
+}
+
+// Dry run of delete_branch for each name (as listed, e.g. "origin/feature")
+pub fn preview_branch_delete(runner: &dyn CommandRunner, path: String, env: Environment, names: Vec<String>) -> Result<Vec<DeletePreview>, KetraError> {
+    let (branches, default) = list_with_default(runner, &path, env)?;
+
+    names
+        .iter()
+        .map(|name| {
+            let branch = deletable(&branches, default.as_deref(), name)?;
+            let unmerged_commits = match (&default, branch.merged) {
+                (Some(default), false) => {
+                    let range = format!("{}..{}", default, branch.refname);
+                    run_git(runner, env, &path, &["log", "--format=%h %s", &range, "--"], "Failed to list unmerged commits")?
+                        .lines()
+                        .map(str::to_string)
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+            Ok(DeletePreview {
+                name: branch.name.clone(),
+                remote: branch.remote.clone(),
+                commit: branch.commit.clone(),
+                merged: branch.merged,
+                unmerged_commits,
+            })
+        })
+        .collect()
+}
+
+// Delete a local branch, or a branch on its remote. Branches the default
+// branch doesn't contain need `confirmed_commit` set to the tip shown by
+// preview_branch_delete, so new commits made after the preview are never lost.
+pub fn delete_branch(
+    runner: &dyn CommandRunner,
+    path: String,
+    env: Environment,
+    name: String,
+    confirmed_commit: Option<String>,
+) -> Result<String, KetraError> {
+    let (branches, default) = list_with_default(runner, &path, env)?;
+    let branch = deletable(&branches, default.as_deref(), &name)?;
+
+    if !branch.merged && confirmed_commit.as_deref() != Some(branch.commit.as_str()) {
+        let message = match confirmed_commit {
+            Some(_) => format!("'{}' has new commits since the preview. Review it again before deleting.", name),
+            None => format!("'{}' has commits that aren't in the default branch. Preview and confirm the delete.", name),
+        };
+        return Err(KetraError::new(ErrorKind::NeedsConfirmation, message));
+    }
+
+    match &branch.remote {
+        Some(remote) => {
+            let remote_branch = &branch.name[remote.len() + 1..];
+            run_git(runner, env, &path, &["push", remote, "--delete", remote_branch], "Failed to delete remote branch")?;
+            Ok(format!("Deleted '{}' on {}", remote_branch, remote))
+        }
+        None => {
+            // Merged (into the default branch, which `-d` doesn't check) or
+            // confirmed above, so force is safe
+            run_git(runner, env, &path, &["branch", "-D", &branch.name], "Failed to delete branch")?;
+            Ok(format!("Deleted branch '{}'", branch.name))
+        }
+    }
+}
+
+// The default branch counts as merged into itself, so it is refused here
+// rather than left to the merged check, both locally and on origin
+fn deletable<'a>(branches: &'a [Branch], default: Option<&str>, name: &str) -> Result<&'a Branch, KetraError> {
+    let branch = branches
+        .iter()
+        .find(|b| b.name == name)
+        .ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, format!("No branch named '{}'", name)))?;
+    if branch.is_current {
+        return Err(KetraError::new(ErrorKind::InvalidInput, format!("'{}' is checked out. Switch to another branch first.", name)));
+    }
+
+    let name_on_origin = branch.name.strip_prefix("origin/").filter(|_| branch.remote.as_deref() == Some("origin"));
+    let short_name = if branch.is_remote() { name_on_origin } else { Some(branch.name.as_str()) };
+    if let Some(default) = default {
+        if branch.refname == default || short_name == Some(default_name(default)) {
+            return Err(KetraError::new(ErrorKind::InvalidInput, format!("'{}' is the default branch and can't be deleted", name)));
+        }
+    }
+    Ok(branch)
+}
+
+pub fn rename_branch(runner: &dyn CommandRunner, path: String, env: Environment, old_name: String, new_name: String) -> Result<String, KetraError> {
+    validate_branch_name(&old_name)?;
+    validate_branch_name(&new_name)?;
+    run_git(runner, env, &path, &["branch", "-m", &old_name, &new_name], "Failed to rename branch")?;
+    Ok(format!("Renamed '{}' to '{}'", old_name, new_name))
+}
+
+// Create a local branch from a remote one ("origin/feature") that tracks it,
+// named after it unless `local_name` is given
+pub fn checkout_remote_branch(
+    runner: &dyn CommandRunner,
+    path: String,
+    env: Environment,
+    remote_branch: String,
+    local_name: Option<String>,
+) -> Result<String, KetraError> {
+    let branches = list_branches(runner, path.clone(), env)?;
+    let remote = branches
+        .iter()
+        .find(|b| b.name == remote_branch)
+        .and_then(|b| b.remote.as_deref())
+        .ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, format!("No remote branch named '{}'", remote_branch)))?;
+
+    let local_name = local_name.unwrap_or_else(|| remote_branch[remote.len() + 1..].to_string());
+    validate_branch_name(&local_name)?;
+    if branches.iter().any(|b| !b.is_remote() && b.name == local_name) {
+        return Err(KetraError::new(ErrorKind::AlreadyExists, format!("A local branch named '{}' already exists", local_name)));
+    }
+
+    run_git(runner, env, &path, &["checkout", "-b", &local_name, "--track", &remote_branch], "Failed to check out remote branch")?;
+    Ok(format!("Switched to '{}' tracking '{}'", local_name, remote_branch))
+}
+
+// Local branches the default branch already contains, except the current one
+// and the local copy of the default itself. With `dry_run` nothing is deleted.
+pub fn prune_merged_branches(runner: &dyn CommandRunner, path: String, env: Environment, dry_run: bool) -> Result<Vec<String>, KetraError> {
+    let (branches, default) = list_with_default(runner, &path, env)?;
+    let Some(default) = default else { return Ok(Vec::new()) };
+    let default_name = default_name(&default);
+
+    let prunable: Vec<String> = branches
+        .iter()
+        .filter(|b| !b.is_remote() && b.merged && !b.is_current && b.name != default_name)
+        .map(|b| b.name.clone())
+        .collect();
+
+    if !dry_run && !prunable.is_empty() {
+        let mut args = vec!["branch", "-D"];
+        args.extend(prunable.iter().map(|name| name.as_str()));
+        run_git(runner, env, &path, &args, "Failed to delete merged branches")?;
+    }
+    Ok(prunable)
+}
+
+// "main" for refs/heads/main or refs/remotes/origin/main; default_branch is
+// either a local branch or origin's HEAD
+fn default_name(default: &str) -> &str {
+    default.strip_prefix("refs/heads/").or_else(|| default.strip_prefix("refs/remotes/origin/")).unwrap_or(default)
+}
+
+// The remote's HEAD (e.g. refs/remotes/origin/main) when the clone recorded
+// one, otherwise a local main or master
+fn default_branch(branches: &[Branch], remote_head: Option<&str>) -> Option<String> {
+    if let Some(head) = remote_head.filter(|head| branches.iter().any(|b| b.refname == *head)) {
+        return Some(head.to_string());
+    }
+    ["refs/heads/main", "refs/heads/master"]
+        .into_iter()
+        .find(|candidate| branches.iter().any(|b| b.refname == *candidate))
+        .map(str::to_string)
+}
+
+// Parse the BRANCH_FORMAT lines. Also returns where origin/HEAD points.
+fn parse_branches(stdout: &str) -> (Vec<Branch>, Option<String>) {
+    let mut branches = Vec::new();
+    let mut remote_head = None;
+
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.splitn(9, '\0').collect();
+        let [refname, name, head, upstream, track, commit, time, symref, subject] = fields[..] else {
+            continue;
+        };
+
+        if !symref.is_empty() {
+            if refname == "refs/remotes/origin/HEAD" {
+                remote_head = Some(symref.to_string());
+            }
+            continue;
+        }
+
+        let remote = refname
+            .strip_prefix("refs/remotes/")
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(remote, _)| remote.to_string());
+        let (ahead, behind, upstream_gone) = parse_track(track);
+
+        branches.push(Branch {
+            name: name.to_string(),
+            refname: refname.to_string(),
+            remote,
+            is_current: head == "*",
+            upstream: (!upstream.is_empty()).then(|| upstream.to_string()),
+            upstream_gone,
+            ahead,
+            behind,
+            commit: commit.to_string(),
+            subject: subject.to_string(),
+            commit_time: time.parse().unwrap_or(0),
+            merged: false,
+        });
+    }
+
+    (branches, remote_head)
+}
+
+// "ahead 2, behind 1", "ahead 2", "gone" or empty -> (ahead, behind, gone)
+fn parse_track(track: &str) -> (i32, i32, bool) {
+    let mut ahead = 0;
+    let mut behind = 0;
+    for part in track.split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", n)) => ahead = n.parse().unwrap_or(0),
+            Some(("behind", n)) => behind = n.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (ahead, behind, track == "gone")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    const REPO: &str = "/home/dev/ketra/app";
+
+    fn line(fields: [&str; 9]) -> String {
+        fields.join("\0") + "\n"
+    }
+
+    fn refs() -> String {
+        [
+            line(["refs/heads/main", "main", "*", "origin/main", "ahead 2, behind 1", "aaa", "1700000000", "", "Fix parser"]),
+            line(["refs/heads/feature", "feature", " ", "", "", "bbb", "1700000100", "", "WIP: login"]),
+            line(["refs/heads/old", "old", " ", "origin/old", "gone", "ccc", "1600000000", "", "Old work"]),
+            line(["refs/remotes/origin/HEAD", "origin", " ", "", "", "aaa", "1700000000", "refs/remotes/origin/main", "Fix parser"]),
+            line(["refs/remotes/origin/main", "origin/main", " ", "", "", "ddd", "1690000000", "", "Release"]),
+            line(["refs/remotes/upstream/main", "upstream/main", " ", "", "", "ddd", "1690000000", "", "Release, with a\ttab"]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn parses_local_and_remote_branches() {
+        let (branches, remote_head) = parse_branches(&refs());
+        assert_eq!(remote_head.as_deref(), Some("refs/remotes/origin/main"));
+
+        let names: Vec<(&str, Option<&str>)> = branches.iter().map(|b| (b.name.as_str(), b.remote.as_deref())).collect();
+        assert_eq!(
+            names,
+            [("main", None), ("feature", None), ("old", None), ("origin/main", Some("origin")), ("upstream/main", Some("upstream"))]
+        );
+
+        let main = &branches[0];
+        assert!(main.is_current);
+        assert_eq!((main.upstream.as_deref(), main.ahead, main.behind), (Some("origin/main"), 2, 1));
+        assert_eq!((main.commit.as_str(), main.subject.as_str(), main.commit_time), ("aaa", "Fix parser", 1700000000));
+        assert_eq!((branches[1].upstream.as_deref(), branches[1].is_current), (None, false));
+        assert!(branches[2].upstream_gone);
+        assert_eq!(branches[4].subject, "Release, with a\ttab");
+    }
+
+    #[test]
+    fn parses_tracking_counts() {
+        assert_eq!(parse_track("ahead 3"), (3, 0, false));
+        assert_eq!(parse_track("behind 12"), (0, 12, false));
+        assert_eq!(parse_track("ahead 1, behind 2"), (1, 2, false));
+        assert_eq!(parse_track("gone"), (0, 0, true));
+        assert_eq!(parse_track(""), (0, 0, false));
+    }
+
+    #[test]
+    fn merged_is_checked_against_the_remote_default() {
+        let runner = FakeRunner::new()
+            .on("--merged=", CommandOutput::ok("refs/heads/old\nrefs/remotes/origin/main\n"))
+            .on("for-each-ref", CommandOutput::ok(&refs()));
+
+        let branches = list_branches(&runner, REPO.to_string(), Environment::Wsl).unwrap();
+        let merged: Vec<&str> = branches.iter().filter(|b| b.merged).map(|b| b.name.as_str()).collect();
+        assert_eq!(merged, ["old", "origin/main"]);
+        assert!(runner.ran("--merged=refs/remotes/origin/main"));
+
+        // Local branches first, then remote ones, by name
+        let order: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(order, ["feature", "main", "old", "origin/main", "upstream/main"]);
+    }
+
+    #[test]
+    fn default_falls_back_to_a_local_main_or_master() {
+        let (branches, _) = parse_branches(&line(["refs/heads/master", "master", "*", "", "", "a", "1", "", "x"]));
+        assert_eq!(default_branch(&branches, None).as_deref(), Some("refs/heads/master"));
+        assert_eq!(default_branch(&branches, Some("refs/remotes/origin/main")).as_deref(), Some("refs/heads/master"));
+        assert_eq!(default_branch(&[], None), None);
+    }
+
+    fn merged_runner() -> FakeRunner {
+        FakeRunner::new()
+            .on("--merged=", CommandOutput::ok("refs/heads/old\nrefs/remotes/origin/main\n"))
+            .on("for-each-ref", CommandOutput::ok(&refs()))
+            .on("log", CommandOutput::ok("bbb1234 WIP: login\nbbb0000 Start login\n"))
+    }
+
+    #[test]
+    fn previews_unmerged_commits_before_deleting() {
+        let runner = merged_runner();
+        let previews = preview_branch_delete(&runner, REPO.to_string(), Environment::Wsl, vec!["feature".into(), "old".into()]).unwrap();
+
+        assert_eq!(previews[0].commit, "bbb");
+        assert!(!previews[0].merged);
+        assert_eq!(previews[0].unmerged_commits, ["bbb1234 WIP: login", "bbb0000 Start login"]);
+        assert!(previews[1].merged && previews[1].unmerged_commits.is_empty());
+        assert!(runner.ran("log --format=%h %s refs/remotes/origin/main..refs/heads/feature --"));
+        assert!(!runner.ran("branch -D"));
+
+        let current = preview_branch_delete(&runner, REPO.to_string(), Environment::Wsl, vec!["main".into()]).unwrap_err();
+        assert!(current.is(&ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn unmerged_deletes_need_the_previewed_tip() {
+        let runner = merged_runner();
+        let delete = |confirmed: Option<&str>| delete_branch(&runner, REPO.to_string(), Environment::Wsl, "feature".into(), confirmed.map(str::to_string));
+
+        assert!(delete(None).unwrap_err().is(&ErrorKind::NeedsConfirmation));
+        assert!(delete(Some("stale")).unwrap_err().is(&ErrorKind::NeedsConfirmation));
+        assert!(!runner.ran("branch -D"));
+
+        delete(Some("bbb")).unwrap();
+        assert!(runner.ran("branch -D feature"));
+
+        // Merged branches go without confirmation
+        delete_branch(&runner, REPO.to_string(), Environment::Wsl, "old".into(), None).unwrap();
+        assert!(runner.ran("branch -D old"));
+    }
+
+    #[test]
+    fn deletes_remote_branches_on_their_remote() {
+        let runner = merged_runner();
+        let message = delete_branch(&runner, REPO.to_string(), Environment::Wsl, "upstream/main".into(), Some("ddd".into())).unwrap();
+        assert_eq!(message, "Deleted 'main' on upstream");
+        assert!(runner.ran("push upstream --delete main"));
+    }
+
+    #[test]
+    fn the_default_branch_is_never_deleted() {
+        let runner = merged_runner();
+        for confirmed in [None, Some("ddd".to_string())] {
+            let err = delete_branch(&runner, REPO.to_string(), Environment::Wsl, "origin/main".into(), confirmed).unwrap_err();
+            assert!(err.is(&ErrorKind::InvalidInput));
+        }
+        assert!(preview_branch_delete(&runner, REPO.to_string(), Environment::Wsl, vec!["origin/main".into()]).is_err());
+        assert!(!runner.ran("push"));
+
+        // A local default that isn't checked out
+        let refs = [
+            line(["refs/heads/master", "master", " ", "", "", "a", "1", "", "x"]),
+            line(["refs/heads/topic", "topic", "*", "", "", "b", "2", "", "y"]),
+        ]
+        .concat();
+        let runner = FakeRunner::new()
+            .on("--merged=", CommandOutput::ok("refs/heads/master\n"))
+            .on("for-each-ref", CommandOutput::ok(&refs));
+        let err = delete_branch(&runner, REPO.to_string(), Environment::Wsl, "master".into(), None).unwrap_err();
+        assert_eq!(err.message, "'master' is the default branch and can't be deleted");
+        assert!(!runner.ran("branch -D"));
+    }
+
+    #[test]
+    fn checks_out_remote_branches_with_tracking() {
+        let runner = merged_runner();
+        let taken = checkout_remote_branch(&runner, REPO.to_string(), Environment::Wsl, "origin/main".into(), None).unwrap_err();
+        assert!(taken.is(&ErrorKind::AlreadyExists));
+
+        checkout_remote_branch(&runner, REPO.to_string(), Environment::Wsl, "upstream/main".into(), Some("upstream-main".into())).unwrap();
+        assert!(runner.ran("checkout -b upstream-main --track upstream/main"));
+
+        let missing = checkout_remote_branch(&runner, REPO.to_string(), Environment::Wsl, "origin/nope".into(), None).unwrap_err();
+        assert!(missing.is(&ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn rename_validates_both_names() {
+        let runner = FakeRunner::new();
+        let err = rename_branch(&runner, REPO.to_string(), Environment::Wsl, "feature".into(), "bad name".into()).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+        assert!(runner.calls().is_empty());
+
+        rename_branch(&runner, REPO.to_string(), Environment::Wsl, "feature".into(), "feature/login".into()).unwrap();
+        assert!(runner.ran("branch -m feature feature/login"));
+    }
+
+    #[test]
+    fn prunes_merged_local_branches() {
+        let runner = merged_runner();
+        let preview = prune_merged_branches(&runner, REPO.to_string(), Environment::Wsl, true).unwrap();
+        assert_eq!(preview, ["old"]);
+        assert!(!runner.ran("branch -D"));
+
+        prune_merged_branches(&runner, REPO.to_string(), Environment::Wsl, false).unwrap();
+        assert!(runner.ran("branch -D old"));
+    }
+
+    #[test]
+    fn lists_the_branches_of_a_real_repo() {
+        use crate::exec::system;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("ketra-branches-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let git = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            let output = system().output(&Environment::host().command("git", &full, Some(&path))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        };
+
+        git(&["init", "-q", "-b", "main"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["branch", "done"]);
+        git(&["checkout", "-q", "-b", "feature"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "feature work"]);
+        git(&["branch", "-q", "--set-upstream-to=main"]);
+
+        let branches = list_branches(system(), path.clone(), Environment::host()).unwrap();
+        let summary: Vec<(&str, bool, bool)> = branches.iter().map(|b| (b.name.as_str(), b.is_current, b.merged)).collect();
+        assert_eq!(summary, [("done", false, true), ("feature", true, false), ("main", false, true)]);
+
+        let feature = &branches[1];
+        assert_eq!((feature.upstream.as_deref(), feature.ahead, feature.behind), (Some("main"), 1, 0));
+        assert_eq!(feature.subject, "feature work");
+        assert_eq!(feature.commit.len(), 40);
+        assert!(feature.commit_time > 0);
+
+        git(&["checkout", "-q", "main"]);
+        assert_eq!(prune_merged_branches(system(), path.clone(), Environment::host(), false).unwrap(), ["done"]);
+
+        let preview = preview_branch_delete(system(), path.clone(), Environment::host(), vec!["feature".into()]).unwrap();
+        assert_eq!(preview[0].unmerged_commits.len(), 1);
+        assert!(preview[0].unmerged_commits[0].ends_with(" feature work"));
+        let tip = preview[0].commit.clone();
+        delete_branch(system(), path.clone(), Environment::host(), "feature".into(), Some(tip)).unwrap();
+
+        let names: Vec<String> = list_branches(system(), path.clone(), Environment::host()).unwrap().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, ["main"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
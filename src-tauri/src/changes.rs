@@ -0,0 +1,645 @@
+use crate::diff::{option_args, DiffOptions};
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
+use crate::git::run_git;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// One changed, untracked or conflicted path in a repo
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: String,
+    // Where a renamed or copied file came from
+    pub original_path: Option<String>,
+    // Porcelain codes: M, T, A, D, R, C, U, ? for untracked, . for unchanged
+    pub index_status: char,
+    pub worktree_status: char,
+    pub conflicted: bool,
+    pub binary: bool,
+    // From `--numstat`; None when that side is unchanged or binary. Untracked
+    // files aren't diffed by git, so they never have counts.
+    pub staged_lines: Option<LineCounts>,
+    pub unstaged_lines: Option<LineCounts>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct LineCounts {
+    pub added: u32,
+    pub deleted: u32,
+}
+
+impl FileChange {
+    fn new(path: &str, index_status: char, worktree_status: char) -> Self {
+        FileChange {
+            path: path.to_string(),
+            original_path: None,
+            index_status,
+            worktree_status,
+            conflicted: false,
+            binary: false,
+            staged_lines: None,
+            unstaged_lines: None,
+        }
+    }
+
+    pub fn is_staged(&self) -> bool {
+        !self.conflicted && !matches!(self.index_status, '.' | '?')
+    }
+
+    pub fn is_unstaged(&self) -> bool {
+        !self.conflicted && !matches!(self.worktree_status, '.' | '?')
+    }
+}
+
+// Every changed file with its line counts: one status call, then a numstat
+// for the index and one for the working tree when either has changes
+pub fn get_changed_files(runner: &dyn CommandRunner, path: String, env: Environment) -> Result<Vec<FileChange>, KetraError> {
+    let status = run_git(
+        runner,
+        env,
+        &path,
+        &["status", "--porcelain=v2", "-z", "--untracked-files=all"],
+        "Failed to get changed files",
+    )?;
+    let mut changes = parse_status_entries(&status);
+
+    if changes.iter().any(FileChange::is_staged) {
+        let numstat = run_git(runner, env, &path, &["diff", "--cached", "--numstat", "-z", "-M"], "Failed to count staged lines")?;
+        for (file, stat) in parse_numstat(&numstat) {
+            if let Some(change) = changes.iter_mut().find(|c| c.path == file) {
+                change.binary |= stat.is_none();
+                change.staged_lines = stat;
+            }
+        }
+    }
+
+    if changes.iter().any(FileChange::is_unstaged) {
+        let numstat = run_git(runner, env, &path, &["diff", "--numstat", "-z"], "Failed to count changed lines")?;
+        for (file, stat) in parse_numstat(&numstat) {
+            if let Some(change) = changes.iter_mut().find(|c| c.path == file && !c.conflicted) {
+                change.binary |= stat.is_none();
+                change.unstaged_lines = stat;
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+// Parse `git status --porcelain=v2 -z`. Entries are NUL separated:
+//   1 XY sub mH mI mW hH hI path
+//   2 XY sub mH mI mW hH hI Xscore path NUL origPath
+//   u XY sub m1 m2 m3 mW h1 h2 h3 path
+//   ? path
+fn parse_status_entries(stdout: &str) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    let mut entries = stdout.split('\0');
+
+    while let Some(entry) = entries.next() {
+        let Some((kind, rest)) = entry.split_once(' ') else { continue };
+        let mut codes = rest.chars();
+        let (x, y) = (codes.next().unwrap_or('.'), codes.next().unwrap_or('.'));
+
+        let change = match kind {
+            "1" => rest.splitn(8, ' ').nth(7).map(|p| FileChange::new(p, x, y)),
+            "2" => rest.splitn(9, ' ').nth(8).map(|p| FileChange {
+                original_path: entries.next().map(str::to_string),
+                ..FileChange::new(p, x, y)
+            }),
+            "u" => rest.splitn(10, ' ').nth(9).map(|p| FileChange {
+                conflicted: true,
+                ..FileChange::new(p, x, y)
+            }),
+            "?" => Some(FileChange::new(rest, '?', '?')),
+            _ => None,
+        };
+        changes.extend(change);
+    }
+
+    changes
+}
+
+// Parse `git diff --numstat -z` into (path, counts); binary files count as
+// "-\t-" and map to None. A rename leaves the path empty and puts the old
+// and new paths in the next two NUL separated fields.
+pub fn parse_numstat(stdout: &str) -> HashMap<String, Option<LineCounts>> {
+    let mut stats = HashMap::new();
+    let mut fields = stdout.split('\0');
+
+    while let Some(field) = fields.next() {
+        let mut parts = field.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let path = if path.is_empty() {
+            fields.next();
+            fields.next().unwrap_or_default()
+        } else {
+            path
+        };
+
+        let counts = match (added.parse(), deleted.parse()) {
+            (Ok(added), Ok(deleted)) => Some(LineCounts { added, deleted }),
+            _ => None,
+        };
+        stats.insert(path.to_string(), counts);
+    }
+
+    stats
+}
+
+// `git add` also records deletions of the given paths
+pub fn stage_paths(runner: &dyn CommandRunner, path: String, env: Environment, paths: Vec<String>) -> Result<(), KetraError> {
+    let paths = checked_paths(&paths)?;
+    let mut args = vec!["add", "-A", "--"];
+    args.extend(paths);
+    run_git(runner, env, &path, &args, "Failed to stage files").map(|_| ())
+}
+
+// Unlike `git restore --staged`, `git reset` also works before the first commit
+pub fn unstage_paths(runner: &dyn CommandRunner, path: String, env: Environment, paths: Vec<String>) -> Result<(), KetraError> {
+    let paths = checked_paths(&paths)?;
+    let mut args = vec!["reset", "-q", "--"];
+    args.extend(paths);
+    run_git(runner, env, &path, &args, "Failed to unstage files").map(|_| ())
+}
+
+// Stage one hunk of a file's unstaged changes. The hunk is named by its
+// `@@ -a,b +c,d @@` header so a stale view can't stage the wrong lines, and
+// `options` must be the ones the hunk was shown with: context and whitespace
+// change where hunks start and end.
+pub fn stage_hunk(runner: &dyn CommandRunner, path: String, env: Environment, file: String, header: String, options: DiffOptions) -> Result<(), KetraError> {
+    apply_hunk(runner, &path, env, &file, &header, &options, false)
+}
+
+// Take one hunk of a file's staged changes back out of the index
+pub fn unstage_hunk(runner: &dyn CommandRunner, path: String, env: Environment, file: String, header: String, options: DiffOptions) -> Result<(), KetraError> {
+    apply_hunk(runner, &path, env, &file, &header, &options, true)
+}
+
+fn apply_hunk(runner: &dyn CommandRunner, path: &str, env: Environment, file: &str, header: &str, options: &DiffOptions, staged: bool) -> Result<(), KetraError> {
+    checked_paths(&[file.to_string()])?;
+    // `git apply` needs the a/ and b/ prefixes whatever diff.noprefix says
+    let mut diff_args: Vec<String> = ["diff", "--no-color", "--no-ext-diff", "--src-prefix=a/", "--dst-prefix=b/"].map(String::from).to_vec();
+    diff_args.extend(option_args(options));
+    if staged {
+        diff_args.push("--cached".to_string());
+    }
+    diff_args.extend(["--".to_string(), file.to_string()]);
+    let diff_args: Vec<&str> = diff_args.iter().map(String::as_str).collect();
+    let diff = run_git(runner, env, path, &diff_args, "Failed to read the file's changes")?;
+
+    let patch = single_hunk_patch(&diff, header).ok_or_else(|| {
+        KetraError::new(ErrorKind::InvalidInput, format!("'{}' has changed since it was shown. Refresh and try again.", file))
+    })?;
+
+    let mut apply_args = vec!["apply", "--cached", "--whitespace=nowarn"];
+    if options.context == Some(0) {
+        apply_args.push("--unidiff-zero");
+    }
+    if options.ignore_whitespace {
+        apply_args.push("--ignore-whitespace");
+    }
+    if staged {
+        apply_args.push("--reverse");
+    }
+    apply_args.push("-");
+    let spec = env.command("git", &apply_args, Some(path)).stdin(&patch);
+    match runner.output(&spec) {
+        Ok(o) if o.success => Ok(()),
+        Ok(o) => Err(KetraError::git(if staged { "Failed to unstage hunk" } else { "Failed to stage hunk" }, &o)),
+        Err(e) => Err(KetraError::spawn("Failed to execute git apply", e)),
+    }
+}
+
+// The file header of a one-file diff followed by just the hunk starting with
+// `header`; None when no hunk has that header (or the file is binary)
+fn single_hunk_patch(diff: &str, header: &str) -> Option<String> {
+    let lines: Vec<&str> = diff.split_inclusive('\n').collect();
+    let first_hunk = lines.iter().position(|l| l.starts_with("@@"))?;
+    let start = lines.iter().position(|l| l.starts_with("@@") && hunk_header(l) == hunk_header(header))?;
+    let end = lines[start + 1..].iter().position(|l| l.starts_with("@@")).map_or(lines.len(), |i| start + 1 + i);
+
+    let mut patch: String = lines[..first_hunk].concat();
+    patch.extend(lines[start..end].iter().copied());
+    Some(patch)
+}
+
+// "@@ -1,3 +1,4 @@ fn main() {" -> "@@ -1,3 +1,4 @@"; the function context after
+// the ranges is only a hint and is ignored
+fn hunk_header(line: &str) -> &str {
+    let line = line.trim_end();
+    line.strip_prefix("@@")
+        .and_then(|rest| rest.find("@@"))
+        .map_or(line, |i| &line[..i + 4])
+}
+
+// Throw away a file's unstaged changes; an untracked file is deleted
+pub fn discard_file(runner: &dyn CommandRunner, path: String, env: Environment, file: String) -> Result<(), KetraError> {
+    checked_paths(std::slice::from_ref(&file))?;
+    let tracked = run_git(runner, env, &path, &["ls-files", "--error-unmatch", "--", &file], "Failed to discard changes").is_ok();
+    if tracked {
+        run_git(runner, env, &path, &["checkout", "-q", "--", &file], "Failed to discard changes").map(|_| ())
+    } else {
+        run_git(runner, env, &path, &["clean", "-f", "-q", "--", &file], "Failed to delete untracked file").map(|_| ())
+    }
+}
+
+// Paths are passed after `--`, so only empty lists and empty names need refusing
+fn checked_paths(paths: &[String]) -> Result<Vec<&str>, KetraError> {
+    if paths.is_empty() || paths.iter().any(|p| p.is_empty()) {
+        return Err(KetraError::new(ErrorKind::InvalidInput, "No files selected"));
+    }
+    Ok(paths.iter().map(|p| p.as_str()).collect())
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CommitOptions {
+    pub message: String,
+    // Replace the last commit; an empty message keeps its message
+    #[serde(default)]
+    pub amend: bool,
+    // Add a Signed-off-by trailer
+    #[serde(default)]
+    pub sign_off: bool,
+    // None follows the repo's commit.gpgsign setting
+    #[serde(default)]
+    pub signing: Option<Signing>,
+    // A key id, or for SSH the path of a public key; defaults to user.signingkey
+    #[serde(default)]
+    pub signing_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Signing {
+    Gpg,
+    Ssh,
+    Off,
+}
+
+// Commit what is staged, and nothing else; pushing is a separate step
+pub fn commit_staged(runner: &dyn CommandRunner, path: String, env: Environment, options: CommitOptions) -> Result<String, KetraError> {
+    let message = options.message.trim();
+    if message.is_empty() && !options.amend {
+        return Err(KetraError::new(ErrorKind::InvalidInput, "Commit message is empty"));
+    }
+
+    let sign_with_key = options.signing_key.as_ref().map(|key| format!("--gpg-sign={}", key));
+    let mut args = Vec::new();
+    match options.signing {
+        Some(Signing::Gpg) => args.extend(["-c", "gpg.format=openpgp"]),
+        Some(Signing::Ssh) => args.extend(["-c", "gpg.format=ssh"]),
+        _ => {}
+    }
+    args.push("commit");
+    match options.signing {
+        Some(Signing::Off) => args.push("--no-gpg-sign"),
+        Some(_) => args.push(sign_with_key.as_deref().unwrap_or("--gpg-sign")),
+        None => {}
+    }
+    if options.sign_off {
+        args.push("--signoff");
+    }
+    if options.amend {
+        args.push("--amend");
+    }
+    if message.is_empty() {
+        args.push("--no-edit");
+    } else {
+        args.extend(["-m", message]);
+    }
+
+    match runner.output(&env.command("git", &args, Some(&path))) {
+        Ok(o) if o.success => {}
+        Ok(o) => {
+            let combined = o.combined();
+            if combined.contains("nothing to commit") || combined.contains("no changes added to commit") {
+                return Err(KetraError::failed("Nothing is staged to commit").with_stderr(combined));
+            }
+            return Err(KetraError::git("Commit failed", &o));
+        }
+        Err(e) => return Err(KetraError::spawn("Failed to execute git commit", e)),
+    }
+
+    let hash = run_git(runner, env, &path, &["rev-parse", "--short", "HEAD"], "Failed to read the new commit")?;
+    Ok(format!("Committed {}", hash.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    const REPO: &str = "/home/dev/ketra/app";
+
+    #[test]
+    fn parses_every_kind_of_status_entry() {
+        let stdout = [
+            "1 .M N... 100644 100644 100644 aaa aaa src/main.rs",
+            "1 MM N... 100644 100644 100644 aaa bbb with space.txt",
+            "2 R. N... 100644 100644 100644 aaa aaa R90 src/new.rs",
+            "src/old.rs",
+            "u UU N... 100644 100644 100644 100644 aaa bbb ccc README.md",
+            "? notes/todo.md",
+            "",
+        ]
+        .join("\0");
+
+        let changes = parse_status_entries(&stdout);
+        let summary: Vec<(&str, char, char)> = changes.iter().map(|c| (c.path.as_str(), c.index_status, c.worktree_status)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("src/main.rs", '.', 'M'),
+                ("with space.txt", 'M', 'M'),
+                ("src/new.rs", 'R', '.'),
+                ("README.md", 'U', 'U'),
+                ("notes/todo.md", '?', '?'),
+            ]
+        );
+        assert_eq!(changes[2].original_path.as_deref(), Some("src/old.rs"));
+        assert!(changes[3].conflicted && !changes[3].is_staged() && !changes[3].is_unstaged());
+        assert!(!changes[4].is_staged() && !changes[4].is_unstaged());
+    }
+
+    #[test]
+    fn parses_numstat_with_renames_and_binaries() {
+        let stats = parse_numstat("3\t1\tsrc/main.rs\0-\t-\tlogo.png\x000\t0\t\0src/old.rs\0src/new.rs\0");
+        assert_eq!(stats["src/main.rs"], Some(LineCounts { added: 3, deleted: 1 }));
+        assert_eq!(stats["logo.png"], None);
+        assert_eq!(stats["src/new.rs"], Some(LineCounts { added: 0, deleted: 0 }));
+        assert!(!stats.contains_key("src/old.rs"));
+    }
+
+    #[test]
+    fn changed_files_combine_status_and_line_counts() {
+        let runner = FakeRunner::new()
+            .on(
+                "status --porcelain=v2",
+                CommandOutput::ok("1 MM N... 100644 100644 100644 a b src/main.rs\x001 .M N... 100644 100644 100644 a a logo.png\0? new.txt\0"),
+            )
+            .on("diff --cached --numstat", CommandOutput::ok("2\t0\tsrc/main.rs\0"))
+            .on("diff --numstat", CommandOutput::ok("1\t4\tsrc/main.rs\0-\t-\tlogo.png\0"));
+
+        let changes = get_changed_files(&runner, REPO.to_string(), Environment::Wsl).unwrap();
+        assert_eq!(changes[0].staged_lines, Some(LineCounts { added: 2, deleted: 0 }));
+        assert_eq!(changes[0].unstaged_lines, Some(LineCounts { added: 1, deleted: 4 }));
+        assert!(!changes[0].binary);
+        assert!(changes[1].binary);
+        assert_eq!((changes[1].staged_lines, changes[1].unstaged_lines), (None, None));
+        assert_eq!(changes[2].index_status, '?');
+        assert_eq!(runner.calls().len(), 3);
+    }
+
+    #[test]
+    fn clean_repos_skip_the_numstat_calls() {
+        let runner = FakeRunner::new().on("status --porcelain=v2", CommandOutput::ok("? scratch.txt\0"));
+
+        assert_eq!(get_changed_files(&runner, REPO.to_string(), Environment::Wsl).unwrap().len(), 1);
+        assert!(!runner.ran("--numstat"));
+    }
+
+    #[test]
+    fn changed_files_outside_a_repo() {
+        let runner = FakeRunner::new().on("status", CommandOutput::fail("fatal: not a git repository (or any of the parent directories): .git"));
+
+        let err = get_changed_files(&runner, REPO.to_string(), Environment::Wsl).unwrap_err();
+        assert!(err.is(&crate::error::ErrorKind::NotARepo));
+    }
+
+    #[test]
+    fn changed_files_of_a_real_repo() {
+        use crate::exec::system;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("ketra-changes-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let run = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            let output = system().output(&Environment::host().command("git", &full, Some(&path))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        };
+
+        run(&["init", "-q", "-b", "main"]);
+        fs::write(dir.join("old name.txt"), "one\ntwo\nthree\n").unwrap();
+        fs::write(dir.join("logo.png"), [0u8, 1, 2, 0, 255]).unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "init"]);
+
+        run(&["mv", "old name.txt", "new name.txt"]);
+        fs::write(dir.join("new name.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+        fs::write(dir.join("logo.png"), [0u8, 9, 9, 0]).unwrap();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::write(dir.join("notes").join("todo.md"), "x\n").unwrap();
+
+        let changes = get_changed_files(system(), path.clone(), Environment::host()).unwrap();
+        let summary: Vec<(&str, char, char)> = changes.iter().map(|c| (c.path.as_str(), c.index_status, c.worktree_status)).collect();
+        assert_eq!(summary, [("logo.png", '.', 'M'), ("new name.txt", 'R', 'M'), ("notes/todo.md", '?', '?')]);
+
+        assert!(changes[0].binary);
+        assert_eq!(changes[1].original_path.as_deref(), Some("old name.txt"));
+        assert_eq!(changes[1].staged_lines, Some(LineCounts { added: 0, deleted: 0 }));
+        assert_eq!(changes[1].unstaged_lines, Some(LineCounts { added: 1, deleted: 0 }));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    const TWO_HUNKS: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,4 @@ use std::io;
++use std::fs;
+ fn main() {
+     run();
+ }
+@@ -20,2 +21,2 @@ fn run() {
+-    old();
++    new();
+\\ No newline at end of file
+";
+
+    #[test]
+    fn builds_a_patch_for_one_hunk() {
+        let patch = single_hunk_patch(TWO_HUNKS, "@@ -20,2 +21,2 @@").unwrap();
+        assert!(patch.starts_with("diff --git a/src/main.rs b/src/main.rs\n"));
+        assert!(patch.contains("+++ b/src/main.rs\n@@ -20,2 +21,2 @@ fn run() {\n"));
+        assert!(!patch.contains("use std::fs"));
+        assert!(patch.ends_with("\\ No newline at end of file\n"));
+
+        let patch = single_hunk_patch(TWO_HUNKS, "@@ -1,3 +1,4 @@ use std::io;").unwrap();
+        assert!(patch.ends_with("     run();\n }\n"));
+
+        assert_eq!(single_hunk_patch(TWO_HUNKS, "@@ -5,1 +5,1 @@"), None);
+        assert_eq!(single_hunk_patch("Binary files a/logo.png and b/logo.png differ\n", "@@ -1 +1 @@"), None);
+    }
+
+    #[test]
+    fn stale_hunks_are_refused() {
+        let runner = FakeRunner::new().on("git diff", CommandOutput::ok(TWO_HUNKS));
+        let err = stage_hunk(&runner, REPO.to_string(), Environment::Wsl, "src/main.rs".to_string(), "@@ -9,1 +9,1 @@".to_string(), DiffOptions::default()).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+        assert!(!runner.ran("git apply"));
+    }
+
+    #[test]
+    fn unstaging_a_hunk_reverses_it_out_of_the_index() {
+        let runner = FakeRunner::new().on("git diff", CommandOutput::ok(TWO_HUNKS));
+        unstage_hunk(&runner, REPO.to_string(), Environment::Wsl, "src/main.rs".to_string(), "@@ -1,3 +1,4 @@".to_string(), DiffOptions::default()).unwrap();
+
+        let calls = runner.calls();
+        assert!(calls[0].args.ends_with(&["--cached".to_string(), "--".to_string(), "src/main.rs".to_string()]));
+        assert_eq!(calls[1].args[4..], ["apply", "--cached", "--whitespace=nowarn", "--reverse", "-"]);
+        assert!(calls[1].stdin.as_ref().unwrap().contains("+use std::fs;"));
+    }
+
+    #[test]
+    fn hunks_are_found_with_the_options_they_were_shown_with() {
+        let runner = FakeRunner::new().on("git diff", CommandOutput::ok(TWO_HUNKS));
+        let options = DiffOptions { context: Some(0), ignore_whitespace: true, ..Default::default() };
+        stage_hunk(&runner, REPO.to_string(), Environment::Wsl, "src/main.rs".to_string(), "@@ -20,2 +21,2 @@".to_string(), options).unwrap();
+
+        let calls = runner.calls();
+        assert!(runner.ran("--dst-prefix=b/ --ignore-all-space --unified=0 -- src/main.rs"));
+        assert_eq!(calls[1].args[4..], ["apply", "--cached", "--whitespace=nowarn", "--unidiff-zero", "--ignore-whitespace", "-"]);
+    }
+
+    #[test]
+    fn paths_follow_a_double_dash() {
+        let runner = FakeRunner::new();
+        stage_paths(&runner, REPO.to_string(), Environment::Wsl, vec!["--force".to_string(), "a b.txt".to_string()]).unwrap();
+        unstage_paths(&runner, REPO.to_string(), Environment::Wsl, vec!["-p".to_string()]).unwrap();
+        assert_eq!(runner.calls()[0].args[4..], ["add", "-A", "--", "--force", "a b.txt"]);
+        assert_eq!(runner.calls()[1].args[4..], ["reset", "-q", "--", "-p"]);
+
+        let err = stage_paths(&runner, REPO.to_string(), Environment::Wsl, Vec::new()).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn discard_deletes_only_untracked_files() {
+        let runner = FakeRunner::new().on("ls-files", CommandOutput::fail("error: pathspec 'tmp.log' did not match"));
+        discard_file(&runner, REPO.to_string(), Environment::Wsl, "tmp.log".to_string()).unwrap();
+        assert!(runner.ran("--exec git clean -f -q -- tmp.log"));
+
+        let runner = FakeRunner::new();
+        discard_file(&runner, REPO.to_string(), Environment::Wsl, "src/main.rs".to_string()).unwrap();
+        assert!(runner.ran("--exec git checkout -q -- src/main.rs"));
+        assert!(!runner.ran("clean"));
+    }
+
+    fn commit_args(options: CommitOptions) -> Vec<String> {
+        let runner = FakeRunner::new().on("rev-parse --short HEAD", CommandOutput::ok("abc1234\n"));
+        assert_eq!(commit_staged(&runner, REPO.to_string(), Environment::Wsl, options).unwrap(), "Committed abc1234");
+        runner.calls()[0].args[4..].to_vec()
+    }
+
+    #[test]
+    fn commit_options_map_to_flags() {
+        let message = || "feat: add login".to_string();
+        assert_eq!(commit_args(CommitOptions { message: message(), ..Default::default() }), ["commit", "-m", "feat: add login"]);
+        assert_eq!(
+            commit_args(CommitOptions { message: message(), sign_off: true, signing: Some(Signing::Gpg), ..Default::default() }),
+            ["-c", "gpg.format=openpgp", "commit", "--gpg-sign", "--signoff", "-m", "feat: add login"]
+        );
+        assert_eq!(
+            commit_args(CommitOptions {
+                message: message(),
+                signing: Some(Signing::Ssh),
+                signing_key: Some("~/.ssh/id_ed25519.pub".to_string()),
+                ..Default::default()
+            }),
+            ["-c", "gpg.format=ssh", "commit", "--gpg-sign=~/.ssh/id_ed25519.pub", "-m", "feat: add login"]
+        );
+        assert_eq!(
+            commit_args(CommitOptions { amend: true, signing: Some(Signing::Off), ..Default::default() }),
+            ["commit", "--no-gpg-sign", "--amend", "--no-edit"]
+        );
+    }
+
+    #[test]
+    fn commit_needs_a_message_and_staged_changes() {
+        let runner = FakeRunner::new();
+        let err = commit_staged(&runner, REPO.to_string(), Environment::Wsl, CommitOptions { message: "  ".to_string(), ..Default::default() }).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+        assert!(runner.calls().is_empty());
+
+        let runner = FakeRunner::new().on("git commit", CommandOutput { success: false, stdout: "nothing to commit, working tree clean\n".to_string(), stderr: String::new() });
+        let err = commit_staged(&runner, REPO.to_string(), Environment::Wsl, CommitOptions { message: "wip".to_string(), ..Default::default() }).unwrap_err();
+        assert_eq!(err.message, "Nothing is staged to commit");
+    }
+
+    #[test]
+    fn stages_and_commits_single_hunks_of_a_real_repo() {
+        use crate::exec::system;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("ketra-hunks-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let git = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com"];
+            full.extend_from_slice(args);
+            let output = system().output(&Environment::host().command("git", &full, Some(&path))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+            output.stdout
+        };
+
+        git(&["init", "-q", "-b", "main"]);
+        git(&["config", "commit.gpgsign", "false"]);
+        git(&["config", "diff.noprefix", "true"]);
+        git(&["config", "user.name", "Ketra"]);
+        git(&["config", "user.email", "ketra@example.com"]);
+        let lines: Vec<String> = (1..=30).map(|n| format!("line {}\n", n)).collect();
+        fs::write(dir.join("file.txt"), lines.concat()).unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        // Two changes far enough apart to be separate hunks
+        let mut changed = lines.clone();
+        changed[1] = "line 2 changed\n".to_string();
+        changed[27] = "line 28 changed\n".to_string();
+        fs::write(dir.join("file.txt"), changed.concat()).unwrap();
+        fs::write(dir.join("stray.txt"), "don't commit me\n").unwrap();
+
+        let diff = git(&["diff"]);
+        let headers: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(headers.len(), 2);
+
+        stage_hunk(system(), path.clone(), Environment::host(), "file.txt".to_string(), headers[1].to_string(), DiffOptions::default()).unwrap();
+        let staged = git(&["diff", "--cached"]);
+        assert!(staged.contains("+line 28 changed") && !staged.contains("+line 2 changed"));
+
+        unstage_hunk(system(), path.clone(), Environment::host(), "file.txt".to_string(), headers[1].to_string(), DiffOptions::default()).unwrap();
+        assert_eq!(git(&["diff", "--cached"]), "");
+
+        // A hunk shown without context only matches a diff without context
+        let zero = DiffOptions { context: Some(0), ..Default::default() };
+        let diff = git(&["diff", "-U0"]);
+        let first = diff.lines().find(|l| l.starts_with("@@")).unwrap();
+        assert!(stage_hunk(system(), path.clone(), Environment::host(), "file.txt".to_string(), first.to_string(), DiffOptions::default()).is_err());
+        stage_hunk(system(), path.clone(), Environment::host(), "file.txt".to_string(), first.to_string(), zero).unwrap();
+        let options = CommitOptions { message: "Change line 2".to_string(), sign_off: true, ..Default::default() };
+        commit_staged(system(), path.clone(), Environment::host(), options).unwrap();
+
+        let body = git(&["log", "-1", "--format=%B"]);
+        assert!(body.starts_with("Change line 2\n") && body.contains("Signed-off-by: Ketra <ketra@example.com>"));
+        let committed = git(&["show", "--format=", "HEAD"]);
+        assert!(committed.contains("+line 2 changed") && !committed.contains("line 28 changed"));
+        assert!(git(&["status", "--porcelain"]).contains("?? stray.txt"));
+
+        discard_file(system(), path.clone(), Environment::host(), "file.txt".to_string()).unwrap();
+        discard_file(system(), path.clone(), Environment::host(), "stray.txt".to_string()).unwrap();
+        assert_eq!(git(&["status", "--porcelain"]), "");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
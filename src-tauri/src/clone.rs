@@ -0,0 +1,342 @@
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
+use crate::git::validate_branch_name;
+use crate::operations::{self, tracked, OperationKind};
+use crate::progress::{run_git_with_progress, ProgressSink};
+use crate::repo_url::RepoUrl;
+use crate::roots::ProjectRoot;
+use crate::utils::{get_default_folder, validate_project_name};
+use serde::{Deserialize, Serialize};
+
+// How far to look for a free "<name>-N" when the name is taken
+const MAX_SUGGESTION: u32 = 20;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CloneOptions {
+    // Folder name; taken from the URL when unset
+    pub name: Option<String>,
+    // Folder to clone into, may start with `~`; the environment's first root when unset
+    pub root: Option<String>,
+    // Branch or tag to check out
+    pub reference: Option<String>,
+    // Only fetch this many commits of history
+    pub depth: Option<u32>,
+    pub submodules: bool,
+    // Partial clone filter: "blob:none", "blob:limit=<size>" or "tree:<depth>"
+    pub filter: Option<String>,
+    // Only check out these directories (cone-mode sparse checkout)
+    pub sparse_paths: Vec<String>,
+}
+
+// Where a clone would go, checked before anything is downloaded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClonePlan {
+    // The URL git will clone, after expanding shorthands and browser URLs
+    pub url: String,
+    pub name: String,
+    pub root: String,
+    pub path: String,
+    // Something is already at `path`, so the clone would be refused
+    pub exists: bool,
+    // A free name to offer instead when `exists`
+    pub suggestion: Option<String>,
+}
+
+pub fn plan_clone(runner: &dyn CommandRunner, env: Environment, repo_url: &str, options: &CloneOptions) -> Result<ClonePlan, KetraError> {
+    let repo = RepoUrl::parse(repo_url)?;
+    let name = match &options.name {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => repo.name,
+    };
+    validate_project_name(&name)?;
+
+    let root = match &options.root {
+        Some(root) => ProjectRoot::new(env, root).resolve(runner)?,
+        None => get_default_folder(runner, env)?,
+    };
+
+    let path = env.join(&root, &name);
+    let exists = env.dir_exists(runner, &path);
+    let suggestion = if exists {
+        (2..=MAX_SUGGESTION)
+            .map(|n| format!("{}-{}", name, n))
+            .find(|candidate| !env.dir_exists(runner, &env.join(&root, candidate)))
+    } else {
+        None
+    };
+
+    Ok(ClonePlan {
+        url: repo.clone_url,
+        name,
+        root,
+        path,
+        exists,
+        suggestion,
+    })
+}
+
+// Clone into a new folder and return its name
+pub async fn git_clone(
+    runner: &dyn CommandRunner,
+    env: Environment,
+    repo_url: String,
+    options: CloneOptions,
+    progress: &dyn ProgressSink,
+) -> Result<String, KetraError> {
+    let plan = plan_clone(runner, env, &repo_url, &options)?;
+    if plan.exists {
+        let mut message = format!("'{}' already exists in {}.", plan.name, plan.root);
+        if let Some(suggestion) = &plan.suggestion {
+            message.push_str(&format!(" Clone it as '{}' instead?", suggestion));
+        }
+        return Err(KetraError::new(ErrorKind::AlreadyExists, message));
+    }
+    let args = clone_args(&plan.url, &plan.name, &options)?;
+    let sparse_paths = checked_sparse_paths(&options.sparse_paths)?;
+
+    env.create_dir_all(runner, &plan.root)?;
+    let operation = operations::begin(progress.operation_id(), OperationKind::Clone, &plan.url, env)?;
+    operation.cleanup_on_cancel(&plan.path);
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let cloned = match run_git_with_progress(runner, env, Some(&plan.root), &args, progress) {
+        Ok(o) if o.success => Ok(()),
+        Ok(o) => Err(KetraError::git("Git clone failed", &o)),
+        Err(e) => Err(KetraError::spawn("Failed to execute git clone", e)),
+    };
+
+    let result = cloned.and_then(|_| {
+        if sparse_paths.is_empty() {
+            return Ok(plan.name.clone());
+        }
+        let mut sparse = vec!["sparse-checkout", "set", "--"];
+        sparse.extend(sparse_paths.iter().map(String::as_str));
+        match runner.output(&tracked(env.command("git", &sparse, Some(&plan.path)), env, progress.operation_id())) {
+            Ok(o) if o.success => Ok(plan.name.clone()),
+            Ok(o) => Err(KetraError::git("Sparse checkout failed", &o)),
+            Err(e) => Err(KetraError::spawn("Failed to execute git sparse-checkout", e)),
+        }
+    });
+    operation.settle(runner, result)
+}
+
+// The `git clone` arguments for the options; the URL always follows `--`
+fn clone_args(repo_url: &str, name: &str, options: &CloneOptions) -> Result<Vec<String>, KetraError> {
+    let mut args = vec!["clone".to_string()];
+
+    if let Some(reference) = &options.reference {
+        validate_branch_name(reference)?;
+        args.extend(["--branch".to_string(), reference.clone()]);
+    }
+    if let Some(depth) = options.depth {
+        if depth == 0 {
+            return Err(KetraError::new(ErrorKind::InvalidInput, "Clone depth must be at least 1"));
+        }
+        args.extend(["--depth".to_string(), depth.to_string()]);
+    }
+    if options.submodules {
+        args.push("--recurse-submodules".to_string());
+        if options.depth.is_some() {
+            args.push("--shallow-submodules".to_string());
+        }
+    }
+    if let Some(filter) = &options.filter {
+        if !is_valid_filter(filter) {
+            return Err(KetraError::new(ErrorKind::InvalidInput, format!("Unsupported clone filter '{}'", filter)));
+        }
+        args.push(format!("--filter={}", filter));
+    }
+    if !options.sparse_paths.is_empty() {
+        args.push("--sparse".to_string());
+    }
+
+    args.extend(["--".to_string(), repo_url.to_string(), name.to_string()]);
+    Ok(args)
+}
+
+// The filters GitHub and GitLab serve: no blobs, blobs up to a size, or trees
+// up to a depth
+fn is_valid_filter(filter: &str) -> bool {
+    if filter == "blob:none" {
+        return true;
+    }
+    if let Some(depth) = filter.strip_prefix("tree:") {
+        return depth.parse::<u32>().is_ok();
+    }
+    if let Some(limit) = filter.strip_prefix("blob:limit=") {
+        let digits = limit.trim_end_matches(['k', 'm', 'g']);
+        return !digits.is_empty() && limit.len() - digits.len() <= 1 && digits.chars().all(|c| c.is_ascii_digit());
+    }
+    false
+}
+
+// Sparse paths are directories relative to the repo root
+fn checked_sparse_paths(paths: &[String]) -> Result<Vec<String>, KetraError> {
+    paths
+        .iter()
+        .map(|path| path.trim().trim_matches('/').to_string())
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            if path.starts_with('-') || path.split('/').any(|part| part == ".." || part == ".") || path.contains('\\') {
+                return Err(KetraError::new(ErrorKind::InvalidInput, format!("Invalid sparse checkout path '{}'", path)));
+            }
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+    use crate::progress::record::Recorder;
+
+    fn options() -> CloneOptions {
+        CloneOptions {
+            root: Some("/home/dev/code".to_string()),
+            ..CloneOptions::default()
+        }
+    }
+
+    #[test]
+    fn builds_clone_arguments() {
+        let url = "https://github.com/ketra/app.git";
+        let cases = [
+            (CloneOptions::default(), vec!["clone", "--", url, "app"]),
+            (
+                CloneOptions { reference: Some("v1.2.0".into()), depth: Some(1), ..CloneOptions::default() },
+                vec!["clone", "--branch", "v1.2.0", "--depth", "1", "--", url, "app"],
+            ),
+            (
+                CloneOptions { submodules: true, depth: Some(5), ..CloneOptions::default() },
+                vec!["clone", "--depth", "5", "--recurse-submodules", "--shallow-submodules", "--", url, "app"],
+            ),
+            (
+                CloneOptions { filter: Some("blob:none".into()), sparse_paths: vec!["docs".into()], ..CloneOptions::default() },
+                vec!["clone", "--filter=blob:none", "--sparse", "--", url, "app"],
+            ),
+        ];
+        for (options, expected) in cases {
+            assert_eq!(clone_args(url, "app", &options).unwrap(), expected, "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn rejects_option_like_values() {
+        let invalid = [
+            CloneOptions { reference: Some("--upload-pack=x".into()), ..CloneOptions::default() },
+            CloneOptions { depth: Some(0), ..CloneOptions::default() },
+            CloneOptions { filter: Some("sparse:oid=HEAD".into()), ..CloneOptions::default() },
+            CloneOptions { filter: Some("blob:limit=1mm".into()), ..CloneOptions::default() },
+        ];
+        for options in invalid {
+            assert!(clone_args("url", "app", &options).unwrap_err().is(&ErrorKind::InvalidInput), "{:?}", options);
+        }
+
+        assert!(is_valid_filter("tree:0") && is_valid_filter("blob:limit=512k") && is_valid_filter("blob:limit=1048576"));
+        assert_eq!(checked_sparse_paths(&["/docs/".into(), " ".into(), "src/app".into()]).unwrap(), ["docs", "src/app"]);
+        for path in ["--all", "../outside", "a/./b", "a\\b"] {
+            assert!(checked_sparse_paths(&[path.to_string()]).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn collisions_suggest_a_free_name() {
+        let runner = FakeRunner::new()
+            .on("test -d /home/dev/code/app-3", CommandOutput::fail(""))
+            .on("test -d /home/dev/code/app-", CommandOutput::ok(""))
+            .on("test -d /home/dev/code/app", CommandOutput::ok(""));
+
+        let plan = plan_clone(&runner, Environment::Wsl, "https://github.com/ketra/app/tree/main", &options()).unwrap();
+        assert_eq!(plan.url, "https://github.com/ketra/app.git");
+        assert_eq!(plan.path, "/home/dev/code/app");
+        assert!(plan.exists);
+        assert_eq!(plan.suggestion.as_deref(), Some("app-3"));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_clone_over_an_existing_folder() {
+        let runner = FakeRunner::new().on("test -d /home/dev/code/app-2", CommandOutput::fail(""));
+
+        let err = git_clone(&runner, Environment::Wsl, "https://github.com/ketra/app".into(), options(), &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::AlreadyExists));
+        assert!(err.message.contains("'app-2'"));
+        assert!(!runner.ran("git clone"));
+    }
+
+    #[tokio::test]
+    async fn clones_under_a_new_name_with_sparse_paths() {
+        let runner = FakeRunner::new().on("test -d", CommandOutput::fail(""));
+        let options = CloneOptions {
+            name: Some("app-fork".into()),
+            sparse_paths: vec!["docs".into()],
+            ..options()
+        };
+
+        let name = git_clone(&runner, Environment::Wsl, "gh:ketra/app".into(), options, &Recorder::default()).await.unwrap();
+        assert_eq!(name, "app-fork");
+        assert!(runner.ran(" git clone --progress --sparse -- https://github.com/ketra/app.git app-fork"));
+        assert!(runner.ran("--cd /home/dev/code/app-fork --exec setsid -w sh -c"));
+        assert!(runner.ran(" git sparse-checkout set -- docs"));
+    }
+
+    #[tokio::test]
+    async fn clone_urls_cannot_become_options() {
+        let runner = FakeRunner::new().on("test -d", CommandOutput::fail(""));
+        let err = git_clone(&runner, Environment::Wsl, "--upload-pack=touch /tmp/pwned".into(), options(), &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+        assert!(!runner.ran("git clone"));
+    }
+
+    #[tokio::test]
+    async fn clones_a_real_repo_shallow_and_sparse() {
+        use crate::exec::system;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("ketra-clone-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let source = dir.join("source");
+        fs::create_dir_all(source.join("docs")).unwrap();
+        fs::create_dir_all(source.join("src")).unwrap();
+        fs::write(source.join("docs/guide.md"), "guide").unwrap();
+        fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let env = Environment::host();
+        let source_path = source.to_string_lossy().to_string();
+        let git = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            let output = system().output(&env.command("git", &full, Some(&source_path))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "first"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "second"]);
+
+        let options = CloneOptions {
+            root: Some(dir.to_string_lossy().to_string()),
+            name: Some("copy".into()),
+            depth: Some(1),
+            sparse_paths: vec!["docs".into()],
+            ..CloneOptions::default()
+        };
+        let url = format!("file://{}", source_path);
+        git_clone(system(), env, url.clone(), options.clone(), &Recorder::default()).await.unwrap();
+
+        let copy = dir.join("copy");
+        assert!(copy.join("docs/guide.md").exists());
+        assert!(!copy.join("src").exists());
+        let log = system().output(&env.command("git", &["rev-list", "--count", "HEAD"], Some(&copy.to_string_lossy()))).unwrap();
+        assert_eq!(log.stdout.trim(), "1");
+
+        // The same name again is caught before git runs
+        let err = git_clone(system(), env, url, options, &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::AlreadyExists));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,603 @@
+use crate::environment::Environment;
+use crate::exec::CommandRunner;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+
+// What a project is built with, read from its manifests
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    // Primary language first
+    pub languages: Vec<String>,
+    pub frameworks: Vec<String>,
+    pub package_managers: Vec<String>,
+    pub toolchains: Vec<Toolchain>,
+}
+
+// A pinned tool version, e.g. `.nvmrc` -> node 20
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toolchain {
+    pub tool: String,
+    pub version: String,
+    pub file: String,
+}
+
+// Files read from the project root (anything else is only listed)
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "src-tauri/Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "requirements.txt",
+    "go.mod",
+    "rust-toolchain.toml",
+    "rust-toolchain",
+    ".nvmrc",
+    ".node-version",
+    ".python-version",
+];
+
+// Manifests are small; don't slurp a huge file someone named package.json
+const MAX_MANIFEST_BYTES: u64 = 64 * 1024;
+
+// (dependency, framework) pairs per ecosystem
+const RUST_FRAMEWORKS: &[(&str, &str)] = &[
+    ("axum", "Axum"),
+    ("actix-web", "Actix Web"),
+    ("rocket", "Rocket"),
+    ("tauri", "Tauri"),
+    ("leptos", "Leptos"),
+    ("bevy", "Bevy"),
+];
+
+const NODE_FRAMEWORKS: &[(&str, &str)] = &[
+    ("next", "Next.js"),
+    ("nuxt", "Nuxt"),
+    ("@remix-run/react", "Remix"),
+    ("@sveltejs/kit", "SvelteKit"),
+    ("astro", "Astro"),
+    ("@angular/core", "Angular"),
+    ("vue", "Vue"),
+    ("react", "React"),
+    ("svelte", "Svelte"),
+    ("@tauri-apps/api", "Tauri"),
+    ("electron", "Electron"),
+    ("@nestjs/core", "NestJS"),
+    ("express", "Express"),
+    ("vite", "Vite"),
+];
+
+const PYTHON_FRAMEWORKS: &[(&str, &str)] = &[
+    ("django", "Django"),
+    ("flask", "Flask"),
+    ("fastapi", "FastAPI"),
+];
+
+const GO_FRAMEWORKS: &[(&str, &str)] = &[
+    ("github.com/gin-gonic/gin", "Gin"),
+    ("github.com/labstack/echo", "Echo"),
+    ("github.com/gofiber/fiber", "Fiber"),
+    ("github.com/go-chi/chi", "Chi"),
+];
+
+// Top-level file names plus the contents of the manifests present
+pub struct Snapshot {
+    pub entries: Vec<String>,
+    pub files: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    fn has(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e == name)
+    }
+
+    fn file(&self, name: &str) -> Option<&str> {
+        self.files.get(name).map(|s| s.as_str())
+    }
+}
+
+pub fn detect_project(runner: &dyn CommandRunner, env: Environment, path: &str) -> Option<ProjectInfo> {
+    let snapshot = if env.is_local() { snapshot_local(env, path)? } else { snapshot_wsl(runner, env, path)? };
+    Some(detect(&snapshot))
+}
+
+fn snapshot_local(env: Environment, path: &str) -> Option<Snapshot> {
+    let entries = fs::read_dir(path)
+        .ok()?
+        .flatten()
+        .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+        .collect();
+
+    let mut files = BTreeMap::new();
+    for name in MANIFESTS {
+        let file_path = env.join(path, &name.replace('/', &env.separator().to_string()));
+        let Ok(file) = fs::File::open(&file_path) else { continue };
+        let mut content = String::new();
+        if file.take(MAX_MANIFEST_BYTES).read_to_string(&mut content).is_ok() {
+            files.insert(name.to_string(), content);
+        }
+    }
+
+    Some(Snapshot { entries, files })
+}
+
+// One WSL call: list the folder, then print each manifest that exists
+// behind a record separator (0x1e) and its name
+const SNAPSHOT_SCRIPT: &str = r#"cd "$1" || exit 1
+max="$2"
+shift 2
+ls -A
+for f; do
+  if [ -f "$f" ]; then printf '\036%s\n' "$f"; head -c "$max" "$f"; fi
+done"#;
+
+fn snapshot_wsl(runner: &dyn CommandRunner, env: Environment, path: &str) -> Option<Snapshot> {
+    let max = MAX_MANIFEST_BYTES.to_string();
+    let mut args = vec!["-c", SNAPSHOT_SCRIPT, "sh", path, &max];
+    args.extend_from_slice(MANIFESTS);
+
+    let output = runner.output(&env.command("sh", &args, None)).ok()?;
+    if !output.success {
+        return None;
+    }
+    Some(parse_snapshot(&output.stdout))
+}
+
+fn parse_snapshot(stdout: &str) -> Snapshot {
+    let mut records = stdout.split('\u{1e}');
+    let entries = records.next().unwrap_or("").lines().map(|l| l.to_string()).filter(|l| !l.is_empty()).collect();
+
+    let mut files = BTreeMap::new();
+    for record in records {
+        let (name, content) = record.split_once('\n').unwrap_or((record, ""));
+        files.insert(name.to_string(), content.to_string());
+    }
+
+    Snapshot { entries, files }
+}
+
+pub fn detect(snapshot: &Snapshot) -> ProjectInfo {
+    let mut info = ProjectInfo::default();
+
+    // Checked in order of how likely the manifest is to describe the main
+    // code: a Rust crate with a package.json for tooling is still Rust
+    if let Some(cargo) = snapshot.file("Cargo.toml") {
+        push(&mut info.languages, "Rust");
+        push(&mut info.package_managers, "cargo");
+        add_frameworks(&mut info, &toml_dependencies(cargo), RUST_FRAMEWORKS);
+    }
+
+    if let Some(go_mod) = snapshot.file("go.mod") {
+        push(&mut info.languages, "Go");
+        push(&mut info.package_managers, "go modules");
+        add_frameworks(&mut info, &go_requirements(go_mod), GO_FRAMEWORKS);
+    }
+
+    let pyproject = snapshot.file("pyproject.toml");
+    let requirements = snapshot.file("requirements.txt");
+    if pyproject.is_some() || requirements.is_some() || snapshot.has("setup.py") || snapshot.has("Pipfile") {
+        push(&mut info.languages, "Python");
+        let pm = if snapshot.has("uv.lock") {
+            "uv"
+        } else if snapshot.has("poetry.lock") || pyproject.is_some_and(|p| p.contains("[tool.poetry]")) {
+            "poetry"
+        } else if snapshot.has("Pipfile") {
+            "pipenv"
+        } else if snapshot.has("pdm.lock") {
+            "pdm"
+        } else {
+            "pip"
+        };
+        push(&mut info.package_managers, pm);
+
+        let mut deps = pyproject.map(python_dependencies).unwrap_or_default();
+        deps.extend(requirements.map(requirement_names).unwrap_or_default());
+        add_frameworks(&mut info, &deps, PYTHON_FRAMEWORKS);
+        if snapshot.has("manage.py") {
+            push(&mut info.frameworks, "Django");
+        }
+    }
+
+    if snapshot.entries.iter().any(|e| e.ends_with(".sln") || e.ends_with(".csproj")) {
+        push(&mut info.languages, "C#");
+        push(&mut info.package_managers, "dotnet");
+    }
+
+    if let Some(package_json) = snapshot.file("package.json") {
+        let json: serde_json::Value = serde_json::from_str(package_json).unwrap_or_default();
+        let deps = node_dependencies(&json);
+
+        let typescript = snapshot.has("tsconfig.json") || deps.iter().any(|d| d == "typescript");
+        push(&mut info.languages, if typescript { "TypeScript" } else { "JavaScript" });
+        push(&mut info.package_managers, node_package_manager(&json, snapshot));
+        add_frameworks(&mut info, &deps, NODE_FRAMEWORKS);
+    }
+
+    // Tauri apps keep the Rust side in src-tauri/
+    if let Some(cargo) = snapshot.file("src-tauri/Cargo.toml") {
+        push(&mut info.languages, "Rust");
+        push(&mut info.package_managers, "cargo");
+        add_frameworks(&mut info, &toml_dependencies(cargo), RUST_FRAMEWORKS);
+    }
+
+    info.toolchains = toolchains(snapshot);
+    info
+}
+
+fn push(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
+
+fn add_frameworks(info: &mut ProjectInfo, deps: &[String], known: &[(&str, &str)]) {
+    for (dep, framework) in known {
+        if deps.iter().any(|d| d.eq_ignore_ascii_case(dep)) {
+            push(&mut info.frameworks, framework);
+        }
+    }
+}
+
+// Crate names from every `[*dependencies]` table, including the
+// `[dependencies.foo]` form
+fn toml_dependencies(text: &str) -> Vec<String> {
+    let mut deps = Vec::new();
+    let mut in_deps = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let header = header.trim_matches(|c| c == '[' || c == ']');
+            match header.split_once("dependencies.") {
+                Some((_, name)) => {
+                    deps.push(name.to_string());
+                    in_deps = false;
+                }
+                None => in_deps = header.ends_with("dependencies"),
+            }
+            continue;
+        }
+        if in_deps {
+            if let Some((key, _)) = line.split_once('=') {
+                let key = key.trim().split('.').next().unwrap_or("").trim_matches('"');
+                if !key.is_empty() && !key.starts_with('#') {
+                    deps.push(key.to_string());
+                }
+            }
+        }
+    }
+    deps
+}
+
+fn node_dependencies(json: &serde_json::Value) -> Vec<String> {
+    ["dependencies", "devDependencies", "peerDependencies"]
+        .iter()
+        .filter_map(|key| json.get(key).and_then(|d| d.as_object()))
+        .flat_map(|deps| deps.keys().cloned())
+        .collect()
+}
+
+fn node_package_manager(json: &serde_json::Value, snapshot: &Snapshot) -> &'static str {
+    // Corepack's "packageManager": "pnpm@9.1.0" wins over lockfiles
+    if let Some(declared) = json.get("packageManager").and_then(|p| p.as_str()) {
+        for pm in ["pnpm", "yarn", "bun", "npm"] {
+            if declared.starts_with(pm) {
+                return pm;
+            }
+        }
+    }
+
+    if snapshot.has("pnpm-lock.yaml") {
+        "pnpm"
+    } else if snapshot.has("yarn.lock") {
+        "yarn"
+    } else if snapshot.has("bun.lockb") || snapshot.has("bun.lock") {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+// Module paths from go.mod `require` lines and blocks
+fn go_requirements(text: &str) -> Vec<String> {
+    let mut deps = Vec::new();
+    let mut in_block = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("require (") {
+            in_block = true;
+        } else if in_block && line == ")" {
+            in_block = false;
+        } else if let Some(module) = line.strip_prefix("require ").or(if in_block { Some(line) } else { None }) {
+            if let Some(module) = module.split_whitespace().next() {
+                // github.com/labstack/echo/v4 -> github.com/labstack/echo
+                let module = match module.rsplit_once("/v") {
+                    Some((base, major)) if !major.is_empty() && major.chars().all(|c| c.is_ascii_digit()) => base,
+                    _ => module,
+                };
+                deps.push(module.to_string());
+            }
+        }
+    }
+    deps
+}
+
+// Dependency names in pyproject.toml: the PEP 621 `dependencies` and
+// `optional-dependencies` arrays and the keys of Poetry's dependency tables.
+// Other strings (descriptions, tool settings) are not requirements.
+fn python_dependencies(text: &str) -> Vec<String> {
+    let mut deps = Vec::new();
+    let mut table = String::new();
+    // Inside a requirement array that started on an earlier line
+    let mut in_array = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if in_array {
+            in_array = read_requirements(line, &mut deps);
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            table = header.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            if let Some(name) = table.strip_prefix("tool.poetry.dependencies.") {
+                deps.push(name.to_lowercase());
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let key = key.trim().trim_matches('"');
+        if is_poetry_dependencies(&table) {
+            if key != "python" {
+                deps.push(key.to_lowercase());
+            }
+        } else if (table == "project" && key == "dependencies") || table == "project.optional-dependencies" {
+            if let Some(array) = value.trim().strip_prefix('[') {
+                in_array = read_requirements(array, &mut deps);
+            }
+        }
+    }
+    deps
+}
+
+// [tool.poetry.dependencies], [tool.poetry.dev-dependencies] and
+// [tool.poetry.group.<name>.dependencies]
+fn is_poetry_dependencies(table: &str) -> bool {
+    table == "tool.poetry.dependencies"
+        || table == "tool.poetry.dev-dependencies"
+        || (table.starts_with("tool.poetry.group.") && table.ends_with(".dependencies"))
+}
+
+// Requirement names from the quoted strings of one line of an array; false
+// once the array's closing bracket is reached
+fn read_requirements(line: &str, deps: &mut Vec<String>) -> bool {
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => {
+                deps.extend(requirement_names(&line[start..i]));
+                quote = None;
+            }
+            Some(_) => {}
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                start = i + 1;
+            }
+            None if c == ']' => return false,
+            None if c == '#' => break,
+            None => {}
+        }
+    }
+    true
+}
+
+// `Django>=4.2`, `fastapi[all]==0.110`, `flask ; python_version > "3"`
+fn requirement_names(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-'))
+        .filter_map(|line| {
+            let name: String = line.chars().take_while(|c| c.is_ascii_alphanumeric() || "-_.".contains(*c)).collect();
+            if name.is_empty() { None } else { Some(name.to_lowercase()) }
+        })
+        .collect()
+}
+
+fn toolchains(snapshot: &Snapshot) -> Vec<Toolchain> {
+    let mut found = Vec::new();
+    let mut add = |tool: &str, file: &str, version: Option<String>| {
+        if let Some(version) = version.filter(|v| !v.is_empty()) {
+            found.push(Toolchain { tool: tool.to_string(), version, file: file.to_string() });
+        }
+    };
+
+    if let Some(text) = snapshot.file("rust-toolchain.toml") {
+        let channel = text
+            .lines()
+            .filter_map(|l| l.trim().strip_prefix("channel"))
+            .filter_map(|l| l.trim_start().strip_prefix('='))
+            .map(|v| v.trim().trim_matches('"').to_string())
+            .next();
+        add("rust", "rust-toolchain.toml", channel);
+    } else if let Some(text) = snapshot.file("rust-toolchain") {
+        add("rust", "rust-toolchain", Some(text.trim().to_string()));
+    }
+
+    for file in [".nvmrc", ".node-version"] {
+        if let Some(text) = snapshot.file(file) {
+            add("node", file, Some(text.trim().trim_start_matches('v').to_string()));
+            break;
+        }
+    }
+
+    if let Some(text) = snapshot.file(".python-version") {
+        add("python", ".python-version", text.lines().next().map(|l| l.trim().to_string()));
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    fn snapshot(entries: &[&str], files: &[(&str, &str)]) -> Snapshot {
+        let mut all: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        all.extend(files.iter().filter(|(n, _)| !n.contains('/')).map(|(n, _)| n.to_string()));
+        Snapshot {
+            entries: all,
+            files: files.iter().map(|(n, c)| (n.to_string(), c.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn detects_a_tauri_app() {
+        let info = detect(&snapshot(
+            &["src-tauri", "tsconfig.json", "pnpm-lock.yaml"],
+            &[
+                ("package.json", r#"{"dependencies": {"@tauri-apps/api": "^2"}, "devDependencies": {"vite": "^5", "typescript": "^5"}}"#),
+                ("src-tauri/Cargo.toml", "[package]\nname = \"app\"\n\n[dependencies]\ntauri = { version = \"2\" }\nserde = \"1\"\n"),
+                (".nvmrc", "v20.11.0\n"),
+            ],
+        ));
+
+        assert_eq!(info.languages, ["TypeScript", "Rust"]);
+        assert_eq!(info.frameworks, ["Tauri", "Vite"]);
+        assert_eq!(info.package_managers, ["pnpm", "cargo"]);
+        assert_eq!(info.toolchains, [Toolchain { tool: "node".into(), version: "20.11.0".into(), file: ".nvmrc".into() }]);
+    }
+
+    #[test]
+    fn detects_rust_web_services() {
+        let info = detect(&snapshot(
+            &[],
+            &[
+                ("Cargo.toml", "[workspace.dependencies]\ntokio = \"1\"\n\n[dependencies.axum]\nversion = \"0.7\"\n\n[dev-dependencies]\n# comment = 1\n"),
+                ("rust-toolchain.toml", "[toolchain]\nchannel = \"1.78.0\"\ncomponents = [\"clippy\"]\n"),
+            ],
+        ));
+
+        assert_eq!(info.languages, ["Rust"]);
+        assert_eq!(info.frameworks, ["Axum"]);
+        assert_eq!(info.toolchains[0].version, "1.78.0");
+    }
+
+    #[test]
+    fn detects_next_with_declared_package_manager() {
+        let info = detect(&snapshot(
+            &["yarn.lock"],
+            &[("package.json", r#"{"packageManager": "pnpm@9.1.0", "dependencies": {"next": "14", "react": "18"}}"#)],
+        ));
+
+        assert_eq!(info.languages, ["JavaScript"]);
+        assert_eq!(info.frameworks, ["Next.js", "React"]);
+        assert_eq!(info.package_managers, ["pnpm"]);
+    }
+
+    #[test]
+    fn detects_python_tools_and_frameworks() {
+        let uv = detect(&snapshot(
+            &["uv.lock"],
+            &[
+                ("pyproject.toml", "[project]\nname = \"api\"\ndependencies = [\n  \"fastapi[standard]>=0.110\",\n  \"httpx\",\n]\n"),
+                (".python-version", "3.12\n"),
+            ],
+        ));
+        assert_eq!(uv.languages, ["Python"]);
+        assert_eq!(uv.frameworks, ["FastAPI"]);
+        assert_eq!(uv.package_managers, ["uv"]);
+        assert_eq!(uv.toolchains[0].version, "3.12");
+
+        let poetry = detect(&snapshot(
+            &[],
+            &[("pyproject.toml", "[tool.poetry]\nname = \"site\"\n\n[tool.poetry.dependencies]\npython = \"^3.11\"\nDjango = \"^5.0\"\n")],
+        ));
+        assert_eq!(poetry.frameworks, ["Django"]);
+        assert_eq!(poetry.package_managers, ["poetry"]);
+
+        // Only requirements count, not descriptions or tool settings
+        let pyproject = "\
+[project]
+name = \"helpers\"
+description = \"Django helpers for Flask users\"
+dependencies = [\"httpx[http2]>=0.27\", 'attrs']  # runtime
+keywords = [\"fastapi\"]
+
+[project.optional-dependencies]
+web = [
+  \"fastapi[standard] ; python_version >= '3.9'\",  # the API
+]
+
+[tool.ruff.lint.isort]
+known-third-party = [\"django\"]
+
+[tool.poetry.group.test.dependencies]
+pytest = \"^8\"
+";
+        assert_eq!(python_dependencies(pyproject), ["httpx", "attrs", "fastapi", "pytest"]);
+        let helpers = detect(&snapshot(&[], &[("pyproject.toml", pyproject)]));
+        assert_eq!(helpers.frameworks, ["FastAPI"]);
+
+        let pip = detect(&snapshot(&["manage.py"], &[("requirements.txt", "# deps\nFlask==3.0 ; python_version > '3.8'\n-r base.txt\n")]));
+        assert_eq!(pip.frameworks, ["Flask", "Django"]);
+        assert_eq!(pip.package_managers, ["pip"]);
+    }
+
+    #[test]
+    fn detects_go_modules() {
+        let info = detect(&snapshot(
+            &[],
+            &[("go.mod", "module example.com/api\n\ngo 1.22\n\nrequire (\n\tgithub.com/labstack/echo/v4 v4.11.4\n\tgolang.org/x/net v0.20.0\n)\n\nrequire github.com/go-chi/chi/v5 v5.0.12\n")],
+        ));
+
+        assert_eq!(info.languages, ["Go"]);
+        assert_eq!(info.frameworks, ["Echo", "Chi"]);
+        assert_eq!(info.package_managers, ["go modules"]);
+    }
+
+    #[test]
+    fn detects_dotnet_solutions() {
+        let info = detect(&snapshot(&["Billing.sln", "README.md"], &[]));
+        assert_eq!(info.languages, ["C#"]);
+
+        assert_eq!(detect(&snapshot(&["README.md"], &[])), ProjectInfo::default());
+    }
+
+    #[test]
+    fn reads_wsl_manifests_in_one_call() {
+        let runner = FakeRunner::new().on(
+            "--exec sh -c",
+            CommandOutput::ok("Cargo.toml\nsrc\n.python-version\n\u{1e}Cargo.toml\n[dependencies]\naxum = \"0.7\"\n\u{1e}.python-version\n3.11\n"),
+        );
+
+        let info = detect_project(&runner, Environment::Wsl, "/home/dev/it's app").unwrap();
+        assert_eq!(info.frameworks, ["Axum"]);
+        assert_eq!(info.toolchains[0].tool, "python");
+
+        let call = &runner.calls()[0];
+        assert_eq!(call.args[5..7], ["/home/dev/it's app", "65536"]);
+        assert!(call.args.iter().any(|a| a == "package.json"));
+    }
+
+    #[test]
+    fn reads_local_manifests() {
+        let dir = std::env::temp_dir().join(format!("ketra-detect-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src-tauri")).unwrap();
+        fs::write(dir.join("package.json"), r#"{"dependencies": {"vue": "3"}}"#).unwrap();
+        fs::write(dir.join("src-tauri").join("Cargo.toml"), "[dependencies]\ntauri = \"2\"\n").unwrap();
+
+        let info = detect_project(&FakeRunner::new(), Environment::host(), &dir.to_string_lossy()).unwrap();
+        assert_eq!(info.languages, ["JavaScript", "Rust"]);
+        assert_eq!(info.frameworks, ["Vue", "Tauri"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
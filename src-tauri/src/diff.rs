@@ -0,0 +1,778 @@
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
+use crate::git::run_git;
+use serde::{Deserialize, Serialize};
+
+// Past this many untracked files, the rest are listed without hunks
+const MAX_UNTRACKED_DIFFS: usize = 100;
+// Lines with more words than this are not word-highlighted
+const MAX_WORD_TOKENS: usize = 200;
+
+// What to compare
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum DiffTarget {
+    // The index against HEAD: what the next commit would contain
+    Staged,
+    // The working tree against the index, plus untracked files
+    Unstaged,
+    // The working tree against a branch, tag or commit, plus untracked files
+    WorkingTree { reference: String },
+    // What `to` changed since it branched off `from`, i.e. `from...to`
+    Range { from: String, to: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    // Only this file or folder
+    pub path: Option<String>,
+    pub ignore_whitespace: bool,
+    // Unchanged lines shown around each change; git's 3 when unset
+    pub context: Option<u32>,
+    // Split modified lines into changed and unchanged words
+    pub word_diff: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffFile {
+    pub path: String,
+    // Where a renamed or copied file came from
+    pub old_path: Option<String>,
+    // A, M, D, R or C
+    pub status: char,
+    pub binary: bool,
+    pub untracked: bool,
+    // Empty for binary files, pure renames and mode changes
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hunk {
+    // The whole "@@ -1,3 +1,4 @@ fn main() {" line, as stage_hunk and
+    // unstage_hunk take it along with the same options
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineKind {
+    Context,
+    Added,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    // 1-based; None on the side the line doesn't exist in
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+    pub text: String,
+    // The file ends after this line without a newline
+    pub no_newline: bool,
+    // With `word_diff`, the text of a modified line in changed and unchanged
+    // parts; None for context lines and lines without a counterpart
+    pub words: Option<Vec<WordSpan>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WordSpan {
+    pub text: String,
+    pub changed: bool,
+}
+
+pub fn get_diff(runner: &dyn CommandRunner, path: String, env: Environment, target: DiffTarget, options: DiffOptions) -> Result<Vec<DiffFile>, KetraError> {
+    let args = diff_args(&target, &options)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let stdout = run_git(runner, env, &path, &args, "Failed to get diff")?;
+    let mut files = parse_diff(&stdout);
+
+    if matches!(target, DiffTarget::Unstaged | DiffTarget::WorkingTree { .. }) {
+        files.extend(untracked_files(runner, &path, env, &options)?);
+    }
+    if options.word_diff {
+        for hunk in files.iter_mut().flat_map(|f| f.hunks.iter_mut()) {
+            highlight_words(&mut hunk.lines);
+        }
+    }
+    Ok(files)
+}
+
+fn diff_args(target: &DiffTarget, options: &DiffOptions) -> Result<Vec<String>, KetraError> {
+    // Fixed prefixes whatever diff.noprefix or diff.mnemonicPrefix say
+    let mut args: Vec<String> = ["diff", "--no-color", "--no-ext-diff", "-M", "--src-prefix=a/", "--dst-prefix=b/"]
+        .map(String::from)
+        .to_vec();
+    args.extend(option_args(options));
+
+    match target {
+        DiffTarget::Staged => args.push("--cached".to_string()),
+        DiffTarget::Unstaged => {}
+        DiffTarget::WorkingTree { reference } => {
+            check_revision(reference)?;
+            args.push(reference.clone());
+        }
+        DiffTarget::Range { from, to } => {
+            check_revision(from)?;
+            check_revision(to)?;
+            args.push(format!("{}...{}", from, to));
+        }
+    }
+
+    args.push("--".to_string());
+    args.extend(path_filter(options));
+    Ok(args)
+}
+
+pub fn option_args(options: &DiffOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if options.ignore_whitespace {
+        args.push("--ignore-all-space".to_string());
+    }
+    if let Some(context) = options.context {
+        args.push(format!("--unified={}", context));
+    }
+    args
+}
+
+fn path_filter(options: &DiffOptions) -> Option<String> {
+    options.path.as_deref().map(str::trim).filter(|p| !p.is_empty()).map(String::from)
+}
+
+// Revisions are separate arguments before `--`, so they must not look like options
+fn check_revision(revision: &str) -> Result<(), KetraError> {
+    if revision.is_empty() || revision.starts_with('-') || revision.contains("..") || revision.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(KetraError::new(ErrorKind::InvalidInput, format!("Invalid revision '{}'", revision)));
+    }
+    Ok(())
+}
+
+// Untracked files as new files, diffed against /dev/null
+fn untracked_files(runner: &dyn CommandRunner, path: &str, env: Environment, options: &DiffOptions) -> Result<Vec<DiffFile>, KetraError> {
+    let mut list_args = vec!["ls-files", "--others", "--exclude-standard", "-z", "--"];
+    let filter = path_filter(options);
+    list_args.extend(filter.as_deref());
+    let listed = run_git(runner, env, path, &list_args, "Failed to list untracked files")?;
+    let listed: Vec<&str> = listed.split('\0').filter(|f| !f.is_empty()).collect();
+    let diffed = &listed[..listed.len().min(MAX_UNTRACKED_DIFFS)];
+
+    // Windows has no sh to share, so each file is its own git call there
+    let mut parsed = Vec::new();
+    if env == Environment::Windows {
+        for file in diffed {
+            parsed.extend(diff_untracked(runner, path, env, options, &[file])?);
+        }
+    } else {
+        parsed = diff_untracked(runner, path, env, options, diffed)?;
+    }
+
+    Ok(listed
+        .iter()
+        .map(|file| {
+            let found = parsed.iter().position(|p| p.path == *file).map(|i| parsed.swap_remove(i));
+            let diff = found.unwrap_or(DiffFile {
+                path: String::new(),
+                old_path: None,
+                status: 'A',
+                binary: false,
+                untracked: true,
+                hunks: Vec::new(),
+            });
+            DiffFile { untracked: true, path: file.to_string(), ..diff }
+        })
+        .collect())
+}
+
+// `git diff --no-index` of each file against /dev/null, in one call: several
+// files share one sh, which on WSL means one wsl.exe start
+fn diff_untracked(runner: &dyn CommandRunner, path: &str, env: Environment, options: &DiffOptions, files: &[&str]) -> Result<Vec<DiffFile>, KetraError> {
+    let mut args: Vec<String> = ["diff", "--no-color", "--no-ext-diff", "--no-index", "--src-prefix=a/", "--dst-prefix=b/"]
+        .map(String::from)
+        .to_vec();
+    args.extend(option_args(options));
+    args.extend(["--", "/dev/null"].map(String::from));
+
+    // `--no-index` exits with 1 when the files differ, which they always do
+    let (spec, batched) = match files {
+        [] => return Ok(Vec::new()),
+        [file] => {
+            args.push(file.to_string());
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            (env.command("git", &args, Some(path)), false)
+        }
+        _ => {
+            // The arguments above are fixed flags; the file names stay separate
+            // arguments. Any exit code past 1 stops the loop and fails the call.
+            let script = format!(r#"for f; do git {} "$f"; s=$?; [ $s -le 1 ] || exit $s; done"#, args.join(" "));
+            let mut sh_args = vec!["-c", script.as_str(), "sh"];
+            sh_args.extend_from_slice(files);
+            (env.command("sh", &sh_args, Some(path)), true)
+        }
+    };
+
+    match runner.output(&spec) {
+        Ok(o) if o.success || (!batched && !o.stdout.is_empty()) => Ok(parse_diff(&o.stdout)),
+        Ok(o) => Err(KetraError::git("Failed to diff untracked files", &o)),
+        Err(e) => Err(KetraError::spawn("Failed to execute git diff", e)),
+    }
+}
+
+// Parse `git diff` output into files, hunks and numbered lines
+fn parse_diff(stdout: &str) -> Vec<DiffFile> {
+    let mut files: Vec<DiffFile> = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+
+    let mut lines: Vec<&str> = stdout.split('\n').collect();
+    if lines.last() == Some(&"") {
+        lines.pop();
+    }
+
+    for line in lines {
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            let path = git_line_path(paths);
+            files.push(DiffFile {
+                path,
+                old_path: None,
+                status: 'M',
+                binary: false,
+                untracked: false,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else { continue };
+
+        if let Some(hunk) = file.hunks.last_mut() {
+            let kind = match line.chars().next() {
+                Some(' ') => Some(LineKind::Context),
+                Some('+') => Some(LineKind::Added),
+                Some('-') => Some(LineKind::Deleted),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                let (old, new) = match kind {
+                    LineKind::Context => (Some(old_line), Some(new_line)),
+                    LineKind::Added => (None, Some(new_line)),
+                    LineKind::Deleted => (Some(old_line), None),
+                };
+                old_line += old.is_some() as u32;
+                new_line += new.is_some() as u32;
+                hunk.lines.push(DiffLine {
+                    kind,
+                    old_line: old,
+                    new_line: new,
+                    text: line[1..].to_string(),
+                    no_newline: false,
+                    words: None,
+                });
+                continue;
+            }
+            if line.starts_with('\\') {
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.no_newline = true;
+                }
+                continue;
+            }
+        }
+
+        if let Some((old_start, old_lines, new_start, new_lines)) = parse_hunk_header(line) {
+            (old_line, new_line) = (old_start, new_start);
+            file.hunks.push(Hunk {
+                header: line.to_string(),
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            });
+        } else if line.starts_with("new file mode") {
+            file.status = 'A';
+        } else if line.starts_with("deleted file mode") {
+            file.status = 'D';
+        } else if let Some(from) = line.strip_prefix("rename from ").or_else(|| line.strip_prefix("copy from ")) {
+            file.status = if line.starts_with("rename") { 'R' } else { 'C' };
+            file.old_path = Some(unquote(from));
+        } else if let Some(to) = line.strip_prefix("rename to ").or_else(|| line.strip_prefix("copy to ")) {
+            file.path = unquote(to);
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        } else if let Some(to) = line.strip_prefix("+++ ") {
+            if let Some(path) = unquote(to).strip_prefix("b/") {
+                file.path = path.to_string();
+            }
+        } else if let Some(from) = line.strip_prefix("--- ") {
+            if let Some(path) = unquote(from).strip_prefix("a/") {
+                file.path = path.to_string();
+            }
+        }
+    }
+
+    files
+}
+
+// "@@ -1,3 +1,4 @@ context" -> (1, 3, 1, 4); a missing count means 1
+fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32, u32)> {
+    let ranges = line.strip_prefix("@@ -")?;
+    let (ranges, _) = ranges.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |r: &str| -> Option<(u32, u32)> {
+        match r.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(old)?;
+    let (new_start, new_lines) = range(new)?;
+    Some((old_start, old_lines, new_start, new_lines))
+}
+
+// The path from "a/<path> b/<path>". Only used for files without ---/+++
+// lines (binaries, pure renames, mode changes), whose two paths are the same
+// unless a rename line says otherwise.
+fn git_line_path(paths: &str) -> String {
+    if paths.starts_with('"') {
+        let end = quoted_end(paths);
+        let first = unquote(&paths[..end]);
+        return first.strip_prefix("a/").unwrap_or(&first).to_string();
+    }
+    let half = paths.len().saturating_sub(5) / 2;
+    paths.get(2..2 + half).unwrap_or(paths).to_string()
+}
+
+// Where a C-quoted string starting at 0 ends
+fn quoted_end(s: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+// Git C-quotes paths with unusual characters: "a/tab\there \303\251"
+fn unquote(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('a') => bytes.push(7),
+            Some('b') => bytes.push(8),
+            Some('f') => bytes.push(12),
+            Some('v') => bytes.push(11),
+            Some(d @ '0'..='7') => {
+                let mut value = d.to_digit(8).unwrap_or(0);
+                for _ in 0..2 {
+                    if let Some(next) = chars.peek().and_then(|c| c.to_digit(8)) {
+                        value = value * 8 + next;
+                        chars.next();
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Pair each run of deleted lines with the added lines right after it, first
+// with first, and mark the words that differ
+fn highlight_words(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].kind != LineKind::Deleted {
+            i += 1;
+            continue;
+        }
+        let deleted = i;
+        while i < lines.len() && lines[i].kind == LineKind::Deleted {
+            i += 1;
+        }
+        let added = i;
+        while i < lines.len() && lines[i].kind == LineKind::Added {
+            i += 1;
+        }
+
+        let pairs = (added - deleted).min(i - added);
+        for n in 0..pairs {
+            if let Some((old, new)) = word_spans(&lines[deleted + n].text, &lines[added + n].text) {
+                lines[deleted + n].words = Some(old);
+                lines[added + n].words = Some(new);
+            }
+        }
+    }
+}
+
+// Both lines as words that are or aren't part of their longest common
+// subsequence of words
+fn word_spans(old: &str, new: &str) -> Option<(Vec<WordSpan>, Vec<WordSpan>)> {
+    let a = tokens(old);
+    let b = tokens(new);
+    if a.len() > MAX_WORD_TOKENS || b.len() > MAX_WORD_TOKENS {
+        return None;
+    }
+
+    // lcs[i][j]: common length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u16; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut a_common = vec![false; a.len()];
+    let mut b_common = vec![false; b.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            a_common[i] = true;
+            b_common[j] = true;
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    Some((spans(&a, &a_common), spans(&b, &b_common)))
+}
+
+// Runs of letters, digits and `_`, runs of whitespace, and single other characters
+fn tokens(text: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            1
+        } else if c.is_whitespace() {
+            2
+        } else {
+            3
+        }
+    };
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if let Some(p) = previous {
+            if class(p) != class(c) || class(c) == 3 {
+                tokens.push(&text[start..i]);
+                start = i;
+            }
+        }
+        previous = Some(c);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+fn spans(tokens: &[&str], common: &[bool]) -> Vec<WordSpan> {
+    let mut spans: Vec<WordSpan> = Vec::new();
+    for (token, common) in tokens.iter().zip(common) {
+        match spans.last_mut() {
+            Some(last) if last.changed != *common => last.text.push_str(token),
+            _ => spans.push(WordSpan { text: token.to_string(), changed: !common }),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    const REPO: &str = "/home/dev/ketra/app";
+
+    const SAMPLE: &str = "\
+diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,4 +1,4 @@ fn main() {
+ fn main() {
+-    println!(\"hello\");
++    println!(\"hello, world\");
+ }
+-// end
+\\ No newline at end of file
++// end
+@@ -20 +20,2 @@
+ run();
++stop();
+diff --git a/old name.rs b/new name.rs
+similarity index 90%
+rename from old name.rs
+rename to new name.rs
+index 3333333..4444444 100644
+--- a/old name.rs
++++ b/new name.rs
+@@ -1 +1 @@
+-a
++b
+diff --git a/logo.png b/logo.png
+new file mode 100644
+index 0000000..5555555
+Binary files /dev/null and b/logo.png differ
+diff --git a/gone.md b/gone.md
+deleted file mode 100644
+index 6666666..0000000
+--- a/gone.md
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+diff --git a/same path.sh b/same path.sh
+old mode 100644
+new mode 100755
+diff --git \"a/tab\\there \\303\\251.txt\" \"b/tab\\there \\303\\251.txt\"
+new file mode 100644
+index 0000000..7777777
+--- /dev/null
++++ \"b/tab\\there \\303\\251.txt\"
+@@ -0,0 +1 @@
++x
+";
+
+    #[test]
+    fn parses_files_hunks_and_line_numbers() {
+        let files = parse_diff(SAMPLE);
+        let summary: Vec<(&str, Option<&str>, char, bool, usize)> = files
+            .iter()
+            .map(|f| (f.path.as_str(), f.old_path.as_deref(), f.status, f.binary, f.hunks.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("src/main.rs", None, 'M', false, 2),
+                ("new name.rs", Some("old name.rs"), 'R', false, 1),
+                ("logo.png", None, 'A', true, 0),
+                ("gone.md", None, 'D', false, 1),
+                ("same path.sh", None, 'M', false, 0),
+                ("tab\there é.txt", None, 'A', false, 1),
+            ]
+        );
+
+        let hunk = &files[0].hunks[0];
+        assert_eq!(hunk.header, "@@ -1,4 +1,4 @@ fn main() {");
+        assert_eq!((hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines), (1, 4, 1, 4));
+        let numbers: Vec<(LineKind, Option<u32>, Option<u32>)> = hunk.lines.iter().map(|l| (l.kind, l.old_line, l.new_line)).collect();
+        assert_eq!(
+            numbers,
+            [
+                (LineKind::Context, Some(1), Some(1)),
+                (LineKind::Deleted, Some(2), None),
+                (LineKind::Added, None, Some(2)),
+                (LineKind::Context, Some(3), Some(3)),
+                (LineKind::Deleted, Some(4), None),
+                (LineKind::Added, None, Some(4)),
+            ]
+        );
+        assert!(hunk.lines[4].no_newline && !hunk.lines[5].no_newline);
+        assert_eq!(hunk.lines[1].text, "    println!(\"hello\");");
+
+        let second = &files[0].hunks[1];
+        assert_eq!((second.old_lines, second.lines[1].new_line), (1, Some(21)));
+        assert_eq!(files[3].hunks[0].lines[0].old_line, Some(1));
+    }
+
+    #[test]
+    fn highlights_changed_words() {
+        let (old, new) = word_spans("let total = sum(a, b);", "let total = sum(a, b, c);").unwrap();
+        let text = |spans: &[WordSpan]| spans.iter().map(|s| if s.changed { format!("[{}]", s.text) } else { s.text.clone() }).collect::<String>();
+        assert_eq!(text(&old), "let total = sum(a, b);");
+        assert_eq!(text(&new), "let total = sum(a, b[, c]);");
+
+        let (old, new) = word_spans("color: red;", "colour: blue;").unwrap();
+        assert_eq!((text(&old), text(&new)), ("[color]: [red];".to_string(), "[colour]: [blue];".to_string()));
+
+        let mut files = parse_diff(SAMPLE);
+        highlight_words(&mut files[0].hunks[0].lines);
+        let lines = &files[0].hunks[0].lines;
+        assert_eq!(text(lines[2].words.as_ref().unwrap()), "    println!(\"hello[, world]\");");
+        assert!(lines[0].words.is_none());
+
+        assert!(word_spans(&"a ".repeat(300), "b").is_none());
+    }
+
+    #[test]
+    fn targets_and_options_become_arguments() {
+        let options = DiffOptions {
+            path: Some("src".into()),
+            ignore_whitespace: true,
+            context: Some(10),
+            word_diff: true,
+        };
+        let cases = [
+            (DiffTarget::Staged, vec!["--cached", "--", "src"]),
+            (DiffTarget::Unstaged, vec!["--", "src"]),
+            (DiffTarget::WorkingTree { reference: "v1.0".into() }, vec!["v1.0", "--", "src"]),
+            (DiffTarget::Range { from: "main".into(), to: "feature/x".into() }, vec!["main...feature/x", "--", "src"]),
+        ];
+        for (target, tail) in cases {
+            let args = diff_args(&target, &options).unwrap();
+            assert_eq!(args[6..8], ["--ignore-all-space", "--unified=10"]);
+            assert_eq!(args[8..], tail, "{:?}", target);
+        }
+
+        for bad in ["--output=/tmp/x", "a..b", "", "main branch"] {
+            let target = DiffTarget::WorkingTree { reference: bad.into() };
+            assert!(diff_args(&target, &options).unwrap_err().is(&ErrorKind::InvalidInput), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn unstaged_diffs_include_untracked_files() {
+        let runner = FakeRunner::new()
+            .on("ls-files --others", CommandOutput::ok("notes.txt\0"))
+            .on(
+                "--no-index",
+                CommandOutput {
+                    success: false,
+                    stdout: "diff --git a/notes.txt b/notes.txt\nnew file mode 100644\n--- /dev/null\n+++ b/notes.txt\n@@ -0,0 +1 @@\n+todo\n".to_string(),
+                    stderr: String::new(),
+                },
+            )
+            .on("git diff", CommandOutput::ok(""));
+
+        let files = get_diff(&runner, REPO.to_string(), Environment::Wsl, DiffTarget::Unstaged, DiffOptions::default()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].untracked);
+        assert_eq!((files[0].status, files[0].hunks[0].lines[0].new_line), ('A', Some(1)));
+        assert!(runner.ran("git diff --no-color --no-ext-diff --no-index --src-prefix=a/ --dst-prefix=b/ -- /dev/null notes.txt"));
+
+        // Staged changes never include untracked files
+        get_diff(&runner, REPO.to_string(), Environment::Wsl, DiffTarget::Staged, DiffOptions::default()).unwrap();
+        assert_eq!(runner.lines().iter().filter(|l| l.contains("ls-files")).count(), 1);
+    }
+
+    #[test]
+    fn untracked_files_on_wsl_share_one_call() {
+        let runner = FakeRunner::new()
+            .on("ls-files --others", CommandOutput::ok("b.txt\0a b.txt\0"))
+            .on(
+                "--exec sh -c",
+                CommandOutput::ok("diff --git a/a b.txt b/a b.txt\nnew file mode 100644\n--- /dev/null\n+++ b/a b.txt\n@@ -0,0 +1 @@\n+a\n"),
+            )
+            .on("git diff", CommandOutput::ok(""));
+
+        let options = DiffOptions { context: Some(1), ..DiffOptions::default() };
+        let files = get_diff(&runner, REPO.to_string(), Environment::Wsl, DiffTarget::Unstaged, options).unwrap();
+        let paths: Vec<(&str, usize)> = files.iter().map(|f| (f.path.as_str(), f.hunks.len())).collect();
+        assert_eq!(paths, [("b.txt", 0), ("a b.txt", 1)]);
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 3);
+        assert!(calls[2].args.iter().any(|a| a.contains(r#"git diff --no-color --no-ext-diff --no-index --src-prefix=a/ --dst-prefix=b/ --unified=1 -- /dev/null "$f""#)));
+        assert_eq!(calls[2].args[calls[2].args.len() - 2..], ["b.txt", "a b.txt"]);
+    }
+
+    #[test]
+    fn a_failing_untracked_diff_is_an_error() {
+        let runner = FakeRunner::new()
+            .on("ls-files --others", CommandOutput::ok("a.txt\0locked.txt\0"))
+            .on(
+                "--exec sh -c",
+                CommandOutput {
+                    success: false,
+                    stdout: "diff --git a/a.txt b/a.txt\nnew file mode 100644\n--- /dev/null\n+++ b/a.txt\n@@ -0,0 +1 @@\n+a\n".to_string(),
+                    stderr: "error: read error while indexing locked.txt: Input/output error\n".to_string(),
+                },
+            )
+            .on("git diff", CommandOutput::ok(""));
+
+        let err = get_diff(&runner, REPO.to_string(), Environment::Wsl, DiffTarget::Unstaged, DiffOptions::default()).unwrap_err();
+        assert!(err.message.starts_with("Failed to diff untracked files") && err.message.contains("locked.txt"), "{}", err.message);
+    }
+
+    #[test]
+    fn diffs_a_real_repo() {
+        use crate::exec::system;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("ketra-diff-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let env = Environment::host();
+        let git = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            let output = system().output(&env.command("git", &full, Some(&path))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        };
+        let diff = |target: DiffTarget, options: DiffOptions| get_diff(system(), path.clone(), env, target, options).unwrap();
+
+        git(&["init", "-q", "-b", "main"]);
+        fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "first"]);
+        git(&["checkout", "-q", "-b", "feature"]);
+        fs::write(dir.join("b.txt"), "feature\n").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "feature"]);
+        git(&["checkout", "-q", "main"]);
+
+        fs::write(dir.join("a.txt"), "one\n2\nthree\n").unwrap();
+        git(&["add", "a.txt"]);
+        fs::write(dir.join("a.txt"), "one\n2\nthree  \nfour\n").unwrap();
+        fs::write(dir.join("new.txt"), "new\n").unwrap();
+        fs::write(dir.join("it's new.txt"), "").unwrap();
+
+        let staged = diff(DiffTarget::Staged, DiffOptions::default());
+        assert_eq!(staged.len(), 1);
+        let changed: Vec<&str> = staged[0].hunks[0].lines.iter().filter(|l| l.kind != LineKind::Context).map(|l| l.text.as_str()).collect();
+        assert_eq!(changed, ["two", "2"]);
+
+        let unstaged = diff(DiffTarget::Unstaged, DiffOptions { word_diff: true, ..DiffOptions::default() });
+        let paths: Vec<(&str, bool)> = unstaged.iter().map(|f| (f.path.as_str(), f.untracked)).collect();
+        assert_eq!(paths, [("a.txt", false), ("it's new.txt", true), ("new.txt", true)]);
+        assert_eq!(unstaged[2].hunks[0].lines[0].text, "new");
+        let words = unstaged[0].hunks[0].lines.iter().find_map(|l| l.words.clone().filter(|_| l.kind == LineKind::Added)).unwrap();
+        assert_eq!(words, [WordSpan { text: "three".into(), changed: false }, WordSpan { text: "  ".into(), changed: true }]);
+
+        // Ignoring whitespace leaves only the added line
+        let ignoring = diff(DiffTarget::Unstaged, DiffOptions { ignore_whitespace: true, context: Some(0), path: Some("a.txt".into()), ..DiffOptions::default() });
+        assert_eq!(ignoring.len(), 1);
+        let lines: Vec<(&str, Option<u32>)> = ignoring[0].hunks[0].lines.iter().map(|l| (l.text.as_str(), l.new_line)).collect();
+        assert_eq!(lines, [("four", Some(4))]);
+
+        let worktree = diff(DiffTarget::WorkingTree { reference: "HEAD".into() }, DiffOptions::default());
+        assert_eq!(worktree.len(), 3);
+
+        // Only what feature changed since it branched off main
+        let range = diff(DiffTarget::Range { from: "main".into(), to: "feature".into() }, DiffOptions::default());
+        let paths: Vec<&str> = range.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["b.txt"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,124 @@
+use crate::environment::Environment;
+use std::fs;
+use std::path::Path;
+
+// Files or folders whose presence makes a folder a project
+pub const MARKERS: &[&str] = &[".git", "Cargo.toml", "package.json", "go.mod", "pyproject.toml"];
+
+// Folders never worth descending into
+pub const SKIPPED: &[&str] = &["node_modules", "target"];
+
+// A project folder found under a root
+pub struct DirEntry {
+    pub name: String,
+    pub path: String,
+    // Path below the root, `/`-separated on every platform
+    pub relative: String,
+    pub modified: u64,
+}
+
+fn is_marker(name: &str) -> bool {
+    MARKERS.contains(&name) || name.to_lowercase().ends_with(".sln")
+}
+
+fn is_skipped(name: &str) -> bool {
+    name.starts_with('.') || SKIPPED.contains(&name)
+}
+
+// Walk `base` up to `depth` levels down and return every folder that looks
+// like a project. A project's own subfolders are not searched, so a monorepo
+// shows up once rather than once per package. Only for environments reachable
+// through std::fs; WSL roots are scanned by wsl_scan with the same rules.
+pub fn discover(env: Environment, base: &str, depth: u32) -> Vec<DirEntry> {
+    let mut found = Vec::new();
+    walk_local(env, Path::new(base), "", depth, &mut found);
+    found
+}
+
+fn walk_local(env: Environment, dir: &Path, relative: &str, depth: u32, found: &mut Vec<DirEntry>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+
+    let mut subdirs = Vec::new();
+    for entry in entries.flatten() {
+        let Some(name) = entry.file_name().to_str().map(|s| s.to_string()) else { continue };
+        if is_marker(&name) {
+            found.push(DirEntry {
+                name: env.file_name(&dir.to_string_lossy()),
+                path: dir.to_string_lossy().to_string(),
+                relative: relative.to_string(),
+                modified: modified_secs(dir),
+            });
+            return;
+        }
+        // file_type() does not follow symlinks, which keeps us out of loops
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) && !is_skipped(&name) {
+            subdirs.push(name);
+        }
+    }
+
+    if depth == 0 {
+        return;
+    }
+
+    subdirs.sort();
+    for name in subdirs {
+        let child = if relative.is_empty() { name.clone() } else { format!("{}/{}", relative, name) };
+        walk_local(env, &dir.join(&name), &child, depth - 1, found);
+    }
+}
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn finds_nested_projects_by_marker() {
+        let base = std::env::temp_dir().join(format!("ketra-discover-{}", std::process::id()));
+        fs::remove_dir_all(&base).ok();
+        fs::create_dir_all(base.join("app").join(".git")).unwrap();
+        touch(&base.join("clients").join("acme").join("api").join("Cargo.toml"));
+        touch(&base.join("clients").join("acme").join("api").join("web").join("package.json"));
+        touch(&base.join("clients").join("globex").join("Globex.sln"));
+        touch(&base.join("node_modules").join("left-pad").join("package.json"));
+        touch(&base.join(".cache").join("tool").join("go.mod"));
+        touch(&base.join("deep").join("a").join("b").join("c").join("go.mod"));
+        fs::create_dir_all(base.join("empty")).unwrap();
+
+        let host = Environment::host();
+        let found = discover(host, &base.to_string_lossy(), 3);
+        let relative: Vec<&str> = found.iter().map(|d| d.relative.as_str()).collect();
+        assert_eq!(relative, ["app", "clients/acme/api", "clients/globex"]);
+        assert_eq!(found[1].name, "api");
+        assert!(found[0].modified > 0);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn a_root_that_is_a_project_is_listed_itself() {
+        let base = std::env::temp_dir().join(format!("ketra-discover-root-{}", std::process::id()));
+        fs::remove_dir_all(&base).ok();
+        touch(&base.join("pyproject.toml"));
+        touch(&base.join("packages").join("core").join("pyproject.toml"));
+
+        let found = discover(Environment::host(), &base.to_string_lossy(), 3);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].relative, "");
+
+        fs::remove_dir_all(&base).ok();
+    }
+}
@@ -0,0 +1,389 @@
+use serde::{Deserialize, Serialize};
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::{CommandOutput, CommandRunner, CommandSpec};
+use std::fs;
+
+// Where a project lives. Windows, Linux and macOS paths are reachable through
+// std::fs from the host; WSL paths have to go through wsl.exe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Windows,
+    Wsl,
+    Linux,
+    Macos,
+}
+
+impl Environment {
+    // The environment ketra itself runs in
+    pub fn host() -> Self {
+        if cfg!(windows) {
+            Environment::Windows
+        } else if cfg!(target_os = "macos") {
+            Environment::Macos
+        } else {
+            Environment::Linux
+        }
+    }
+
+    // Environments that can hold projects on this machine
+    pub fn available() -> Vec<Self> {
+        if cfg!(windows) {
+            vec![Environment::Windows, Environment::Wsl]
+        } else {
+            vec![Self::host()]
+        }
+    }
+
+    // Guess the environment of a project from its path. Unix-style paths only
+    // mean WSL when ketra runs on Windows.
+    pub fn from_path(path: &str) -> Self {
+        if cfg!(windows) && path.starts_with('/') {
+            Environment::Wsl
+        } else {
+            Self::host()
+        }
+    }
+
+    pub fn is_local(self) -> bool {
+        self != Environment::Wsl
+    }
+
+    pub fn separator(self) -> char {
+        match self {
+            Environment::Windows => '\\',
+            _ => '/',
+        }
+    }
+
+    pub fn join(self, base: &str, name: &str) -> String {
+        let sep = self.separator();
+        format!("{}{}{}", base.trim_end_matches(sep), sep, name)
+    }
+
+    // Last path segment, used as the project name
+    pub fn file_name(self, path: &str) -> String {
+        path.trim_end_matches(self.separator())
+            .rsplit(self.separator())
+            .next()
+            .unwrap_or(path)
+            .to_string()
+    }
+
+    pub fn home_dir(self, runner: &dyn CommandRunner) -> Result<String, KetraError> {
+        match self {
+            Environment::Windows => std::env::var("USERPROFILE")
+                .map_err(|_| KetraError::new(ErrorKind::PathNotFound, "Failed to get USERPROFILE environment variable")),
+            Environment::Wsl => {
+                let cache = runner.wsl_home();
+                if let Some(home) = cache.and_then(|c| c.get()) {
+                    return Ok(home.clone());
+                }
+
+                let output = runner
+                    .output(&self.command("printenv", &["HOME"], None))
+                    .map_err(|e| KetraError::spawn("Failed to get WSL home folder", e))?;
+
+                let home = output.stdout.trim().to_string();
+                if home.is_empty() {
+                    return Err(KetraError::new(ErrorKind::ToolMissing, "Failed to get WSL home folder").with_stderr(output.stderr));
+                }
+                if let Some(cache) = cache {
+                    let _ = cache.set(home.clone());
+                }
+                Ok(home)
+            }
+            Environment::Linux | Environment::Macos => dirs::home_dir()
+                .map(|p| p.to_string_lossy().to_string())
+                .ok_or_else(|| KetraError::new(ErrorKind::PathNotFound, "Failed to get home folder")),
+        }
+    }
+
+    // Run `program` inside this environment, from `dir` when given. WSL
+    // commands are exec'd directly so arguments are not re-parsed by a shell.
+    pub fn command(self, program: &str, args: &[&str], dir: Option<&str>) -> CommandSpec {
+        match self {
+            Environment::Wsl => {
+                let mut spec = CommandSpec::new("wsl");
+                if let Some(dir) = dir {
+                    spec = spec.args(&["--cd", dir]);
+                }
+                spec.args(&["--exec", program]).args(args)
+            }
+            _ => {
+                let spec = CommandSpec::new(program).args(args);
+                match dir {
+                    Some(dir) => spec.dir(dir),
+                    None => spec,
+                }
+            }
+        }
+    }
+
+    // Like `command`, but for tools that are shell shims on Windows (npm, npx, cargo)
+    pub fn tool_command(self, program: &str, args: &[&str], dir: Option<&str>) -> CommandSpec {
+        match self {
+            Environment::Windows => self.command("cmd", &["/C", program], dir).args(args),
+            _ => self.command(program, args, dir),
+        }
+    }
+
+    // A file or folder
+    pub fn path_exists(self, runner: &dyn CommandRunner, path: &str) -> bool {
+        if self.is_local() {
+            std::path::Path::new(path).exists()
+        } else {
+            runner
+                .output(&self.command("test", &["-e", path], None))
+                .map(|o| o.success)
+                .unwrap_or(false)
+        }
+    }
+
+    pub fn dir_exists(self, runner: &dyn CommandRunner, path: &str) -> bool {
+        if self.is_local() {
+            std::path::Path::new(path).is_dir()
+        } else {
+            runner
+                .output(&self.command("test", &["-d", path], None))
+                .map(|o| o.success)
+                .unwrap_or(false)
+        }
+    }
+
+    pub fn create_dir_all(self, runner: &dyn CommandRunner, path: &str) -> Result<(), KetraError> {
+        if self.is_local() {
+            fs::create_dir_all(path).map_err(|e| KetraError::io("Failed to create directory", path, e))
+        } else {
+            let output = runner
+                .output(&self.command("mkdir", &["-p", "--", path], None))
+                .map_err(|e| KetraError::spawn("Failed to create WSL directory", e))?;
+            check_output(output, "Failed to create WSL directory")
+        }
+    }
+
+    pub fn remove_dir_all(self, runner: &dyn CommandRunner, path: &str) -> Result<(), KetraError> {
+        if self.is_local() {
+            fs::remove_dir_all(path).map_err(|e| {
+                // Check if it's a permission/access error (likely because folder is open)
+                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                    KetraError::new(ErrorKind::PermissionDenied, "Cannot delete project - please close VSCode or any programs using this folder first")
+                        .with_stderr(e.to_string())
+                } else {
+                    KetraError::io("Failed to delete project folder", path, e)
+                }
+            })
+        } else {
+            let output = runner
+                .output(&self.command("rm", &["-rf", "--", path], None))
+                .map_err(|e| KetraError::spawn("Failed to delete WSL project", e))?;
+            check_output(output, "Failed to delete WSL project")
+        }
+    }
+
+    pub fn write_file(self, runner: &dyn CommandRunner, path: &str, content: &str) -> Result<(), KetraError> {
+        if self.is_local() {
+            fs::write(path, content).map_err(|e| KetraError::io("Failed to write", path, e))
+        } else {
+            // Content and path are passed as positional parameters, never
+            // spliced into the script text
+            let output = runner
+                .output(&self.command("sh", &["-c", r#"printf '%s' "$1" > "$2""#, "sh", content, path], None))
+                .map_err(|e| KetraError::spawn("Failed to write file", e))?;
+            check_output(output, &format!("Failed to write '{}'", path))
+        }
+    }
+
+    // Copy a folder from the host filesystem into this environment
+    pub fn copy_dir_from_host(self, runner: &dyn CommandRunner, source: &str, dest: &str) -> Result<(), KetraError> {
+        let spec = match self {
+            Environment::Windows => {
+                CommandSpec::new("robocopy").args(&[source, dest, "/E", "/NFL", "/NDL", "/NJH", "/NJS"])
+            }
+            Environment::Wsl => {
+                // Convert Windows path to WSL path for source
+                let wsl_source = windows_to_wsl_path(source);
+                self.command("cp", &["-r", "--", &wsl_source, dest], None)
+            }
+            Environment::Linux | Environment::Macos => self.command("cp", &["-R", "--", source, dest], None),
+        };
+
+        runner.output(&spec).map_err(|e| KetraError::spawn("Failed to copy folder", e))?;
+        Ok(())
+    }
+
+    // Open a folder in the platform's file manager
+    pub fn open_folder(self, runner: &dyn CommandRunner, path: &str) -> Result<(), KetraError> {
+        let spec = match self {
+            Environment::Windows => CommandSpec::new("explorer").arg(path),
+            // Open WSL folder in Windows Explorer
+            Environment::Wsl => {
+                CommandSpec::new("explorer.exe").arg(&format!("\\\\wsl$\\Ubuntu{}", path.replace('/', "\\")))
+            }
+            Environment::Linux => CommandSpec::new("xdg-open").arg(path),
+            Environment::Macos => CommandSpec::new("open").arg(path),
+        };
+
+        runner.spawn(&spec).map_err(|e| KetraError::spawn("Failed to open folder", e))
+    }
+
+    // Open a terminal window in `dir`
+    pub fn open_terminal(self, runner: &dyn CommandRunner, dir: &str) -> Result<(), KetraError> {
+        let spec = match self {
+            Environment::Windows => CommandSpec::new("wt").args(&["-d", dir]),
+            Environment::Wsl => CommandSpec::new("wt").args(&["wsl.exe", "--cd", &wt_escape(dir)]),
+            Environment::Linux => CommandSpec::new("x-terminal-emulator").dir(dir),
+            Environment::Macos => CommandSpec::new("open").args(&["-a", "Terminal", dir]),
+        };
+
+        runner.spawn(&spec).map_err(|e| KetraError::spawn("Failed to open terminal", e))
+    }
+
+    // Open a project folder in VSCode
+    pub fn open_editor(self, runner: &dyn CommandRunner, path: &str) -> Result<(), KetraError> {
+        let spec = match self {
+            Environment::Windows => {
+                CommandSpec::new("powershell").args(&["-NoProfile", "-Command", &format!("code {}", ps_quote(path))])
+            }
+            Environment::Wsl => CommandSpec::new("powershell").args(&[
+                "-NoProfile",
+                "-Command",
+                &format!("code --folder-uri {}", ps_quote(&format!("vscode-remote://wsl+Ubuntu{}", path))),
+            ]),
+            Environment::Linux | Environment::Macos => CommandSpec::new("code").arg(path),
+        };
+
+        runner.spawn(&spec).map_err(|e| KetraError::spawn("Failed to open VSCode", e))
+    }
+}
+
+// `C:\Users\dev` -> `/mnt/c/Users/dev`
+pub fn windows_to_wsl_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            format!("/mnt/{}{}", drive.to_ascii_lowercase(), chars.as_str())
+        }
+        _ => path,
+    }
+}
+
+// Single-quoted PowerShell string literal; nothing inside is expanded
+fn ps_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+// Windows Terminal splits its command line on `;` unless escaped
+fn wt_escape(s: &str) -> String {
+    s.replace(';', "\\;")
+}
+
+fn check_output(output: CommandOutput, context: &str) -> Result<(), KetraError> {
+    if output.success {
+        Ok(())
+    } else {
+        Err(KetraError::git(context, &output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+
+    #[test]
+    fn joins_with_the_environment_separator() {
+        assert_eq!(Environment::Windows.join("C:\\Users\\dev\\", "ketra"), "C:\\Users\\dev\\ketra");
+        assert_eq!(Environment::Wsl.join("/home/dev", "ketra"), "/home/dev/ketra");
+        assert_eq!(Environment::Linux.join("/home/dev/", "ketra"), "/home/dev/ketra");
+    }
+
+    #[test]
+    fn takes_the_last_path_segment() {
+        assert_eq!(Environment::Windows.file_name("C:\\Users\\dev\\ketra\\app"), "app");
+        assert_eq!(Environment::Linux.file_name("/home/dev/ketra/app/"), "app");
+    }
+
+    #[test]
+    fn wsl_commands_go_through_wsl_exe() {
+        let spec = Environment::Wsl.command("git", &["status"], Some("/home/dev/app"));
+        assert_eq!(spec.to_string(), "wsl --cd /home/dev/app --exec git status");
+        assert_eq!(spec.dir, None);
+
+        let spec = Environment::Linux.command("git", &["status"], Some("/home/dev/app"));
+        assert_eq!(spec.to_string(), "git status");
+        assert_eq!(spec.dir.as_deref(), Some("/home/dev/app"));
+    }
+
+    #[test]
+    fn windows_tools_run_through_cmd() {
+        let spec = Environment::Windows.tool_command("npm", &["init", "-y"], None);
+        assert_eq!(spec.to_string(), "cmd /C npm init -y");
+        assert_eq!(Environment::Linux.tool_command("npm", &["init", "-y"], None).to_string(), "npm init -y");
+    }
+
+    #[test]
+    fn wsl_home_comes_from_wsl() {
+        let runner = FakeRunner::new().on("printenv HOME", CommandOutput::ok("/home/dev\n"));
+        assert_eq!(Environment::Wsl.home_dir(&runner).unwrap(), "/home/dev");
+        // Asked once per runner
+        assert_eq!(Environment::Wsl.home_dir(&runner).unwrap(), "/home/dev");
+        assert_eq!(runner.calls().len(), 1);
+
+        let runner = FakeRunner::new().missing("wsl");
+        assert!(Environment::Wsl.home_dir(&runner).is_err());
+    }
+
+    const HOSTILE_PATH: &str = "/home/dev/ketra/it's a $(reboot); `id` && rm -rf ~";
+
+    #[test]
+    fn wsl_arguments_are_passed_verbatim() {
+        let spec = Environment::Wsl.command("git", &["commit", "-m", "fix; rm -rf ~"], Some(HOSTILE_PATH));
+        assert_eq!(spec.program, "wsl");
+        assert_eq!(spec.args, ["--cd", HOSTILE_PATH, "--exec", "git", "commit", "-m", "fix; rm -rf ~"]);
+    }
+
+    #[test]
+    fn wsl_file_writes_do_not_interpolate() {
+        let runner = FakeRunner::new();
+        let content = "echo '$HOME' \"$(id)\"\n";
+        Environment::Wsl.write_file(&runner, HOSTILE_PATH, content).unwrap();
+
+        let call = &runner.calls()[0];
+        assert_eq!(call.args[..4], ["--exec", "sh", "-c", r#"printf '%s' "$1" > "$2""#]);
+        assert_eq!(call.args[4..], ["sh", content, HOSTILE_PATH]);
+    }
+
+    #[test]
+    fn wsl_paths_cannot_become_options() {
+        let runner = FakeRunner::new();
+        Environment::Wsl.remove_dir_all(&runner, "-rf /").unwrap();
+        assert_eq!(runner.calls()[0].args, ["--exec", "rm", "-rf", "--", "-rf /"]);
+    }
+
+    #[test]
+    fn editor_paths_are_quoted_for_powershell() {
+        let runner = FakeRunner::new();
+        Environment::Windows.open_editor(&runner, "C:\\Users\\o'neil\\$(app)").unwrap();
+        Environment::Wsl.open_editor(&runner, "/home/o'neil/app").unwrap();
+
+        let calls = runner.calls();
+        assert_eq!(calls[0].args[2], "code 'C:\\Users\\o''neil\\$(app)'");
+        assert_eq!(calls[1].args[2], "code --folder-uri 'vscode-remote://wsl+Ubuntu/home/o''neil/app'");
+    }
+
+    #[test]
+    fn terminal_dirs_escape_windows_terminal_separators() {
+        let runner = FakeRunner::new();
+        Environment::Wsl.open_terminal(&runner, "/home/dev/a;b").unwrap();
+        assert_eq!(runner.calls()[0].args, ["wsl.exe", "--cd", "/home/dev/a\\;b"]);
+    }
+
+    #[test]
+    fn converts_windows_paths_for_wsl() {
+        assert_eq!(windows_to_wsl_path("C:\\Users\\dev\\app"), "/mnt/c/Users/dev/app");
+        assert_eq!(windows_to_wsl_path("D:\\src"), "/mnt/d/src");
+        assert_eq!(windows_to_wsl_path("\\\\server\\share"), "//server/share");
+    }
+}
@@ -0,0 +1,185 @@
+use crate::exec::CommandOutput;
+use serde::Serialize;
+use std::fmt;
+use std::io;
+
+// What went wrong, so the UI can offer a targeted fix (a login button for
+// AuthMissing, "set upstream" for NoUpstream, ...) instead of just a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum ErrorKind {
+    NotARepo,
+    NoUpstream,
+    AuthMissing,
+    PermissionDenied,
+    ToolMissing,
+    PathNotFound,
+    AlreadyExists,
+    Conflict,
+    GitHubApi { status: u16 },
+    InvalidInput,
+    // Refused until the user confirms, e.g. deleting unmerged work
+    NeedsConfirmation,
+    // The user cancelled a running operation
+    Cancelled,
+    Failed,
+}
+
+// Error returned by every Tauri command. Serializes as
+// `{ kind, message, stderr }` plus any fields of the kind (e.g. `status`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KetraError {
+    #[serde(flatten)]
+    pub kind: ErrorKind,
+    pub message: String,
+    pub stderr: String,
+}
+
+impl KetraError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        KetraError {
+            kind,
+            message: message.into(),
+            stderr: String::new(),
+        }
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Failed, message)
+    }
+
+    pub fn with_stderr(mut self, stderr: impl Into<String>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+
+    // A process could not be started at all
+    pub fn spawn(context: &str, err: io::Error) -> Self {
+        let kind = match err.kind() {
+            io::ErrorKind::NotFound => ErrorKind::ToolMissing,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            _ => ErrorKind::Failed,
+        };
+        Self::new(kind, format!("{}: {}", context, err))
+    }
+
+    // A filesystem operation on `path` failed
+    pub fn io(context: &str, path: &str, err: io::Error) -> Self {
+        let kind = match err.kind() {
+            io::ErrorKind::NotFound => ErrorKind::PathNotFound,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+            _ => ErrorKind::Failed,
+        };
+        Self::new(kind, format!("{} '{}': {}", context, path, err))
+    }
+
+    // A git (or git-like) command ran and exited with an error
+    pub fn git(context: &str, output: &CommandOutput) -> Self {
+        let combined = output.combined();
+        let kind = classify_git_output(&combined);
+        let message = match kind {
+            ErrorKind::NotARepo => "Not a git repository".to_string(),
+            ErrorKind::NoUpstream => "No remote tracking branch. This project may not have been pushed yet.".to_string(),
+            ErrorKind::AuthMissing => "Git could not authenticate. Sign in to GitHub and try again.".to_string(),
+            ErrorKind::PermissionDenied => "Permission denied. You don't have access to this repository.".to_string(),
+            ErrorKind::ToolMissing => format!("{}: required tool is not installed", context),
+            _ => format!("{}: {}", context, combined.trim()),
+        };
+        Self::new(kind, message).with_stderr(combined)
+    }
+
+    pub fn is(&self, kind: &ErrorKind) -> bool {
+        &self.kind == kind
+    }
+}
+
+// Map git's stderr onto an error kind
+pub fn classify_git_output(output: &str) -> ErrorKind {
+    let lower = output.to_lowercase();
+    if lower.contains("not a git repository") {
+        ErrorKind::NotARepo
+    } else if lower.contains("no tracking information") || lower.contains("no upstream branch") || lower.contains("no upstream configured") {
+        ErrorKind::NoUpstream
+    } else if lower.contains("authentication failed") || lower.contains("could not read username") || lower.contains("terminal prompts disabled") {
+        ErrorKind::AuthMissing
+    } else if lower.contains("permission denied") || lower.contains("403") || lower.contains("denied to push") {
+        ErrorKind::PermissionDenied
+    } else if lower.contains("conflict") || lower.contains("would be overwritten") || lower.contains("unmerged files") || lower.contains("non-fast-forward") || lower.contains("fetch first") {
+        ErrorKind::Conflict
+    } else if lower.contains("already exists") {
+        ErrorKind::AlreadyExists
+    } else if lower.contains("no such file or directory") || lower.contains("cannot change to") {
+        ErrorKind::PathNotFound
+    } else if lower.contains("command not found") || lower.contains("execvpe") {
+        ErrorKind::ToolMissing
+    } else {
+        ErrorKind::Failed
+    }
+}
+
+impl fmt::Display for KetraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for KetraError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_git_failures() {
+        let cases = [
+            ("fatal: not a git repository (or any of the parent directories): .git", ErrorKind::NotARepo),
+            ("There is no tracking information for the current branch.", ErrorKind::NoUpstream),
+            ("fatal: The current branch feature has no upstream branch.", ErrorKind::NoUpstream),
+            ("fatal: Authentication failed for 'https://github.com/a/b.git/'", ErrorKind::AuthMissing),
+            ("fatal: could not read Username for 'https://github.com': terminal prompts disabled", ErrorKind::AuthMissing),
+            ("git@github.com: Permission denied (publickey).", ErrorKind::PermissionDenied),
+            ("The requested URL returned error: 403", ErrorKind::PermissionDenied),
+            ("CONFLICT (content): Merge conflict in src/main.rs", ErrorKind::Conflict),
+            ("error: Your local changes to the following files would be overwritten by checkout:", ErrorKind::Conflict),
+            (" ! [rejected]        main -> main (fetch first)", ErrorKind::Conflict),
+            ("fatal: a branch named 'main' already exists", ErrorKind::AlreadyExists),
+            ("fatal: cannot change to '/nope': No such file or directory", ErrorKind::PathNotFound),
+            ("bash: git: command not found", ErrorKind::ToolMissing),
+            ("error: something else", ErrorKind::Failed),
+        ];
+
+        for (output, kind) in cases {
+            assert_eq!(classify_git_output(output), kind, "{}", output);
+        }
+    }
+
+    #[test]
+    fn git_errors_keep_the_raw_output() {
+        let output = CommandOutput::fail("There is no tracking information for the current branch.\n");
+        let err = KetraError::git("Pull failed", &output);
+        assert!(err.is(&ErrorKind::NoUpstream));
+        assert_eq!(err.stderr, output.stderr);
+    }
+
+    #[test]
+    fn serializes_kind_next_to_message() {
+        let err = KetraError::new(ErrorKind::GitHubApi { status: 422 }, "GitHub API error").with_stderr("name already exists");
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "GitHubApi",
+                "status": 422,
+                "message": "GitHub API error",
+                "stderr": "name already exists"
+            })
+        );
+    }
+
+    #[test]
+    fn missing_programs_are_tool_errors() {
+        let err = KetraError::spawn("Failed to execute git", io::Error::new(io::ErrorKind::NotFound, "not found"));
+        assert!(err.is(&ErrorKind::ToolMissing));
+    }
+}
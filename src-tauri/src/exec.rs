@@ -0,0 +1,341 @@
+use crate::operations::{operations, Pid, WSL_PID_MARKER};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+
+// A process to run, described independently of how it gets executed so the
+// git/WSL/GitHub flows can be driven by a fake runner in tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub dir: Option<String>,
+    // Piped to the process, e.g. a patch for `git apply -`
+    pub stdin: Option<String>,
+    // The cancellable operation the process belongs to; see operations::tracked
+    pub operation: Option<String>,
+}
+
+impl CommandSpec {
+    pub fn new(program: &str) -> Self {
+        CommandSpec {
+            program: program.to_string(),
+            args: Vec::new(),
+            dir: None,
+            stdin: None,
+            operation: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|a| a.to_string()));
+        self
+    }
+
+    pub fn dir(mut self, dir: &str) -> Self {
+        self.dir = Some(dir.to_string());
+        self
+    }
+
+    pub fn stdin(mut self, input: &str) -> Self {
+        self.stdin = Some(input.to_string());
+        self
+    }
+}
+
+impl fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[cfg(test)]
+impl CommandOutput {
+    pub fn ok(stdout: &str) -> Self {
+        CommandOutput {
+            success: true,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    pub fn fail(stderr: &str) -> Self {
+        CommandOutput {
+            success: false,
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+        }
+    }
+}
+
+impl CommandOutput {
+    // stdout followed by stderr, for matching git's error messages
+    pub fn combined(&self) -> String {
+        format!("{}{}", self.stdout, self.stderr)
+    }
+}
+
+pub trait CommandRunner: Send + Sync {
+    // Run to completion and capture the output
+    fn output(&self, spec: &CommandSpec) -> io::Result<CommandOutput>;
+
+    // Start without waiting (editors, terminals, file managers)
+    fn spawn(&self, spec: &CommandSpec) -> io::Result<()>;
+
+    // Like output, but hands each line of stdout and stderr to `on_line` as it
+    // arrives. Lines end at '\n' or '\r', which git uses to redraw progress.
+    fn stream(&self, spec: &CommandSpec, on_line: &mut dyn FnMut(&str)) -> io::Result<CommandOutput> {
+        let output = self.output(spec)?;
+        for line in output.combined().split(['\r', '\n']).filter(|l| !l.is_empty()) {
+            on_line(line);
+        }
+        Ok(output)
+    }
+
+    // Where $HOME inside WSL is kept once known; None asks every time
+    fn wsl_home(&self) -> Option<&OnceLock<String>> {
+        None
+    }
+}
+
+pub struct SystemRunner;
+
+impl SystemRunner {
+    fn command(spec: &CommandSpec) -> Command {
+        let mut cmd = Command::new(&spec.program);
+        cmd.args(&spec.args);
+        if let Some(dir) = &spec.dir {
+            cmd.current_dir(dir);
+        }
+        // Its own process group, so cancelling can kill its children too
+        #[cfg(unix)]
+        if spec.operation.is_some() {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        cmd
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, spec: &CommandSpec) -> io::Result<CommandOutput> {
+        // Streaming is what registers the process with its operation
+        if spec.operation.is_some() && spec.stdin.is_none() {
+            return self.stream(spec, &mut |_| {});
+        }
+
+        let output = match &spec.stdin {
+            None => Self::command(spec).output()?,
+            Some(input) => {
+                let mut child = Self::command(spec)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+                // Written from another thread so a process that fills its
+                // stdout before reading all of stdin can't deadlock us
+                let mut pipe = child.stdin.take().expect("stdin is piped");
+                let input = input.clone();
+                let writer = thread::spawn(move || pipe.write_all(input.as_bytes()));
+                let output = child.wait_with_output()?;
+                writer.join().ok();
+                output
+            }
+        };
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> io::Result<()> {
+        Self::command(spec).spawn()?;
+        Ok(())
+    }
+
+    // It doesn't change while ketra runs, and asking costs a wsl.exe start
+    fn wsl_home(&self) -> Option<&OnceLock<String>> {
+        static WSL_HOME: OnceLock<String> = OnceLock::new();
+        Some(&WSL_HOME)
+    }
+
+    fn stream(&self, spec: &CommandSpec, on_line: &mut dyn FnMut(&str)) -> io::Result<CommandOutput> {
+        let mut child = Self::command(spec)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let operation = spec.operation.as_deref();
+        if let Some(id) = operation {
+            if !operations().attach(id, Pid::Host(child.id())) {
+                child.kill().ok();
+            }
+        }
+
+        // Both pipes are read on their own threads so neither can fill up
+        // and stall the process; lines come back here in arrival order
+        let (tx, rx) = mpsc::channel();
+        let readers = [
+            read_lines(child.stdout.take().expect("stdout is piped"), false, tx.clone()),
+            read_lines(child.stderr.take().expect("stderr is piped"), true, tx),
+        ];
+
+        let (mut stdout, mut stderr) = (String::new(), String::new());
+        for (is_stderr, line, ending) in rx {
+            if let (Some(id), true) = (operation, is_stderr) {
+                if let Some(pid) = line.strip_prefix(WSL_PID_MARKER).and_then(|pid| pid.parse().ok()) {
+                    operations().attach(id, Pid::Wsl(pid));
+                    continue;
+                }
+            }
+            if !line.is_empty() {
+                on_line(&line);
+            }
+            let out = if is_stderr { &mut stderr } else { &mut stdout };
+            out.push_str(&line);
+            out.push(ending);
+        }
+        for reader in readers {
+            reader.join().ok();
+        }
+        let status = child.wait()?;
+        if let Some(id) = operation {
+            operations().detach(id);
+        }
+
+        Ok(CommandOutput {
+            success: status.success(),
+            stdout,
+            stderr,
+        })
+    }
+}
+
+// Send each '\n' or '\r' terminated line of a pipe, with its terminator
+fn read_lines(mut pipe: impl Read + Send + 'static, is_stderr: bool, tx: mpsc::Sender<(bool, String, char)>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut pending = Vec::new();
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            for &byte in &buf[..n] {
+                if byte == b'\n' || byte == b'\r' {
+                    let line = String::from_utf8_lossy(&pending).to_string();
+                    pending.clear();
+                    if tx.send((is_stderr, line, byte as char)).is_err() {
+                        return;
+                    }
+                } else {
+                    pending.push(byte);
+                }
+            }
+        }
+        if !pending.is_empty() {
+            tx.send((is_stderr, String::from_utf8_lossy(&pending).to_string(), '\n')).ok();
+        }
+    })
+}
+
+static SYSTEM: SystemRunner = SystemRunner;
+
+// The runner used by the Tauri commands
+pub fn system() -> &'static dyn CommandRunner {
+    &SYSTEM
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use std::sync::Mutex;
+
+    enum Reply {
+        Output(CommandOutput),
+        Missing,
+    }
+
+    // Answers commands from canned replies and records every command line.
+    // A reply is used for the first command whose line contains its pattern;
+    // anything unmatched succeeds with empty output.
+    #[derive(Default)]
+    pub struct FakeRunner {
+        replies: Vec<(String, Reply)>,
+        calls: Mutex<Vec<CommandSpec>>,
+        // Each fake has its own WSL, so its own home
+        home: OnceLock<String>,
+    }
+
+    impl FakeRunner {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn on(mut self, pattern: &str, output: CommandOutput) -> Self {
+            self.replies.push((pattern.to_string(), Reply::Output(output)));
+            self
+        }
+
+        // Simulate a program that is not installed
+        pub fn missing(mut self, pattern: &str) -> Self {
+            self.replies.push((pattern.to_string(), Reply::Missing));
+            self
+        }
+
+        pub fn calls(&self) -> Vec<CommandSpec> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        pub fn lines(&self) -> Vec<String> {
+            self.calls().iter().map(|c| c.to_string()).collect()
+        }
+
+        pub fn ran(&self, pattern: &str) -> bool {
+            self.lines().iter().any(|l| l.contains(pattern))
+        }
+
+        fn reply(&self, spec: &CommandSpec) -> io::Result<CommandOutput> {
+            self.calls.lock().unwrap().push(spec.clone());
+            let line = spec.to_string();
+            match self.replies.iter().find(|(pattern, _)| line.contains(pattern.as_str())) {
+                Some((_, Reply::Output(output))) => Ok(output.clone()),
+                Some((_, Reply::Missing)) => Err(io::Error::new(io::ErrorKind::NotFound, "program not found")),
+                None => Ok(CommandOutput::ok("")),
+            }
+        }
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn output(&self, spec: &CommandSpec) -> io::Result<CommandOutput> {
+            self.reply(spec)
+        }
+
+        fn spawn(&self, spec: &CommandSpec) -> io::Result<()> {
+            self.reply(spec).map(|_| ())
+        }
+
+        fn wsl_home(&self) -> Option<&OnceLock<String>> {
+            Some(&self.home)
+        }
+    }
+}
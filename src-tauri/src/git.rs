@@ -1,387 +1,802 @@
-use crate::types::GitStatus;
-use std::process::Command;
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::{CommandOutput, CommandRunner};
+use crate::operations::{self, OperationKind};
+use crate::progress::{run_git_with_progress, ProgressSink};
+use crate::types::{GitOperation, GitStatus};
+use git2::{Repository, RepositoryState, Status, StatusOptions};
+use std::path::Path;
+
+fn git(runner: &dyn CommandRunner, env: Environment, path: &str, args: &[&str]) -> std::io::Result<CommandOutput> {
+    runner.output(&env.command("git", args, Some(path)))
+}
+
+// Stdout of a git command run in `path`; `context` starts the error message
+pub fn run_git(runner: &dyn CommandRunner, env: Environment, path: &str, args: &[&str], context: &str) -> Result<String, KetraError> {
+    match git(runner, env, path, args) {
+        Ok(o) if o.success => Ok(o.stdout),
+        Ok(o) => Err(KetraError::git(context, &o)),
+        Err(e) => Err(KetraError::spawn(&format!("Failed to execute git {}", args[0]), e)),
+    }
+}
 
-pub fn get_git_status(path: &str) -> Option<GitStatus> {
-    // Check if .git folder exists
-    let git_path = format!("{}/.git", path.replace("\\", "/"));
-    if !std::path::Path::new(&git_path).exists() {
+pub fn get_git_status(runner: &dyn CommandRunner, path: &str, env: Environment) -> Option<GitStatus> {
+    // A folder in a plain repo, a file in worktrees and submodules
+    if !env.path_exists(runner, &env.join(path, ".git")) {
         return None;
     }
 
-    // Get branch name
-    let branch_output = Command::new("git")
-        .args(&["-C", path, "branch", "--show-current"])
-        .output()
-        .ok()?;
-    let branch = String::from_utf8_lossy(&branch_output.stdout).trim().to_string();
-
-    // Get status
-    let status_output = Command::new("git")
-        .args(&["-C", path, "status", "--porcelain"])
-        .output()
-        .ok()?;
-    let status_str = String::from_utf8_lossy(&status_output.stdout);
-    let uncommitted_files = status_str.lines().count() as i32;
-    let is_clean = uncommitted_files == 0;
-
-    // Get ahead/behind counts
-    let rev_output = Command::new("git")
-        .args(&["-C", path, "rev-list", "--left-right", "--count", "HEAD...@{u}"])
-        .output();
-
-    let (commits_ahead, commits_behind) = if let Ok(output) = rev_output {
-        let rev_str = String::from_utf8_lossy(&output.stdout);
-        let parts: Vec<&str> = rev_str.trim().split_whitespace().collect();
-        if parts.len() == 2 {
-            (
-                parts[0].parse().unwrap_or(0),
-                parts[1].parse().unwrap_or(0),
-            )
-        } else {
-            (0, 0)
+    // Local repos are read in-process; WSL repos, and anything libgit2
+    // refuses to open (e.g. a repo owned by another user), use the git CLI
+    if env.is_local() {
+        match status_in_process(path) {
+            Ok(status) => return Some(status),
+            Err(e) => println!("[DEBUG] libgit2 status failed for {}, using git: {}", path, e.message()),
         }
-    } else {
-        (0, 0)
-    };
-
-    Some(GitStatus {
-        branch,
-        is_clean,
-        commits_ahead,
-        commits_behind,
-        uncommitted_files,
-    })
+    }
+    status_with_cli(runner, path, env)
 }
 
-pub fn get_git_status_wsl(path: &str) -> Option<GitStatus> {
-    // Check if git repo exists
-    let check = Command::new("wsl")
-        .args(&["test", "-d", &format!("{}/.git", path)])
-        .status()
-        .ok()?;
+// Same answers as the CLI below: `status --porcelain=v2 --branch` counts,
+// the stash reflog and the marker files of an unfinished operation
+fn status_in_process(path: &str) -> Result<GitStatus, git2::Error> {
+    let repo = Repository::open(path)?;
 
-    if !check.success() {
-        return None;
-    }
+    let head = repo.find_reference("HEAD")?;
+    let branch = head
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .unwrap_or("")
+        .to_string();
 
-    // Get branch name
-    let branch_output = Command::new("wsl")
-        .args(&["bash", "-c", &format!("cd {} && git branch --show-current 2>/dev/null || echo ''", path)])
-        .output()
-        .ok()?;
-    let branch = String::from_utf8_lossy(&branch_output.stdout).trim().to_string();
-
-    // Get status
-    let status_output = Command::new("wsl")
-        .args(&["bash", "-c", &format!("cd {} && git status --porcelain 2>/dev/null || echo ''", path)])
-        .output()
-        .ok()?;
-    let status_str = String::from_utf8_lossy(&status_output.stdout);
-    let uncommitted_files = status_str.lines().filter(|l| !l.is_empty()).count() as i32;
-    let is_clean = uncommitted_files == 0;
-
-    // Get ahead/behind
-    let rev_output = Command::new("wsl")
-        .args(&["bash", "-c", &format!("cd {} && git rev-list --left-right --count HEAD...@{{u}} 2>/dev/null || echo '0 0'", path)])
-        .output();
-
-    let (commits_ahead, commits_behind) = if let Ok(output) = rev_output {
-        let rev_str = String::from_utf8_lossy(&output.stdout);
-        let parts: Vec<&str> = rev_str.trim().split_whitespace().collect();
-        if parts.len() >= 2 {
-            (
-                parts[0].parse().unwrap_or(0),
-                parts[1].parse().unwrap_or(0),
-            )
-        } else {
-            (0, 0)
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .renames_head_to_index(true);
+    let statuses = repo.statuses(Some(&mut options))?;
+
+    let mut counts = StatusCounts::default();
+    for entry in statuses.iter() {
+        let flags = entry.status();
+        if flags.is_conflicted() {
+            counts.conflicted += 1;
+            continue;
+        }
+        if flags.is_wt_new() {
+            counts.untracked += 1;
+            continue;
         }
+        let staged = flags.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE);
+        let modified = flags.intersects(Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE);
+        counts.add(staged, modified);
+    }
+
+    // An unborn branch has no commit yet: nothing to compare or date
+    let head_commit = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+
+    let upstream_ref = if branch.is_empty() {
+        None
+    } else {
+        repo.branch_upstream_name(&format!("refs/heads/{}", branch))
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_string))
+    };
+    let upstream = upstream_ref.as_deref().map(|name| {
+        name.strip_prefix("refs/remotes/")
+            .or_else(|| name.strip_prefix("refs/heads/"))
+            .unwrap_or(name)
+            .to_string()
+    });
+
+    let (commits_ahead, commits_behind) = head_commit
+        .as_ref()
+        .zip(upstream_ref.as_deref())
+        .and_then(|(commit, upstream)| {
+            let upstream = repo.find_reference(upstream).ok()?.target()?;
+            repo.graph_ahead_behind(commit.id(), upstream).ok()
+        })
+        .map(|(ahead, behind)| (ahead as i32, behind as i32))
+        .unwrap_or((0, 0));
+
+    let detached_at = if head.symbolic_target().is_none() {
+        head.target().map(|oid| short_hash(&oid.to_string()))
     } else {
-        (0, 0)
+        None
     };
 
-    Some(GitStatus {
+    let operation = match repo.state() {
+        RepositoryState::Merge => Some(GitOperation::Merge),
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge
+        | RepositoryState::ApplyMailbox
+        | RepositoryState::ApplyMailboxOrRebase => Some(GitOperation::Rebase),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some(GitOperation::CherryPick),
+        RepositoryState::Bisect => Some(GitOperation::Bisect),
+        _ => None,
+    };
+
+    let stash_count = repo.reflog("refs/stash").map(|log| log.len() as i32).unwrap_or(0);
+    let last_commit_time = head_commit.map(|c| c.committer().when().seconds().max(0) as u64).unwrap_or(0);
+
+    Ok(GitStatus {
         branch,
-        is_clean,
+        is_clean: counts.total() == 0,
         commits_ahead,
         commits_behind,
-        uncommitted_files,
+        uncommitted_files: counts.total(),
+        last_commit_time,
+        staged: counts.staged,
+        modified: counts.modified,
+        untracked: counts.untracked,
+        conflicted: counts.conflicted,
+        stash_count,
+        upstream,
+        detached_at,
+        operation,
     })
 }
 
-pub async fn git_pull(path: String, env: String) -> Result<String, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git pull 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "pull"])
-            .output()
-    };
-
-    match output {
-        Ok(o) => {
-            let stdout = String::from_utf8_lossy(&o.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&o.stderr).to_string();
+fn status_with_cli(runner: &dyn CommandRunner, path: &str, env: Environment) -> Option<GitStatus> {
+    let status_output = git(runner, env, path, &["status", "--porcelain=v2", "--branch"])
+        .ok()
+        .filter(|output| output.success)?;
+    let porcelain = parse_porcelain_v2(&status_output.stdout);
+
+    // Committer time of HEAD; fails on a repo without commits
+    let last_commit_time = git(runner, env, path, &["log", "-1", "--format=%ct"])
+        .ok()
+        .and_then(|output| output.stdout.trim().parse().ok())
+        .unwrap_or(0);
+
+    // Fails when nothing was ever stashed
+    let stash_count = git(runner, env, path, &["rev-list", "--walk-reflogs", "--count", "refs/stash"])
+        .ok()
+        .filter(|output| output.success)
+        .and_then(|output| output.stdout.trim().parse().ok())
+        .unwrap_or(0);
+
+    let counts = porcelain.counts;
+    Some(GitStatus {
+        is_clean: counts.total() == 0,
+        commits_ahead: porcelain.ahead,
+        commits_behind: porcelain.behind,
+        uncommitted_files: counts.total(),
+        last_commit_time,
+        staged: counts.staged,
+        modified: counts.modified,
+        untracked: counts.untracked,
+        conflicted: counts.conflicted,
+        stash_count,
+        detached_at: porcelain.detached.then(|| short_hash(&porcelain.oid)),
+        branch: porcelain.branch,
+        upstream: porcelain.upstream,
+        operation: operation_with_cli(runner, path, env),
+    })
+}
 
-            if o.status.success() {
-                Ok(stdout)
-            } else {
-                let combined = format!("{}{}", stdout, stderr);
-                if combined.contains("No such file or directory") || combined.contains("not a git repository") {
-                    Err("Not a git repository".to_string())
-                } else if combined.contains("no tracking information") || combined.contains("no upstream branch") {
-                    Err("No remote tracking branch. This project may not have been pushed yet.".to_string())
-                } else {
-                    Err(format!("Pull failed: {}", combined))
-                }
-            }
-        },
-        Err(e) => Err(format!("Failed to execute pull: {}", e)),
+// Marker files git leaves in the git dir, in the order libgit2 checks them
+const OPERATION_MARKERS: &[(&str, GitOperation)] = &[
+    ("rebase-merge", GitOperation::Rebase),
+    ("rebase-apply", GitOperation::Rebase),
+    ("MERGE_HEAD", GitOperation::Merge),
+    ("CHERRY_PICK_HEAD", GitOperation::CherryPick),
+    ("BISECT_LOG", GitOperation::Bisect),
+];
+
+fn operation_with_cli(runner: &dyn CommandRunner, path: &str, env: Environment) -> Option<GitOperation> {
+    let output = git(runner, env, path, &["rev-parse", "--absolute-git-dir"]).ok().filter(|o| o.success)?;
+    let git_dir = output.stdout.trim();
+
+    if env.is_local() {
+        return OPERATION_MARKERS
+            .iter()
+            .find(|(marker, _)| Path::new(git_dir).join(marker).exists())
+            .map(|(_, operation)| *operation);
     }
+
+    // One call for every marker: print the ones that exist
+    let mut args = vec!["-c", r#"cd "$1" && shift && for f; do [ -e "$f" ] && echo "$f"; done; true"#, "sh", git_dir];
+    args.extend(OPERATION_MARKERS.iter().map(|(marker, _)| *marker));
+    let output = runner.output(&env.command("sh", &args, None)).ok()?;
+    let present: Vec<&str> = output.stdout.lines().map(str::trim).collect();
+    OPERATION_MARKERS
+        .iter()
+        .find(|(marker, _)| present.contains(marker))
+        .map(|(_, operation)| *operation)
 }
 
-pub async fn git_clone(repo_url: String, env: String, base_folder: String) -> Result<String, String> {
-    // Extract repo name from URL
-    let repo_name = repo_url
-        .trim_end_matches(".git")
-        .split('/')
-        .last()
-        .ok_or("Invalid repository URL")?
-        .to_string();
+// Same length as `git log --oneline` in most repos
+fn short_hash(hash: &str) -> String {
+    hash.chars().take(7).collect()
+}
 
-    let _clone_path = if env == "wsl" {
-        format!("{}/{}", base_folder, repo_name)
-    } else {
-        format!("{}\\{}", base_folder, repo_name)
-    };
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct StatusCounts {
+    // Tracked paths with a change in the index, the working tree or both
+    changed: i32,
+    staged: i32,
+    modified: i32,
+    untracked: i32,
+    conflicted: i32,
+}
 
-    // Clone the repository
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git clone {} 2>&1", base_folder, repo_url)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &base_folder, "clone", &repo_url])
-            .output()
-    };
+impl StatusCounts {
+    fn add(&mut self, staged: bool, modified: bool) {
+        self.changed += 1;
+        self.staged += staged as i32;
+        self.modified += modified as i32;
+    }
 
-    match output {
-        Ok(o) => {
-            if o.status.success() {
-                Ok(repo_name)
-            } else {
-                let error = String::from_utf8_lossy(&o.stderr).to_string();
-                Err(format!("Git clone failed: {}", error))
-            }
-        }
-        Err(e) => Err(format!("Failed to execute git clone: {}", e))
+    fn total(&self) -> i32 {
+        self.changed + self.untracked + self.conflicted
     }
 }
 
-// Get list of all branches
-pub fn get_branches(path: String, env: String) -> Result<Vec<String>, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git branch --all 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "branch", "--all"])
-            .output()
-    };
+#[derive(Debug, Default, PartialEq)]
+struct Porcelain {
+    branch: String,
+    oid: String,
+    detached: bool,
+    upstream: Option<String>,
+    ahead: i32,
+    behind: i32,
+    counts: StatusCounts,
+}
 
-    match output {
-        Ok(o) => {
-            if o.status.success() {
-                let branches: Vec<String> = String::from_utf8_lossy(&o.stdout)
-                    .lines()
-                    .map(|line| line.trim().trim_start_matches("* ").trim_start_matches("remotes/origin/").to_string())
-                    .filter(|b| !b.is_empty() && !b.contains("HEAD ->"))
-                    .collect();
-                Ok(branches)
-            } else {
-                Err("Failed to get branches".to_string())
+// Parse `git status --porcelain=v2 --branch`. Header lines start with `#`;
+// entries are `1 XY ...` (changed), `2 XY ...` (renamed or copied),
+// `u XY ...` (unmerged) and `? path` (untracked). X is the index, Y the
+// working tree, and `.` means unchanged.
+fn parse_porcelain_v2(stdout: &str) -> Porcelain {
+    let mut status = Porcelain::default();
+
+    for line in stdout.lines() {
+        if let Some(header) = line.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" => status.oid = value.to_string(),
+                "branch.head" if value == "(detached)" => status.detached = true,
+                "branch.head" => status.branch = value.to_string(),
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    let mut parts = value.split_whitespace();
+                    status.ahead = parts.next().and_then(|a| a.trim_start_matches('+').parse().ok()).unwrap_or(0);
+                    status.behind = parts.next().and_then(|b| b.trim_start_matches('-').parse().ok()).unwrap_or(0);
+                }
+                _ => {}
             }
+            continue;
         }
-        Err(e) => Err(format!("Failed to execute git branch: {}", e))
+
+        let mut fields = line.splitn(3, ' ');
+        let kind = fields.next().unwrap_or("");
+        let xy = fields.next().unwrap_or("");
+        match kind {
+            "1" | "2" => {
+                let mut codes = xy.chars();
+                let (x, y) = (codes.next().unwrap_or('.'), codes.next().unwrap_or('.'));
+                status.counts.add(x != '.', y != '.');
+            }
+            "u" => status.counts.conflicted += 1,
+            "?" => status.counts.untracked += 1,
+            _ => {}
+        }
+    }
+
+    // "(initial)" on an unborn branch
+    if status.oid.starts_with('(') {
+        status.oid.clear();
     }
+    status
 }
 
-// Switch to a different branch
-pub async fn switch_branch(path: String, env: String, branch: String) -> Result<String, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git checkout {} 2>&1", path, branch)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "checkout", &branch])
-            .output()
-    };
+pub async fn git_pull(runner: &dyn CommandRunner, path: String, env: Environment, progress: &dyn ProgressSink) -> Result<String, KetraError> {
+    let operation = operations::begin(progress.operation_id(), OperationKind::Pull, &env.file_name(&path), env)?;
+    let output = run_git_with_progress(runner, env, Some(&path), &["pull"], progress);
 
-    match output {
+    let result = match output {
         Ok(o) => {
-            if o.status.success() {
-                Ok(format!("Switched to branch '{}'", branch))
+            if o.success {
+                Ok(o.stdout)
             } else {
-                let error = String::from_utf8_lossy(&o.stderr).to_string();
-                Err(format!("Failed to switch branch: {}", error))
+                Err(KetraError::git("Pull failed", &o))
             }
-        }
-        Err(e) => Err(format!("Failed to execute git checkout: {}", e))
-    }
+        },
+        Err(e) => Err(KetraError::spawn("Failed to execute pull", e)),
+    };
+    operation.settle(runner, result)
 }
 
-// Create a new branch
-pub async fn create_branch(path: String, env: String, branch_name: String) -> Result<String, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git checkout -b {} 2>&1", path, branch_name)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "checkout", "-b", &branch_name])
-            .output()
+// Fetch every remote, dropping remote branches that were deleted upstream
+pub async fn git_fetch(runner: &dyn CommandRunner, path: String, env: Environment, progress: &dyn ProgressSink) -> Result<String, KetraError> {
+    let operation = operations::begin(progress.operation_id(), OperationKind::Fetch, &env.file_name(&path), env)?;
+    let output = run_git_with_progress(runner, env, Some(&path), &["fetch", "--all", "--prune"], progress);
+
+    let result = match output {
+        Ok(o) if o.success => Ok(o.stdout),
+        Ok(o) => Err(KetraError::git("Fetch failed", &o)),
+        Err(e) => Err(KetraError::spawn("Failed to execute fetch", e)),
     };
+    operation.settle(runner, result)
+}
 
-    match output {
-        Ok(o) => {
-            if o.status.success() {
-                Ok(format!("Created and switched to branch '{}'", branch_name))
-            } else {
-                let error = String::from_utf8_lossy(&o.stderr).to_string();
-                Err(format!("Failed to create branch: {}", error))
-            }
-        }
-        Err(e) => Err(format!("Failed to execute git checkout: {}", e))
+// Branch names are passed as plain arguments, but one starting with `-` would
+// still be read as an option by git. Also reject what git itself would refuse.
+pub fn validate_branch_name(name: &str) -> Result<(), KetraError> {
+    let invalid = name.is_empty()
+        || name.starts_with('-')
+        || name.starts_with('/')
+        || name.ends_with('/')
+        || name.ends_with('.')
+        || name.ends_with(".lock")
+        || name.contains("..")
+        || name.contains("@{")
+        || name.chars().any(|c| c.is_control() || c.is_whitespace() || "~^:?*[\\".contains(c));
+
+    if invalid {
+        return Err(KetraError::new(ErrorKind::InvalidInput, format!("Invalid branch name '{}'", name)));
     }
+    Ok(())
 }
 
-// Get commit history
-pub fn get_commit_history(path: String, env: String, limit: i32) -> Result<Vec<serde_json::Value>, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!(
-                "cd {} && git log -{} --pretty=format:'%H|%an|%ae|%at|%s' 2>&1",
-                path, limit
-            )])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "log", &format!("-{}", limit), "--pretty=format:%H|%an|%ae|%at|%s"])
-            .output()
-    };
+// Switch to a different branch
+pub async fn switch_branch(runner: &dyn CommandRunner, path: String, env: Environment, branch: String) -> Result<String, KetraError> {
+    validate_branch_name(&branch)?;
+    let output = git(runner, env, &path, &["checkout", &branch]);
 
     match output {
         Ok(o) => {
-            if o.status.success() {
-                let commits: Vec<serde_json::Value> = String::from_utf8_lossy(&o.stdout)
-                    .lines()
-                    .filter_map(|line| {
-                        let parts: Vec<&str> = line.splitn(5, '|').collect();
-                        if parts.len() == 5 {
-                            Some(serde_json::json!({
-                                "hash": parts[0],
-                                "author": parts[1],
-                                "email": parts[2],
-                                "timestamp": parts[3].parse::<i64>().unwrap_or(0),
-                                "message": parts[4]
-                            }))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                Ok(commits)
+            if o.success {
+                Ok(format!("Switched to branch '{}'", branch))
             } else {
-                Err("Failed to get commit history".to_string())
+                Err(KetraError::git("Failed to switch branch", &o))
             }
         }
-        Err(e) => Err(format!("Failed to execute git log: {}", e))
+        Err(e) => Err(KetraError::spawn("Failed to execute git checkout", e))
     }
 }
 
-// Get git diff
-pub fn get_diff(path: String, env: String) -> Result<String, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git diff HEAD 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "diff", "HEAD"])
-            .output()
-    };
+// Create a new branch
+pub async fn create_branch(runner: &dyn CommandRunner, path: String, env: Environment, branch_name: String) -> Result<String, KetraError> {
+    validate_branch_name(&branch_name)?;
+    let output = git(runner, env, &path, &["checkout", "-b", &branch_name]);
 
     match output {
         Ok(o) => {
-            if o.status.success() {
-                Ok(String::from_utf8_lossy(&o.stdout).to_string())
+            if o.success {
+                Ok(format!("Created and switched to branch '{}'", branch_name))
             } else {
-                Err("Failed to get diff".to_string())
+                Err(KetraError::git("Failed to create branch", &o))
             }
         }
-        Err(e) => Err(format!("Failed to execute git diff: {}", e))
+        Err(e) => Err(KetraError::spawn("Failed to execute git checkout", e))
     }
 }
 
-// Git stash
-pub async fn git_stash(path: String, env: String) -> Result<String, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git stash 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "stash"])
-            .output()
-    };
+pub async fn git_stash(runner: &dyn CommandRunner, path: String, env: Environment) -> Result<String, KetraError> {
+    let output = git(runner, env, &path, &["stash"]);
 
     match output {
         Ok(o) => {
-            if o.status.success() {
-                let result = String::from_utf8_lossy(&o.stdout).to_string();
-                if result.contains("No local changes to save") {
+            if o.success {
+                if o.stdout.contains("No local changes to save") {
                     Ok("No changes to stash".to_string())
                 } else {
                     Ok("Changes stashed successfully".to_string())
                 }
             } else {
-                Err("Failed to stash changes".to_string())
+                Err(KetraError::git("Failed to stash changes", &o))
             }
         }
-        Err(e) => Err(format!("Failed to execute git stash: {}", e))
+        Err(e) => Err(KetraError::spawn("Failed to execute git stash", e))
     }
 }
 
 // Git stash pop
-pub async fn git_stash_pop(path: String, env: String) -> Result<String, String> {
-    let output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git stash pop 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "stash", "pop"])
-            .output()
-    };
+pub async fn git_stash_pop(runner: &dyn CommandRunner, path: String, env: Environment) -> Result<String, KetraError> {
+    let output = git(runner, env, &path, &["stash", "pop"]);
 
     match output {
         Ok(o) => {
-            if o.status.success() {
+            if o.success {
                 Ok("Stash applied successfully".to_string())
             } else {
-                let error = String::from_utf8_lossy(&o.stderr).to_string();
-                if error.contains("No stash entries found") {
-                    Err("No stashed changes to restore".to_string())
+                if o.stderr.contains("No stash entries found") {
+                    Err(KetraError::failed("No stashed changes to restore").with_stderr(o.stderr))
                 } else {
-                    Err(format!("Failed to apply stash: {}", error))
+                    Err(KetraError::git("Failed to apply stash", &o))
                 }
             }
         }
-        Err(e) => Err(format!("Failed to execute git stash pop: {}", e))
+        Err(e) => Err(KetraError::spawn("Failed to execute git stash pop", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::progress::record::Recorder;
+    use crate::progress::ProgressEvent;
+
+    const WSL_REPO: &str = "/home/dev/ketra/app";
+
+    #[test]
+    fn parses_porcelain_v2() {
+        let stdout = "\
+# branch.oid 4b825dc642cb6eb9a060e54bf8d69288fbee4904
+# branch.head feature/login
+# branch.upstream origin/feature/login
+# branch.ab +3 -1
+1 M. N... 100644 100644 100644 aaa bbb src/main.rs
+1 MM N... 100644 100644 100644 aaa bbb src/lib.rs
+1 .D N... 100644 100644 000000 aaa aaa old file.txt
+2 R. N... 100644 100644 100644 aaa aaa R100 new.rs\told.rs
+u UU N... 100644 100644 100644 100644 aaa bbb ccc README.md
+? notes.md
+? build/
+";
+        let status = parse_porcelain_v2(stdout);
+        assert_eq!(status.branch, "feature/login");
+        assert!(!status.detached);
+        assert_eq!(status.upstream.as_deref(), Some("origin/feature/login"));
+        assert_eq!((status.ahead, status.behind), (3, 1));
+        assert_eq!(
+            status.counts,
+            StatusCounts { changed: 4, staged: 3, modified: 2, untracked: 2, conflicted: 1 }
+        );
+        assert_eq!(status.counts.total(), 7);
+    }
+
+    #[test]
+    fn parses_detached_and_unborn_heads() {
+        let detached = parse_porcelain_v2("# branch.oid 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n# branch.head (detached)\n");
+        assert!(detached.detached);
+        assert_eq!(detached.branch, "");
+        assert_eq!(short_hash(&detached.oid), "4b825dc");
+
+        let unborn = parse_porcelain_v2("# branch.oid (initial)\n# branch.head trunk\n? README.md\n");
+        assert_eq!((unborn.branch.as_str(), unborn.oid.as_str(), unborn.upstream), ("trunk", "", None));
+        assert_eq!(unborn.counts.untracked, 1);
+    }
+
+    #[test]
+    fn status_is_none_outside_a_repo() {
+        let runner = FakeRunner::new().on("test -e", CommandOutput::fail(""));
+        assert!(get_git_status(&runner, WSL_REPO, Environment::Wsl).is_none());
+        assert!(!runner.ran("--exec git "));
+    }
+
+    #[test]
+    fn failing_status_is_not_a_clean_repo() {
+        let runner = FakeRunner::new().on("status --porcelain=v2", CommandOutput::fail("fatal: detected dubious ownership"));
+        assert!(get_git_status(&runner, WSL_REPO, Environment::Wsl).is_none());
+    }
+
+    #[test]
+    fn status_counts_changes_and_divergence() {
+        let runner = FakeRunner::new()
+            .on(
+                "status --porcelain=v2 --branch",
+                CommandOutput::ok(
+                    "# branch.oid abc\n# branch.head feature\n# branch.upstream origin/feature\n# branch.ab +2 -5\n\
+                     1 .M N... 100644 100644 100644 aaa aaa src/main.rs\n? notes.md\n",
+                ),
+            )
+            .on("log -1 --format=%ct", CommandOutput::ok("1700000000\n"))
+            .on("--walk-reflogs --count refs/stash", CommandOutput::ok("2\n"));
+
+        let status = get_git_status(&runner, WSL_REPO, Environment::Wsl).unwrap();
+        assert_eq!(status.branch, "feature");
+        assert!(!status.is_clean);
+        assert_eq!(status.uncommitted_files, 2);
+        assert_eq!((status.staged, status.modified, status.untracked, status.conflicted), (0, 1, 1, 0));
+        assert_eq!((status.commits_ahead, status.commits_behind), (2, 5));
+        assert_eq!(status.upstream.as_deref(), Some("origin/feature"));
+        assert_eq!(status.stash_count, 2);
+        assert_eq!(status.last_commit_time, 1700000000);
+        assert_eq!(status.detached_at, None);
+        assert_eq!(status.operation, None);
+    }
+
+    #[test]
+    fn status_without_upstream_reports_zero_divergence() {
+        let runner = FakeRunner::new()
+            .on("status --porcelain=v2", CommandOutput::ok("# branch.oid abc\n# branch.head main\n"))
+            .on("refs/stash", CommandOutput::fail("fatal: ambiguous argument 'refs/stash'"));
+
+        let status = get_git_status(&runner, WSL_REPO, Environment::Wsl).unwrap();
+        assert!(status.is_clean);
+        assert_eq!((status.commits_ahead, status.commits_behind), (0, 0));
+        assert_eq!(status.upstream, None);
+        assert_eq!(status.stash_count, 0);
+        assert_eq!(status.last_commit_time, 0);
+    }
+
+    #[test]
+    fn status_reports_a_detached_head_mid_rebase() {
+        let git_dir = format!("{}/.git", WSL_REPO);
+        let runner = FakeRunner::new()
+            .on(
+                "status --porcelain=v2",
+                CommandOutput::ok("# branch.oid 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n# branch.head (detached)\nu UU N... 1 1 1 1 a b c lib.rs\n"),
+            )
+            .on("--absolute-git-dir", CommandOutput::ok(&format!("{}\n", git_dir)))
+            .on("--exec sh -c", CommandOutput::ok("rebase-merge\n"));
+
+        let status = get_git_status(&runner, WSL_REPO, Environment::Wsl).unwrap();
+        assert_eq!(status.branch, "");
+        assert_eq!(status.detached_at.as_deref(), Some("4b825dc"));
+        assert_eq!(status.conflicted, 1);
+        assert_eq!(status.operation, Some(GitOperation::Rebase));
+
+        let markers = runner.calls().into_iter().find(|c| c.args.contains(&"-c".to_string())).unwrap();
+        assert_eq!(markers.args[5..], [git_dir.as_str(), "rebase-merge", "rebase-apply", "MERGE_HEAD", "CHERRY_PICK_HEAD", "BISECT_LOG"]);
+    }
+
+    #[tokio::test]
+    async fn pull_maps_missing_upstream() {
+        let runner = FakeRunner::new().on(
+            "git pull",
+            CommandOutput::fail("There is no tracking information for the current branch.\n"),
+        );
+
+        let err = git_pull(&runner, WSL_REPO.to_string(), Environment::Wsl, &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::NoUpstream));
+        assert!(err.message.starts_with("No remote tracking branch"));
+        assert!(err.stderr.contains("no tracking information"));
+    }
+
+    #[tokio::test]
+    async fn pull_maps_conflicts() {
+        let runner = FakeRunner::new().on(
+            "git pull",
+            CommandOutput::fail("CONFLICT (content): Merge conflict in README.md\nAutomatic merge failed\n"),
+        );
+
+        let err = git_pull(&runner, WSL_REPO.to_string(), Environment::Wsl, &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::Conflict));
+    }
+
+    #[tokio::test]
+    async fn pull_reports_missing_git() {
+        let runner = FakeRunner::new().missing("git pull");
+
+        let err = git_pull(&runner, WSL_REPO.to_string(), Environment::Wsl, &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::ToolMissing));
+    }
+
+    #[tokio::test]
+    async fn stash_pop_without_entries() {
+        let runner = FakeRunner::new().on("stash pop", CommandOutput::fail("No stash entries found.\n"));
+
+        let err = git_stash_pop(&runner, WSL_REPO.to_string(), Environment::Wsl).await.unwrap_err();
+        assert_eq!(err.message, "No stashed changes to restore");
+    }
+
+    #[tokio::test]
+    async fn hostile_branch_names_stay_one_argument() {
+        let runner = FakeRunner::new();
+        let branch = "feat/$(reboot)`id`;rm";
+        let path = "/home/dev/ketra/it's a $(reboot); app";
+
+        switch_branch(&runner, path.to_string(), Environment::Wsl, branch.to_string()).await.unwrap();
+        assert_eq!(runner.calls()[0].args, ["--cd", path, "--exec", "git", "checkout", branch]);
+    }
+
+    #[tokio::test]
+    async fn option_like_branch_names_are_rejected() {
+        for branch in ["--orphan", "-f", "a b", "a..b", "main.lock", "x~1", ""] {
+            let runner = FakeRunner::new();
+            let err = create_branch(&runner, WSL_REPO.to_string(), Environment::Wsl, branch.to_string()).await.unwrap_err();
+            assert!(err.is(&ErrorKind::InvalidInput), "{}", branch);
+            assert!(runner.calls().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_reports_progress_and_finishes() {
+        let runner = FakeRunner::new().on(
+            "git fetch",
+            CommandOutput { success: true, stdout: String::new(), stderr: "remote: Counting objects: 100% (3/3), done.\n".to_string() },
+        );
+        let progress = Recorder::default();
+
+        git_fetch(&runner, WSL_REPO.to_string(), Environment::Wsl, &progress).await.unwrap();
+        assert!(runner.ran(" git fetch --progress --all --prune"));
+
+        let events = progress.events();
+        assert!(matches!(&events[0], ProgressEvent::Progress { phase, percent: Some(100), done: true, .. } if phase == "Counting objects"));
+        assert!(matches!(&events[1], ProgressEvent::Finished { success: true, .. }));
+    }
+
+    // Real repositories for comparing libgit2 with the git CLI
+    mod local {
+        use super::*;
+        use crate::exec::system;
+        use std::fs;
+        use std::path::{Path, PathBuf};
+        use std::time::Instant;
+
+        fn temp_dir(name: &str) -> PathBuf {
+            let dir = std::env::temp_dir().join(format!("ketra-git-{}-{}", name, std::process::id()));
+            fs::remove_dir_all(&dir).ok();
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        fn git_output(dir: &Path, args: &[&str]) -> CommandOutput {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            system().output(&Environment::host().command("git", &full, Some(&dir.to_string_lossy()))).unwrap()
+        }
+
+        fn run_git(dir: &Path, args: &[&str]) {
+            let output = git_output(dir, args);
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        }
+
+        fn commit(dir: &Path, file: &str, content: &str) {
+            fs::write(dir.join(file), content).unwrap();
+            run_git(dir, &["add", file]);
+            run_git(dir, &["commit", "-q", "-m", file]);
+        }
+
+        fn both(dir: &Path) -> (GitStatus, GitStatus) {
+            let path = dir.to_string_lossy();
+            let cli = status_with_cli(system(), &path, Environment::host()).unwrap();
+            (status_in_process(&path).unwrap(), cli)
+        }
+
+        #[test]
+        fn in_process_status_matches_the_cli() {
+            let dir = temp_dir("status");
+            run_git(&dir, &["init", "-q", "-b", "main"]);
+            commit(&dir, "a.txt", "a");
+            commit(&dir, "b.txt", "b");
+            run_git(&dir, &["branch", "base"]);
+            commit(&dir, "c.txt", "c");
+            commit(&dir, "d.txt", "d");
+            run_git(&dir, &["checkout", "-q", "base"]);
+            commit(&dir, "e.txt", "e");
+            run_git(&dir, &["checkout", "-q", "main"]);
+            run_git(&dir, &["branch", "-q", "--set-upstream-to=base"]);
+
+            // Modified, renamed in the index, and an untracked folder of two files
+            fs::write(dir.join("a.txt"), "changed").unwrap();
+            run_git(&dir, &["mv", "b.txt", "renamed.txt"]);
+            fs::create_dir_all(dir.join("new")).unwrap();
+            fs::write(dir.join("new").join("x"), "").unwrap();
+            fs::write(dir.join("new").join("y"), "").unwrap();
+
+            let (lib, cli) = both(&dir);
+            assert_eq!(lib, cli);
+            assert_eq!(lib.branch, "main");
+            assert_eq!(lib.uncommitted_files, 3);
+            assert_eq!((lib.staged, lib.modified, lib.untracked), (1, 1, 1));
+            assert_eq!((lib.commits_ahead, lib.commits_behind), (2, 1));
+            assert_eq!(lib.upstream.as_deref(), Some("base"));
+            assert!(lib.last_commit_time > 0);
+
+            // Staged and then changed again counts as both
+            run_git(&dir, &["add", "a.txt"]);
+            fs::write(dir.join("a.txt"), "changed twice").unwrap();
+            run_git(&dir, &["stash", "-q"]);
+            fs::write(dir.join("a.txt"), "changed").unwrap();
+            run_git(&dir, &["add", "a.txt"]);
+            fs::write(dir.join("a.txt"), "changed twice").unwrap();
+            let (lib, cli) = both(&dir);
+            assert_eq!(lib, cli);
+            assert_eq!((lib.uncommitted_files, lib.staged, lib.modified), (2, 1, 1));
+            assert_eq!(lib.stash_count, 1);
+
+            run_git(&dir, &["checkout", "-q", "--detach"]);
+            let (lib, cli) = both(&dir);
+            assert_eq!(lib, cli);
+            assert_eq!(lib.branch, "");
+            assert_eq!(lib.upstream, None);
+            assert_eq!(lib.detached_at.as_ref().map(|h| h.len()), Some(7));
+
+            fs::remove_dir_all(&dir).ok();
+        }
+
+        #[test]
+        fn in_process_status_reports_conflicts_and_operations() {
+            let dir = temp_dir("merge");
+            run_git(&dir, &["init", "-q", "-b", "main"]);
+            commit(&dir, "a.txt", "base");
+            run_git(&dir, &["checkout", "-q", "-b", "other"]);
+            commit(&dir, "a.txt", "theirs");
+            run_git(&dir, &["checkout", "-q", "main"]);
+            commit(&dir, "a.txt", "ours");
+
+            assert!(!git_output(&dir, &["merge", "-q", "other"]).success);
+
+            let (lib, cli) = both(&dir);
+            assert_eq!(lib, cli);
+            assert_eq!((lib.conflicted, lib.uncommitted_files), (1, 1));
+            assert_eq!(lib.operation, Some(GitOperation::Merge));
+
+            run_git(&dir, &["merge", "--abort"]);
+            run_git(&dir, &["bisect", "start"]);
+            let (lib, cli) = both(&dir);
+            assert_eq!(lib, cli);
+            assert_eq!(lib.operation, Some(GitOperation::Bisect));
+            assert!(lib.is_clean);
+
+            fs::remove_dir_all(&dir).ok();
+        }
+
+        #[test]
+        fn in_process_status_of_an_empty_repo() {
+            let dir = temp_dir("unborn");
+            run_git(&dir, &["init", "-q", "-b", "trunk"]);
+            fs::write(dir.join("README.md"), "").unwrap();
+
+            let (lib, cli) = both(&dir);
+            assert_eq!(lib, cli);
+            assert_eq!(lib.branch, "trunk");
+            assert_eq!(lib.uncommitted_files, 1);
+            assert_eq!(lib.last_commit_time, 0);
+
+            fs::remove_dir_all(&dir).ok();
+        }
+
+        #[test]
+        fn status_of_a_worktree() {
+            let dir = temp_dir("worktree");
+            let main = dir.join("main");
+            fs::create_dir_all(&main).unwrap();
+            run_git(&main, &["init", "-q", "-b", "main"]);
+            commit(&main, "README.md", "hello\n");
+            run_git(&main, &["worktree", "add", "-q", "-b", "feature/x", "../linked"]);
+            fs::write(dir.join("linked").join("README.md"), "changed\n").unwrap();
+
+            let linked = dir.join("linked");
+            assert!(linked.join(".git").is_file());
+            let status = get_git_status(system(), &linked.to_string_lossy(), Environment::host()).unwrap();
+            assert_eq!(status.branch, "feature/x");
+            assert_eq!(status.modified, 1);
+            assert_eq!(status_with_cli(system(), &linked.to_string_lossy(), Environment::host()).unwrap(), status);
+
+            fs::remove_dir_all(&dir).ok();
+        }
+
+        // cargo test --release status_benchmark -- --ignored --nocapture
+        #[test]
+        #[ignore]
+        fn status_benchmark() {
+            const REPOS: usize = 100;
+            let base = temp_dir("bench");
+            let repos: Vec<PathBuf> = (0..REPOS)
+                .map(|i| {
+                    let dir = base.join(format!("repo-{}", i));
+                    fs::create_dir_all(dir.join("src")).unwrap();
+                    run_git(&dir, &["init", "-q", "-b", "main"]);
+                    for n in 0..50 {
+                        fs::write(dir.join("src").join(format!("file{}.rs", n)), format!("fn f{}() {{}}\n", n)).unwrap();
+                    }
+                    run_git(&dir, &["add", "."]);
+                    run_git(&dir, &["commit", "-q", "-m", "init"]);
+                    fs::write(dir.join("src").join("file0.rs"), "changed").unwrap();
+                    dir
+                })
+                .collect();
+
+            let time = |status: &dyn Fn(&str) -> Option<GitStatus>| {
+                let start = Instant::now();
+                for repo in &repos {
+                    assert!(status(&repo.to_string_lossy()).is_some());
+                }
+                start.elapsed()
+            };
+            let cli = time(&|path| status_with_cli(system(), path, Environment::host()));
+            let lib = time(&|path| status_in_process(path).ok());
+
+            println!("{} repos: git CLI {:?}, libgit2 {:?} ({:.1}x)", REPOS, cli, lib, cli.as_secs_f64() / lib.as_secs_f64());
+            fs::remove_dir_all(&base).ok();
+        }
     }
 }
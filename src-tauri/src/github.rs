@@ -1,67 +1,79 @@
-use std::process::Command;
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::{CommandOutput, CommandRunner, CommandSpec};
+use crate::operations::{self, OperationKind};
+use crate::progress::{run_git_with_progress, ProgressSink};
 
-pub fn get_github_token() -> Result<String, String> {
+pub fn get_github_token(runner: &dyn CommandRunner) -> Result<String, KetraError> {
     // Try to read GitHub token from environment or git config
     if let Ok(token) = std::env::var("GITHUB_TOKEN") {
         return Ok(token);
     }
 
     // Try gh CLI token
-    let gh_result = Command::new("gh")
-        .args(&["auth", "token"])
-        .output();
+    let gh_result = runner.output(&CommandSpec::new("gh").args(&["auth", "token"]));
 
     if let Ok(output) = gh_result {
-        if output.status.success() {
-            let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.success {
+            let token = output.stdout.trim().to_string();
             if !token.is_empty() {
                 return Ok(token);
             }
         }
     }
 
-    Err("GitHub token not found. Please run 'gh auth login' or set GITHUB_TOKEN environment variable.".to_string())
+    Err(KetraError::new(
+        ErrorKind::AuthMissing,
+        "GitHub token not found. Please run 'gh auth login' or set GITHUB_TOKEN environment variable.",
+    ))
 }
 
-pub fn check_github_auth() -> Result<String, String> {
+pub fn check_github_auth(runner: &dyn CommandRunner) -> Result<String, KetraError> {
     // Check if gh CLI is authenticated
-    let gh_result = Command::new("gh")
-        .args(&["auth", "status"])
-        .output();
+    let gh_result = runner.output(&CommandSpec::new("gh").args(&["auth", "status"]));
 
     match gh_result {
         Ok(output) => {
-            if output.status.success() {
+            if output.success {
                 // Get username
-                let username_result = Command::new("gh")
-                    .args(&["api", "user", "--jq", ".login"])
-                    .output();
+                let username_result = runner.output(&CommandSpec::new("gh").args(&["api", "user", "--jq", ".login"]));
 
                 if let Ok(user_output) = username_result {
-                    let username = String::from_utf8_lossy(&user_output.stdout).trim().to_string();
+                    let username = user_output.stdout.trim().to_string();
                     return Ok(username);
                 }
                 Ok("authenticated".to_string())
             } else {
-                Err("not_authenticated".to_string())
+                Err(KetraError::new(ErrorKind::AuthMissing, "not_authenticated").with_stderr(output.stderr))
             }
         },
-        Err(_) => Err("gh_not_installed".to_string())
+        Err(_) => Err(KetraError::new(ErrorKind::ToolMissing, "gh_not_installed"))
     }
 }
 
-pub fn github_login() -> Result<(), String> {
-    // Launch gh auth login
-    Command::new("cmd")
-        .args(&["/C", "start", "cmd", "/K", "gh", "auth", "login"])
-        .spawn()
-        .map_err(|e| format!("Failed to launch GitHub login: {}", e))?;
+pub fn github_login(runner: &dyn CommandRunner) -> Result<(), KetraError> {
+    // Launch gh auth login in a new terminal window
+    let spec = match Environment::host() {
+        Environment::Windows => CommandSpec::new("cmd").args(&["/C", "start", "cmd", "/K", "gh", "auth", "login"]),
+        Environment::Macos => {
+            CommandSpec::new("osascript").args(&["-e", r#"tell application "Terminal" to do script "gh auth login""#])
+        }
+        _ => CommandSpec::new("x-terminal-emulator").args(&["-e", "gh", "auth", "login"]),
+    };
+
+    runner.spawn(&spec).map_err(|e| KetraError::spawn("Failed to launch GitHub login", e))?;
 
     Ok(())
 }
 
-pub async fn create_github_repo(name: String) -> Result<String, String> {
-    let token = get_github_token()?;
+async fn api_error(response: reqwest::Response) -> KetraError {
+    let status = response.status().as_u16();
+    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+    KetraError::new(ErrorKind::GitHubApi { status }, format!("GitHub API error: {}", error_text)).with_stderr(error_text)
+}
+
+pub async fn create_github_repo(runner: &dyn CommandRunner, name: String) -> Result<String, KetraError> {
+    let token = get_github_token(runner)?;
 
     // Create private repo using GitHub API
     let client = reqwest::Client::new();
@@ -78,26 +90,25 @@ pub async fn create_github_repo(name: String) -> Result<String, String> {
         .json(&body)
         .send()
         .await
-        .map_err(|e| format!("Failed to create GitHub repo: {}", e))?;
+        .map_err(|e| KetraError::failed(format!("Failed to create GitHub repo: {}", e)))?;
 
     if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("GitHub API error: {}", error_text));
+        return Err(api_error(response).await);
     }
 
     let repo_data: serde_json::Value = response.json().await
-        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+        .map_err(|e| KetraError::failed(format!("Failed to parse GitHub response: {}", e)))?;
 
     let clone_url = repo_data["clone_url"]
         .as_str()
-        .ok_or("Failed to get clone URL from response")?
+        .ok_or_else(|| KetraError::failed("Failed to get clone URL from response"))?
         .to_string();
 
     Ok(clone_url)
 }
 
-pub async fn delete_github_repo(repo_name: String) -> Result<(), String> {
-    let token = match get_github_token() {
+pub async fn delete_github_repo(runner: &dyn CommandRunner, repo_name: String) -> Result<(), KetraError> {
+    let token = match get_github_token(runner) {
         Ok(t) => t,
         Err(e) => {
             // No GitHub token, skip GitHub deletion
@@ -107,14 +118,12 @@ pub async fn delete_github_repo(repo_name: String) -> Result<(), String> {
     };
 
     // Get GitHub username
-    let username_result = Command::new("gh")
-        .args(&["api", "user", "--jq", ".login"])
-        .output();
+    let username_result = runner.output(&CommandSpec::new("gh").args(&["api", "user", "--jq", ".login"]));
 
     let username = if let Ok(output) = username_result {
-        String::from_utf8_lossy(&output.stdout).trim().to_string()
+        output.stdout.trim().to_string()
     } else {
-        return Err("Failed to get GitHub username".to_string());
+        return Err(KetraError::new(ErrorKind::AuthMissing, "Failed to get GitHub username"));
     };
 
     // Delete repo using GitHub API
@@ -127,164 +136,222 @@ pub async fn delete_github_repo(repo_name: String) -> Result<(), String> {
         .header("User-Agent", "launcher-app")
         .send()
         .await
-        .map_err(|e| format!("Failed to delete GitHub repo: {}", e))?;
+        .map_err(|e| KetraError::failed(format!("Failed to delete GitHub repo: {}", e)))?;
 
     if !response.status().is_success() {
         // If repo doesn't exist (404), that's fine
         if response.status().as_u16() == 404 {
             return Ok(());
         }
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("GitHub API error: {}", error_text));
+        return Err(api_error(response).await);
     }
 
     Ok(())
 }
 
-pub async fn git_push(path: String, env: String, message: String) -> Result<String, String> {
-    // Check if remote exists
-    let remote_check = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git remote get-url origin 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "remote", "get-url", "origin"])
-            .output()
-    };
-
-    let has_remote = if let Ok(output) = remote_check {
-        output.status.success()
-    } else {
-        false
-    };
+// Turn a failed git push into an error the UI can act on
+fn push_error(output: &CommandOutput) -> KetraError {
+    let mut err = KetraError::git("Push failed", output);
+    if err.is(&ErrorKind::PermissionDenied) {
+        err.message = "Permission denied. You don't have push access to this repository. Fork it or create your own repo to push changes.".to_string();
+    }
+    err
+}
 
-    // If no remote, create GitHub repo and add it
-    if !has_remote {
-        // Extract project name from path
-        let project_name = if env == "wsl" {
-            path.split('/').last().unwrap_or("project")
-        } else {
-            path.split('\\').last().unwrap_or("project")
-        };
-
-        // Create GitHub repo
-        let clone_url = create_github_repo(project_name.to_string()).await?;
-
-        // Add remote
-        let add_remote = if env == "wsl" {
-            Command::new("wsl")
-                .args(&["bash", "-c", &format!("cd {} && git remote add origin {} 2>&1", path, clone_url)])
-                .output()
-        } else {
-            Command::new("git")
-                .args(&["-C", &path, "remote", "add", "origin", &clone_url])
-                .output()
-        };
-
-        if let Err(e) = add_remote {
-            return Err(format!("Failed to add remote: {}", e));
-        }
+// Stage everything, commit it and push: the one-click flow of the PUSH button
+pub async fn git_push(
+    runner: &dyn CommandRunner,
+    path: String,
+    env: Environment,
+    message: String,
+    progress: &dyn ProgressSink,
+) -> Result<String, KetraError> {
+    let git = |args: &[&str]| runner.output(&env.command("git", args, Some(&path)));
 
-        // Set upstream branch
-        let set_upstream = if env == "wsl" {
-            Command::new("wsl")
-                .args(&["bash", "-c", &format!("cd {} && git branch -M main 2>&1", path)])
-                .output()
-        } else {
-            Command::new("git")
-                .args(&["-C", &path, "branch", "-M", "main"])
-                .output()
-        };
-
-        set_upstream.ok(); // Ignore errors if branch is already main
-    }
+    let operation = operations::begin(progress.operation_id(), OperationKind::Push, &env.file_name(&path), env)?;
+    let created_remote = ensure_origin(runner, &path, env).await?;
 
     // Add all changes
-    let add_output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git add . 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "add", "."])
-            .output()
-    };
+    let add_output = git(&["add", "."]);
 
     if let Err(e) = add_output {
-        return Err(format!("Failed to add files: {}", e));
+        return Err(KetraError::spawn("Failed to add files", e));
     }
 
     // Check if there are changes to commit
-    let status_output = if env == "wsl" {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git status --porcelain 2>&1", path)])
-            .output()
-    } else {
-        Command::new("git")
-            .args(&["-C", &path, "status", "--porcelain"])
-            .output()
-    };
+    let status_output = git(&["status", "--porcelain"]);
 
     let has_changes = if let Ok(output) = status_output {
-        !String::from_utf8_lossy(&output.stdout).trim().is_empty()
+        !output.stdout.trim().is_empty()
     } else {
         false
     };
 
     // Commit if there are changes
     if has_changes {
-        let commit_output = if env == "wsl" {
-            Command::new("wsl")
-                .args(&["bash", "-c", &format!("cd {} && git commit -m '{}' 2>&1", path, message.replace("'", "'\\''"))])
-                .output()
-        } else {
-            Command::new("git")
-                .args(&["-C", &path, "commit", "-m", &message])
-                .output()
-        };
+        let commit_output = git(&["commit", "-m", &message]);
 
         if let Err(e) = commit_output {
-            return Err(format!("Failed to commit: {}", e));
+            return Err(KetraError::spawn("Failed to commit", e));
         }
     }
 
-    // Push (with -u flag if this is the first push to new remote)
-    let output = if env == "wsl" {
-        let push_cmd = if !has_remote {
-            format!("cd {} && git push -u origin main 2>&1", path)
-        } else {
-            format!("cd {} && git push 2>&1", path)
-        };
-        Command::new("wsl")
-            .args(&["bash", "-c", &push_cmd])
-            .output()
+    let result = push(runner, &path, env, created_remote, progress);
+    operation.settle(runner, result)
+}
+
+// Push commits that were already made, without staging or committing anything
+pub async fn push_commits(runner: &dyn CommandRunner, path: String, env: Environment, progress: &dyn ProgressSink) -> Result<String, KetraError> {
+    let operation = operations::begin(progress.operation_id(), OperationKind::Push, &env.file_name(&path), env)?;
+    let created_remote = ensure_origin(runner, &path, env).await?;
+    let result = push(runner, &path, env, created_remote, progress);
+    operation.settle(runner, result)
+}
+
+// Create a GitHub repo for a project without an `origin` remote. Returns
+// whether one was created.
+async fn ensure_origin(runner: &dyn CommandRunner, path: &str, env: Environment) -> Result<bool, KetraError> {
+    let git = |args: &[&str]| runner.output(&env.command("git", args, Some(path)));
+
+    // Check if remote exists
+    let remote_check = git(&["remote", "get-url", "origin"]);
+
+    let has_remote = if let Ok(output) = remote_check {
+        output.success
     } else {
-        let mut args = vec!["-C", &path, "push"];
-        if !has_remote {
-            args.extend_from_slice(&["-u", "origin", "main"]);
-        }
-        Command::new("git")
-            .args(&args)
-            .output()
+        false
     };
 
+    if has_remote {
+        return Ok(false);
+    }
+
+    // Extract project name from path
+    let project_name = env.file_name(path);
+
+    // Create GitHub repo
+    let clone_url = create_github_repo(runner, project_name).await?;
+
+    // Add remote
+    let add_remote = git(&["remote", "add", "origin", &clone_url]);
+
+    if let Err(e) = add_remote {
+        return Err(KetraError::spawn("Failed to add remote", e));
+    }
+
+    // Set upstream branch
+    let set_upstream = git(&["branch", "-M", "main"]);
+
+    set_upstream.ok(); // Ignore errors if branch is already main
+    Ok(true)
+}
+
+fn push(runner: &dyn CommandRunner, path: &str, env: Environment, created_remote: bool, progress: &dyn ProgressSink) -> Result<String, KetraError> {
+    // Push (with -u flag if this is the first push to new remote)
+    let mut args = vec!["push"];
+    if created_remote {
+        args.extend_from_slice(&["-u", "origin", "main"]);
+    }
+    let output = run_git_with_progress(runner, env, Some(path), &args, progress);
+
     match output {
         Ok(o) => {
-            let stdout = String::from_utf8_lossy(&o.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&o.stderr).to_string();
-
-            if o.status.success() {
-                Ok(stdout)
+            if o.success {
+                Ok(o.stdout)
             } else {
-                let combined = format!("{}{}", stdout, stderr);
-                if combined.contains("Permission denied") || combined.contains("403") || combined.contains("denied to push") {
-                    Err("Permission denied. You don't have push access to this repository. Fork it or create your own repo to push changes.".to_string())
-                } else {
-                    Err(format!("Push failed: {}", combined))
-                }
+                Err(push_error(&o))
             }
         },
-        Err(e) => Err(format!("Failed to execute push: {}", e)),
+        Err(e) => Err(KetraError::spawn("Failed to execute push", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::progress::record::Recorder;
+
+    const WSL_REPO: &str = "/home/dev/ketra/app";
+
+    #[test]
+    fn push_errors_explain_missing_access() {
+        let err = push_error(&CommandOutput::fail("ERROR: Permission denied (publickey)."));
+        assert!(err.is(&ErrorKind::PermissionDenied));
+        assert!(err.message.contains("Fork it"));
+
+        let err = push_error(&CommandOutput::fail(" ! [rejected]        main -> main (fetch first)"));
+        assert!(err.is(&ErrorKind::Conflict));
+        assert!(err.stderr.contains("fetch first"));
+    }
+
+    #[test]
+    fn auth_check_reports_missing_gh() {
+        let runner = FakeRunner::new().missing("gh auth status");
+        assert!(check_github_auth(&runner).unwrap_err().is(&ErrorKind::ToolMissing));
+    }
+
+    #[test]
+    fn auth_check_returns_username() {
+        let runner = FakeRunner::new().on("gh api user", CommandOutput::ok("octocat\n"));
+        assert_eq!(check_github_auth(&runner).unwrap(), "octocat");
+
+        let runner = FakeRunner::new().on("gh auth status", CommandOutput::fail("You are not logged into any GitHub hosts."));
+        assert!(check_github_auth(&runner).unwrap_err().is(&ErrorKind::AuthMissing));
+    }
+
+    #[tokio::test]
+    async fn push_with_existing_remote_commits_and_pushes() {
+        let runner = FakeRunner::new()
+            .on("remote get-url origin", CommandOutput::ok("https://github.com/dev/app.git\n"))
+            .on("status --porcelain", CommandOutput::ok(" M README.md\n"))
+            .on("git push", CommandOutput::ok("Everything up-to-date\n"));
+
+        git_push(&runner, WSL_REPO.to_string(), Environment::Wsl, "it's done".to_string(), &Recorder::default()).await.unwrap();
+
+        assert!(!runner.ran("remote add"));
+        assert!(runner.ran("--exec git commit -m it's done"));
+        assert!(runner.lines().last().unwrap().ends_with(" git push --progress"));
+    }
+
+    #[tokio::test]
+    async fn push_commits_never_stages_or_commits() {
+        let runner = FakeRunner::new().on("remote get-url origin", CommandOutput::ok("git@github.com:dev/app.git\n"));
+
+        push_commits(&runner, WSL_REPO.to_string(), Environment::Wsl, &Recorder::default()).await.unwrap();
+        assert!(!runner.ran("git add") && !runner.ran("git commit"));
+        assert!(runner.lines().last().unwrap().ends_with(" git push --progress"));
+    }
+
+    #[tokio::test]
+    async fn push_skips_commit_when_clean() {
+        let runner = FakeRunner::new().on("remote get-url origin", CommandOutput::ok("git@github.com:dev/app.git\n"));
+
+        git_push(&runner, WSL_REPO.to_string(), Environment::Wsl, "msg".to_string(), &Recorder::default()).await.unwrap();
+        assert!(!runner.ran("git commit"));
+    }
+
+    #[tokio::test]
+    async fn push_maps_permission_errors() {
+        let runner = FakeRunner::new()
+            .on("remote get-url origin", CommandOutput::ok("https://github.com/other/app.git\n"))
+            .on("git push", CommandOutput::fail("remote: Permission to other/app.git denied to dev.\nfatal: ... 403\n"));
+
+        let err = git_push(&runner, WSL_REPO.to_string(), Environment::Wsl, "msg".to_string(), &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::PermissionDenied));
+    }
+
+    #[tokio::test]
+    async fn push_without_remote_needs_a_token() {
+        if std::env::var("GITHUB_TOKEN").is_ok() {
+            return;
+        }
+        let runner = FakeRunner::new()
+            .on("remote get-url origin", CommandOutput::fail("error: No such remote 'origin'"))
+            .on("gh auth token", CommandOutput::fail("not logged in"));
+
+        let err = git_push(&runner, WSL_REPO.to_string(), Environment::Wsl, "msg".to_string(), &Recorder::default()).await.unwrap_err();
+        assert!(err.is(&ErrorKind::AuthMissing));
+        assert!(!runner.ran("git push"));
     }
 }
@@ -0,0 +1,596 @@
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::changes::{parse_numstat, LineCounts};
+use crate::exec::CommandRunner;
+use crate::git::run_git;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+// One NUL-separated field each; `-z` ends every commit with another NUL.
+// Nothing in a commit can contain a NUL, so subjects and bodies come through
+// untouched.
+const LOG_FORMAT: &str = "%H%x00%P%x00%an%x00%ae%x00%at%x00%cn%x00%ce%x00%ct%x00%D%x00%s%x00%b";
+const LOG_FIELDS: usize = 11;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    // Branch, tag or commit to start from; HEAD when unset
+    pub branch: Option<String>,
+    // Matched against author name and email, case-insensitively
+    pub author: Option<String>,
+    // Only commits touching this file or folder
+    pub path: Option<String>,
+    // Unix seconds, inclusive
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    // Text searched for in commit messages, case-insensitively
+    pub grep: Option<String>,
+    // Commits per page
+    pub limit: Option<u32>,
+    // `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RefKind {
+    // HEAD itself; also listed when it points at a branch
+    Head,
+    Branch,
+    Remote,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitRef {
+    // Short name, e.g. "main", "origin/main" or "v1.0"
+    pub name: String,
+    pub kind: RefKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Commit {
+    pub hash: String,
+    // First parent first; two or more for a merge, none for a root commit.
+    // With a path filter these are the nearest ancestors that touch the path,
+    // so the graph stays connected.
+    pub parents: Vec<String>,
+    pub author_name: String,
+    pub author_email: String,
+    // Unix seconds
+    pub author_time: u64,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub commit_time: u64,
+    pub subject: String,
+    pub body: String,
+    pub refs: Vec<CommitRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryPage {
+    // Newest first, children always before their parents
+    pub commits: Vec<Commit>,
+    // Pass back as `cursor` for the next page; None on the last page
+    pub next_cursor: Option<String>,
+}
+
+// A page of history. The first page pins the commit it starts from in the
+// cursor, so commits made while paging don't shift later pages.
+pub fn get_commit_history(runner: &dyn CommandRunner, path: String, env: Environment, query: HistoryQuery) -> Result<HistoryPage, KetraError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let (tip, skip) = match &query.cursor {
+        Some(cursor) => parse_cursor(cursor)?,
+        None => match resolve_tip(runner, &path, env, query.branch.as_deref())? {
+            Some(tip) => (tip, 0),
+            // Nothing committed yet
+            None => return Ok(HistoryPage { commits: Vec::new(), next_cursor: None }),
+        },
+    };
+
+    let args = log_args(&query, &tip, skip, limit)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let stdout = run_git(runner, env, &path, &args, "Failed to get commit history")?;
+
+    let mut commits = parse_log(&stdout);
+    // One extra commit was asked for to learn whether another page follows
+    let next_cursor = if commits.len() > limit as usize {
+        commits.truncate(limit as usize);
+        Some(format!("{}:{}", tip, skip + limit))
+    } else {
+        None
+    };
+    Ok(HistoryPage { commits, next_cursor })
+}
+
+// One file changed by a commit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitFile {
+    pub path: String,
+    // Where a renamed or copied file came from
+    pub original_path: Option<String>,
+    // A, M, D, R, C or T, as in `git diff --name-status`
+    pub status: char,
+    pub binary: bool,
+    // None for binary files
+    pub lines: Option<LineCounts>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitDetail {
+    pub commit: Commit,
+    // What the files are compared against: the first parent (also for
+    // merges), None for a root commit
+    pub base: Option<String>,
+    pub files: Vec<CommitFile>,
+}
+
+pub fn get_commit_detail(runner: &dyn CommandRunner, path: String, env: Environment, hash: String) -> Result<CommitDetail, KetraError> {
+    let commit = read_commit(runner, &path, env, &hash)?;
+    let files = commit_files(runner, &path, env, &commit)?;
+    Ok(CommitDetail {
+        base: commit.parents.first().cloned(),
+        commit,
+        files,
+    })
+}
+
+// The unified diff of a commit against its first parent, for the whole commit
+// or just `file`. A renamed file's patch includes where it came from.
+pub fn get_commit_patch(runner: &dyn CommandRunner, path: String, env: Environment, hash: String, file: Option<String>) -> Result<String, KetraError> {
+    let commit = read_commit(runner, &path, env, &hash)?;
+
+    let mut paths = Vec::new();
+    if let Some(file) = non_empty(&file) {
+        let files = commit_files(runner, &path, env, &commit)?;
+        let Some(changed) = files.iter().find(|f| f.path == file || f.original_path.as_deref() == Some(file)) else {
+            return Err(KetraError::new(ErrorKind::InvalidInput, format!("'{}' is not changed in {}", file, &commit.hash[..7])));
+        };
+        paths.extend(changed.original_path.clone());
+        paths.push(changed.path.clone());
+    }
+
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    run_git(runner, env, &path, &diff_tree_args(&commit, &["-p"], &paths), "Failed to get commit diff")
+}
+
+fn read_commit(runner: &dyn CommandRunner, path: &str, env: Environment, hash: &str) -> Result<Commit, KetraError> {
+    let Some(full) = resolve_tip(runner, path, env, Some(hash))? else {
+        return Err(KetraError::new(ErrorKind::InvalidInput, format!("Unknown commit '{}'", hash)));
+    };
+    let format = format!("--format={}", LOG_FORMAT);
+    let stdout = run_git(runner, env, path, &["log", "-z", "--max-count=1", "--decorate=full", &format, &full, "--"], "Failed to read commit")?;
+    parse_log(&stdout)
+        .pop()
+        .ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, format!("Unknown commit '{}'", hash)))
+}
+
+// Changed files with their line counts, renames detected
+fn commit_files(runner: &dyn CommandRunner, path: &str, env: Environment, commit: &Commit) -> Result<Vec<CommitFile>, KetraError> {
+    let names = run_git(runner, env, path, &diff_tree_args(commit, &["--name-status"], &[]), "Failed to list changed files")?;
+    let numstat = run_git(runner, env, path, &diff_tree_args(commit, &["--numstat"], &[]), "Failed to count changed lines")?;
+    let stats = parse_numstat(&numstat);
+
+    let mut files = parse_name_status(&names);
+    for file in &mut files {
+        file.lines = stats.get(&file.path).copied().flatten();
+        file.binary = matches!(stats.get(&file.path), Some(None));
+    }
+    Ok(files)
+}
+
+// `diff-tree` between the first parent and the commit; `--root` diffs a root
+// commit against the empty tree. Combined merge diffs are never used.
+fn diff_tree_args<'a>(commit: &'a Commit, options: &[&'a str], paths: &[&'a str]) -> Vec<&'a str> {
+    let mut args = vec!["diff-tree", "-r", "-z", "-M", "--no-commit-id"];
+    args.extend(options);
+    match commit.parents.first() {
+        Some(parent) => args.extend([parent.as_str(), commit.hash.as_str()]),
+        None => args.extend(["--root", commit.hash.as_str()]),
+    }
+    args.push("--");
+    args.extend(paths);
+    args
+}
+
+// `--name-status -z`: a status field, then the path, or the old and new path
+// for renames and copies ("R087")
+fn parse_name_status(stdout: &str) -> Vec<CommitFile> {
+    let mut files = Vec::new();
+    let mut fields = stdout.split('\0').filter(|f| !f.is_empty());
+
+    while let Some(status) = fields.next() {
+        let code = status.chars().next().unwrap_or('M');
+        let (original_path, path) = if matches!(code, 'R' | 'C') {
+            (fields.next().map(String::from), fields.next())
+        } else {
+            (None, fields.next())
+        };
+        let Some(path) = path else { break };
+        files.push(CommitFile {
+            path: path.to_string(),
+            original_path,
+            status: code,
+            binary: false,
+            lines: None,
+        });
+    }
+    files
+}
+
+// The commit `branch` (or HEAD) points at; None when HEAD has no commits yet
+fn resolve_tip(runner: &dyn CommandRunner, path: &str, env: Environment, branch: Option<&str>) -> Result<Option<String>, KetraError> {
+    if let Some(branch) = branch {
+        check_value("branch", branch)?;
+    }
+    let rev = format!("{}^{{commit}}", branch.unwrap_or("HEAD"));
+    match run_git(runner, env, path, &["rev-parse", "--verify", "--quiet", &rev], "Failed to resolve commit") {
+        Ok(stdout) => Ok(Some(stdout.trim().to_string())),
+        Err(e) if e.is(&ErrorKind::Failed) => match branch {
+            Some(branch) => Err(KetraError::new(ErrorKind::InvalidInput, format!("Unknown branch or commit '{}'", branch))),
+            None => Ok(None),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+// "<tip hash>:<commits already shown>"
+fn parse_cursor(cursor: &str) -> Result<(String, u32), KetraError> {
+    let parsed = cursor
+        .split_once(':')
+        .filter(|(tip, _)| tip.len() >= 7 && tip.chars().all(|c| c.is_ascii_hexdigit()))
+        .and_then(|(tip, skip)| Some((tip.to_string(), skip.parse().ok()?)));
+    parsed.ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, format!("Invalid history cursor '{}'", cursor)))
+}
+
+fn log_args(query: &HistoryQuery, tip: &str, skip: u32, limit: u32) -> Result<Vec<String>, KetraError> {
+    let mut args = vec![
+        "log".to_string(),
+        "-z".to_string(),
+        "--topo-order".to_string(),
+        // Rewrite parents past commits hidden by a path filter
+        "--parents".to_string(),
+        "--decorate=full".to_string(),
+        format!("--format={}", LOG_FORMAT),
+        format!("--skip={}", skip),
+        format!("--max-count={}", limit + 1),
+    ];
+
+    let mut searched = false;
+    if let Some(author) = non_empty(&query.author) {
+        args.push(format!("--author={}", author));
+        searched = true;
+    }
+    if let Some(grep) = non_empty(&query.grep) {
+        args.push(format!("--grep={}", grep));
+        searched = true;
+    }
+    if searched {
+        // Plain text from a search box, not a regex
+        args.extend(["--fixed-strings".to_string(), "--regexp-ignore-case".to_string()]);
+    }
+    if let Some(since) = query.since {
+        args.push(format!("--since=@{}", since));
+    }
+    if let Some(until) = query.until {
+        args.push(format!("--until=@{}", until));
+    }
+
+    args.extend([tip.to_string(), "--".to_string()]);
+    if let Some(path) = non_empty(&query.path) {
+        check_value("path", path)?;
+        args.push(path.to_string());
+    }
+    Ok(args)
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+// Values that end up as separate arguments must not look like options
+fn check_value(what: &str, value: &str) -> Result<(), KetraError> {
+    if value.starts_with('-') || value.chars().any(char::is_control) {
+        return Err(KetraError::new(ErrorKind::InvalidInput, format!("Invalid {} '{}'", what, value)));
+    }
+    Ok(())
+}
+
+fn parse_log(stdout: &str) -> Vec<Commit> {
+    let fields: Vec<&str> = stdout.split('\0').collect();
+    fields
+        .chunks_exact(LOG_FIELDS)
+        .map(|f| Commit {
+            hash: f[0].to_string(),
+            parents: f[1].split_whitespace().map(String::from).collect(),
+            author_name: f[2].to_string(),
+            author_email: f[3].to_string(),
+            author_time: f[4].parse().unwrap_or(0),
+            committer_name: f[5].to_string(),
+            committer_email: f[6].to_string(),
+            commit_time: f[7].parse().unwrap_or(0),
+            refs: parse_refs(f[8]),
+            subject: f[9].to_string(),
+            body: f[10].trim_end().to_string(),
+        })
+        .collect()
+}
+
+// `--decorate=full` decorations: "HEAD -> refs/heads/main, refs/remotes/origin/main, tag: refs/tags/v1"
+fn parse_refs(decorations: &str) -> Vec<CommitRef> {
+    let mut refs = Vec::new();
+    for decoration in decorations.split(", ").filter(|d| !d.is_empty()) {
+        let name = match decoration.strip_prefix("HEAD -> ") {
+            Some(branch) => {
+                refs.push(CommitRef { name: "HEAD".to_string(), kind: RefKind::Head });
+                branch
+            }
+            None => decoration,
+        };
+        let name = name.strip_prefix("tag: ").unwrap_or(name);
+        let (kind, short) = if let Some(short) = name.strip_prefix("refs/heads/") {
+            (RefKind::Branch, short)
+        } else if let Some(short) = name.strip_prefix("refs/remotes/") {
+            (RefKind::Remote, short)
+        } else if let Some(short) = name.strip_prefix("refs/tags/") {
+            (RefKind::Tag, short)
+        } else if name == "HEAD" {
+            (RefKind::Head, name)
+        } else {
+            // refs/stash and the like
+            continue;
+        };
+        refs.push(CommitRef { name: short.to_string(), kind });
+    }
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    const REPO: &str = "/home/dev/ketra/app";
+
+    fn record(fields: [&str; LOG_FIELDS]) -> String {
+        fields.join("\0") + "\0"
+    }
+
+    #[test]
+    fn parses_nul_delimited_log() {
+        let stdout = [
+            record(["aaa", "bbb ccc", "Ada", "ada@example.com", "1700000100", "Bob", "bob@example.com", "1700000200", "HEAD -> refs/heads/main, refs/remotes/origin/main, tag: refs/tags/v1.0", "Merge a | b", "Long body\n\nwith | pipes\n"]),
+            record(["bbb", "", "Ada", "ada@example.com", "1700000000", "Ada", "ada@example.com", "1700000000", "", "Root", ""]),
+        ]
+        .concat();
+
+        let commits = parse_log(&stdout);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].parents, ["bbb", "ccc"]);
+        assert_eq!((commits[0].subject.as_str(), commits[0].body.as_str()), ("Merge a | b", "Long body\n\nwith | pipes"));
+        assert_eq!((commits[0].author_time, commits[0].commit_time), (1700000100, 1700000200));
+        assert_eq!(commits[0].committer_name, "Bob");
+        assert_eq!(
+            commits[0].refs,
+            [
+                CommitRef { name: "HEAD".into(), kind: RefKind::Head },
+                CommitRef { name: "main".into(), kind: RefKind::Branch },
+                CommitRef { name: "origin/main".into(), kind: RefKind::Remote },
+                CommitRef { name: "v1.0".into(), kind: RefKind::Tag },
+            ]
+        );
+        assert_eq!(commits[1].hash, "bbb");
+        assert!(commits[1].parents.is_empty() && commits[1].refs.is_empty());
+    }
+
+    #[test]
+    fn filters_become_log_arguments() {
+        let query = HistoryQuery {
+            author: Some("ada".into()),
+            grep: Some("fix(".into()),
+            since: Some(1700000000),
+            until: Some(1710000000),
+            path: Some("src/app".into()),
+            ..HistoryQuery::default()
+        };
+        let args = log_args(&query, "abc1234", 50, 50).unwrap();
+        assert_eq!(
+            args[6..],
+            [
+                "--skip=50",
+                "--max-count=51",
+                "--author=ada",
+                "--grep=fix(",
+                "--fixed-strings",
+                "--regexp-ignore-case",
+                "--since=@1700000000",
+                "--until=@1710000000",
+                "abc1234",
+                "--",
+                "src/app",
+            ]
+        );
+
+        let hostile = HistoryQuery { path: Some("--output=/tmp/x".into()), ..HistoryQuery::default() };
+        assert!(log_args(&hostile, "abc1234", 0, 10).unwrap_err().is(&ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn pages_through_a_pinned_tip() {
+        let page = [
+            record(["c3", "c2", "A", "a@x", "3", "A", "a@x", "3", "", "three", ""]),
+            record(["c2", "c1", "A", "a@x", "2", "A", "a@x", "2", "", "two", ""]),
+            record(["c1", "", "A", "a@x", "1", "A", "a@x", "1", "", "one", ""]),
+        ]
+        .concat();
+        let runner = FakeRunner::new()
+            .on("rev-parse", CommandOutput::ok("abcdef1234\n"))
+            .on("git log", CommandOutput::ok(&page));
+
+        let query = HistoryQuery { branch: Some("main".into()), limit: Some(2), ..HistoryQuery::default() };
+        let first = get_commit_history(&runner, REPO.to_string(), Environment::Wsl, query).unwrap();
+        assert_eq!(first.commits.len(), 2);
+        assert_eq!(first.next_cursor.as_deref(), Some("abcdef1234:2"));
+        assert!(runner.ran("git rev-parse --verify --quiet main^{commit}"));
+
+        let query = HistoryQuery { cursor: first.next_cursor, limit: Some(5), ..HistoryQuery::default() };
+        let second = get_commit_history(&runner, REPO.to_string(), Environment::Wsl, query).unwrap();
+        assert_eq!(second.next_cursor, None);
+        assert!(runner.ran("--skip=2 --max-count=6 abcdef1234 --"));
+
+        assert!(parse_cursor("nothex:2").is_err() && parse_cursor("abcdef1234:x").is_err());
+    }
+
+    #[test]
+    fn unborn_head_is_an_empty_history() {
+        let runner = FakeRunner::new().on("rev-parse", CommandOutput::fail(""));
+
+        let page = get_commit_history(&runner, REPO.to_string(), Environment::Wsl, HistoryQuery::default()).unwrap();
+        assert!(page.commits.is_empty() && page.next_cursor.is_none());
+
+        let query = HistoryQuery { branch: Some("nope".into()), ..HistoryQuery::default() };
+        let err = get_commit_history(&runner, REPO.to_string(), Environment::Wsl, query).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+        assert!(!runner.ran("git log"));
+    }
+
+    #[test]
+    fn parses_name_status_with_renames_and_copies() {
+        let files = parse_name_status("M\0src/main.rs\0R087\0old.rs\0new.rs\0A\0logo.png\0C100\0a.txt\0b.txt\0D\0gone.md\0");
+        let summary: Vec<(char, &str, Option<&str>)> = files.iter().map(|f| (f.status, f.path.as_str(), f.original_path.as_deref())).collect();
+        assert_eq!(
+            summary,
+            [
+                ('M', "src/main.rs", None),
+                ('R', "new.rs", Some("old.rs")),
+                ('A', "logo.png", None),
+                ('C', "b.txt", Some("a.txt")),
+                ('D', "gone.md", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_are_compared_with_their_first_parent() {
+        let runner = FakeRunner::new()
+            .on("rev-parse", CommandOutput::ok("abcdef1\n"))
+            .on("git log", CommandOutput::ok(&record(["abcdef1", "1111111 2222222", "A", "a@x", "1", "A", "a@x", "1", "", "Merge", ""])))
+            .on("--name-status", CommandOutput::ok("R100\0old.rs\0new.rs\0"))
+            .on("--numstat", CommandOutput::ok("0\t0\t\0old.rs\0new.rs\0"));
+
+        let detail = get_commit_detail(&runner, REPO.to_string(), Environment::Wsl, "abcdef1".into()).unwrap();
+        assert_eq!(detail.base.as_deref(), Some("1111111"));
+        assert!(runner.ran("git diff-tree -r -z -M --no-commit-id --name-status 1111111 abcdef1 --"));
+        assert_eq!(detail.files[0].lines, Some(LineCounts { added: 0, deleted: 0 }));
+
+        // A renamed file's patch is asked for under both names
+        get_commit_patch(&runner, REPO.to_string(), Environment::Wsl, "abcdef1".into(), Some("new.rs".into())).unwrap();
+        assert!(runner.ran("--no-commit-id -p 1111111 abcdef1 -- old.rs new.rs"));
+
+        let err = get_commit_patch(&runner, REPO.to_string(), Environment::Wsl, "abcdef1".into(), Some("other.rs".into())).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+        let err = get_commit_detail(&runner, REPO.to_string(), Environment::Wsl, "--all".into()).unwrap_err();
+        assert!(err.is(&ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn reads_a_real_repo_with_merges_and_path_filters() {
+        use crate::exec::system;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("ketra-history-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let env = Environment::host();
+        let git = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            let output = system().output(&env.command("git", &full, Some(&path))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "Add a | with pipe", "-m", "Body\nover lines"]);
+        git(&["checkout", "-q", "-b", "side"]);
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::write(dir.join("docs/guide.md"), "guide").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "Add docs"]);
+        git(&["checkout", "-q", "main"]);
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "Add b"]);
+        git(&["merge", "-q", "--no-ff", "side", "-m", "Merge side"]);
+        git(&["tag", "v1"]);
+
+        let page = get_commit_history(system(), path.clone(), env, HistoryQuery::default()).unwrap();
+        let subjects: Vec<&str> = page.commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects.len(), 4);
+        assert_eq!(subjects[0], "Merge side");
+        assert_eq!(subjects[3], "Add a | with pipe");
+        assert_eq!(page.commits[0].parents.len(), 2);
+        assert!(page.commits[0].refs.contains(&CommitRef { name: "v1".into(), kind: RefKind::Tag }));
+        let root_hash = page.commits[3].hash.clone();
+        assert_eq!(page.commits[3].body, "Body\nover lines");
+
+        // Paging covers every commit exactly once
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let query = HistoryQuery { limit: Some(3), cursor: cursor.take(), ..HistoryQuery::default() };
+            let page = get_commit_history(system(), path.clone(), env, query).unwrap();
+            seen.extend(page.commits.into_iter().map(|c| c.hash));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen.len(), 4);
+
+        let query = HistoryQuery { path: Some("docs".into()), ..HistoryQuery::default() };
+        let docs = get_commit_history(system(), path.clone(), env, query).unwrap();
+        assert_eq!(docs.commits.len(), 1);
+        assert_eq!(docs.commits[0].subject, "Add docs");
+
+        let query = HistoryQuery { grep: Some("ADD B".into()), branch: Some("main".into()), ..HistoryQuery::default() };
+        let grep = get_commit_history(system(), path.clone(), env, query).unwrap();
+        assert_eq!(grep.commits.len(), 1);
+
+        // The merge brings in docs/ relative to its first parent
+        let merge = get_commit_detail(system(), path.clone(), env, "HEAD".into()).unwrap();
+        assert_eq!(merge.commit.subject, "Merge side");
+        let files: Vec<&str> = merge.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(files, ["docs/guide.md"]);
+
+        let root = get_commit_detail(system(), path.clone(), env, root_hash).unwrap();
+        assert_eq!(root.base, None);
+        assert_eq!((root.files[0].status, root.files[0].lines), ('A', Some(LineCounts { added: 1, deleted: 0 })));
+
+        // A rename and a binary file in one commit
+        git(&["mv", "a.txt", "renamed.txt"]);
+        fs::write(dir.join("logo.png"), [0u8, 159, 146, 150, 0, 1]).unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "Rename and add logo"]);
+
+        let detail = get_commit_detail(system(), path.clone(), env, "HEAD".into()).unwrap();
+        let rename = detail.files.iter().find(|f| f.status == 'R').unwrap();
+        assert_eq!((rename.path.as_str(), rename.original_path.as_deref()), ("renamed.txt", Some("a.txt")));
+        let logo = detail.files.iter().find(|f| f.path == "logo.png").unwrap();
+        assert!(logo.binary && logo.lines.is_none());
+
+        let patch = get_commit_patch(system(), path.clone(), env, "HEAD".into(), Some("renamed.txt".into())).unwrap();
+        assert!(patch.contains("rename from a.txt\nrename to renamed.txt"));
+        assert!(!patch.contains("logo.png"));
+        let whole = get_commit_patch(system(), path.clone(), env, "HEAD".into(), None).unwrap();
+        assert!(whole.contains("Binary files /dev/null and b/logo.png differ"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
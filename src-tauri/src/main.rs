@@ -2,168 +2,390 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod types;
+mod branches;
+mod changes;
+mod clone;
+mod detect;
+mod diff;
+mod discovery;
+mod environment;
+mod error;
+mod exec;
 mod git;
 mod github;
+mod history;
+mod operations;
+mod progress;
 mod projects;
+mod registry;
+mod repo_url;
+mod roots;
+mod status_cache;
 mod templates;
 mod utils;
+mod watcher;
+mod wsl_scan;
 
+use clone::CloneOptions;
+use environment::Environment;
+use error::KetraError;
+use exec::system;
+use progress::EventProgress;
 use types::{Project, GitStatus};
-use git::{get_git_status, get_git_status_wsl};
+use status_cache::{cached_git_status, status_cache};
 use projects::*;
+use registry::{registry, LegacyState, ProjectKey};
+use roots::ProjectRoot;
+use std::cmp::Reverse;
+use tauri::AppHandle;
+
+// Most recently opened first; never-opened projects by folder modification time
+fn sort_recent(projects: &mut [Project]) {
+    projects.sort_by_key(|p| Reverse((p.last_opened, p.last_modified)));
+}
+
+fn record_opened(env: Environment, path: &str) -> Result<(), KetraError> {
+    let mut registry = registry();
+    registry.touch(ProjectKey::new(env, path), utils::unix_now());
+    registry.save()
+}
 
 #[tauri::command]
 async fn get_recent_projects() -> Vec<Project> {
-    let mut projects = scan_launcher_folders_async().await;
-    // Sort by last_opened (modified time), most recent first
-    projects.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+    let roots = registry().roots();
+    let mut projects = scan_launcher_folders_async(system(), roots).await;
+    registry().annotate(&mut projects);
+    // Every environment was scanned, so anything else in the cache is gone
+    status_cache().retain_projects(&projects);
+    if let Err(e) = status_cache().save() {
+        println!("[DEBUG] {}", e);
+    }
+    sort_recent(&mut projects);
     projects
 }
 
 #[tauri::command]
 fn get_projects_fast() -> Vec<Project> {
-    let mut all_projects = scan_launcher_folders_fast();
-    // Sort by last_opened (modified time), most recent first
-    all_projects.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+    let mut all_projects = scan_launcher_folders_fast(system(), &registry().roots());
+    registry().annotate(&mut all_projects);
+    sort_recent(&mut all_projects);
     all_projects
 }
 
 #[tauri::command]
 fn get_project_git_status(path: String) -> Option<GitStatus> {
-    if path.starts_with("/home/") || path.starts_with("/mnt/") {
-        get_git_status_wsl(&path)
-    } else {
-        get_git_status(&path)
-    }
+    cached_git_status(system(), &path, Environment::from_path(&path))
+}
+
+#[tauri::command]
+fn get_project_info(path: String, env: Environment) -> Option<detect::ProjectInfo> {
+    detect::detect_project(system(), env, &path)
+}
+
+#[tauri::command]
+fn get_environments() -> Vec<Environment> {
+    Environment::available()
 }
 
 #[tauri::command]
-async fn delete_github_repo(repo_name: String) -> Result<(), String> {
-    github::delete_github_repo(repo_name).await
+async fn delete_github_repo(repo_name: String) -> Result<(), KetraError> {
+    github::delete_github_repo(system(), repo_name).await
 }
 
 #[tauri::command]
-async fn delete_project(path: String, name: String) -> Result<(), String> {
-    projects::delete_project(path, name).await
+async fn delete_project(path: String, name: String) -> Result<(), KetraError> {
+    projects::delete_project(system(), path, name).await
 }
 
 #[tauri::command]
-fn check_github_auth() -> Result<String, String> {
-    github::check_github_auth()
+fn check_github_auth() -> Result<String, KetraError> {
+    github::check_github_auth(system())
 }
 
 #[tauri::command]
-fn github_login() -> Result<(), String> {
-    github::github_login()
+fn github_login() -> Result<(), KetraError> {
+    github::github_login(system())
 }
 
 #[tauri::command]
-async fn create_github_repo(name: String) -> Result<String, String> {
-    github::create_github_repo(name).await
+async fn create_github_repo(name: String) -> Result<String, KetraError> {
+    github::create_github_repo(system(), name).await
 }
 
 #[tauri::command]
-fn check_project_exists(name: String, env: String) -> Result<bool, String> {
-    projects::check_project_exists(name, env)
+fn check_project_exists(name: String, env: Environment) -> Result<bool, KetraError> {
+    projects::check_project_exists(system(), name, env)
 }
 
 #[tauri::command]
-fn open_existing_project(path: String, env: String) -> Result<(), String> {
-    projects::open_existing_project(path, env)
+fn open_existing_project(path: String, env: Environment) -> Result<(), KetraError> {
+    projects::open_existing_project(system(), path.clone(), env)?;
+    record_opened(env, &path)
 }
 
 #[tauri::command]
-async fn launch_project(env: String, name: String, template: String, create_repo: bool) -> Result<(), String> {
-    templates::launch_project(env, name, template, create_repo).await
+async fn launch_project(env: Environment, name: String, template: String, create_repo: bool, operation_id: String) -> Result<(), KetraError> {
+    let path = templates::launch_project(system(), env, name, template, create_repo, &operation_id).await?;
+    record_opened(env, &path)
+}
+
+// Long-running git commands report progress as OPERATION_PROGRESS events
+// tagged with the `operation_id` the UI picked
+
+#[tauri::command]
+async fn git_pull(app: AppHandle, path: String, env: Environment, operation_id: String) -> Result<String, KetraError> {
+    git::git_pull(system(), path, env, &EventProgress::new(app, operation_id)).await
+}
+
+#[tauri::command]
+async fn git_fetch(app: AppHandle, path: String, env: Environment, operation_id: String) -> Result<String, KetraError> {
+    git::git_fetch(system(), path, env, &EventProgress::new(app, operation_id)).await
+}
+
+#[tauri::command]
+async fn git_push(app: AppHandle, path: String, env: Environment, message: String, operation_id: String) -> Result<String, KetraError> {
+    github::git_push(system(), path, env, message, &EventProgress::new(app, operation_id)).await
 }
 
 #[tauri::command]
-async fn git_pull(path: String, env: String) -> Result<String, String> {
-    git::git_pull(path, env).await
+fn list_operations() -> Vec<operations::Operation> {
+    operations::list_operations()
 }
 
+// Kills the operation's processes; its own command then fails with Cancelled
 #[tauri::command]
-async fn git_push(path: String, env: String, message: String) -> Result<String, String> {
-    github::git_push(path, env, message).await
+fn cancel_operation(operation_id: String) -> Result<(), KetraError> {
+    operations::cancel(system(), &operation_id)
 }
 
 #[tauri::command]
-fn toggle_pin(_name: String) -> Result<(), String> {
-    // For now, just return Ok - will implement persistence later
+async fn push_commits(app: AppHandle, path: String, env: Environment, operation_id: String) -> Result<String, KetraError> {
+    github::push_commits(system(), path, env, &EventProgress::new(app, operation_id)).await
+}
+
+#[tauri::command]
+fn toggle_pin(path: String, env: Environment) -> Result<bool, KetraError> {
+    let mut registry = registry();
+    let pinned = registry.toggle_pin(ProjectKey::new(env, &path));
+    registry.save()?;
+    Ok(pinned)
+}
+
+#[tauri::command]
+fn set_project_order(projects: Vec<ProjectKey>) -> Result<(), KetraError> {
+    let keys = projects.into_iter().map(|p| ProjectKey::new(p.env, &p.path)).collect();
+    let mut registry = registry();
+    registry.set_order(keys);
+    registry.save()
+}
+
+#[tauri::command]
+fn set_project_metadata(path: String, env: Environment, field: String, value: Option<String>) -> Result<(), KetraError> {
+    let mut registry = registry();
+    registry.set_metadata(ProjectKey::new(env, &path), field, value);
+    registry.save()
+}
+
+// Configured roots; environments without any use ~/ketra
+#[tauri::command]
+fn get_project_roots() -> Vec<ProjectRoot> {
+    registry().roots()
+}
+
+#[tauri::command]
+fn set_project_roots(roots: Vec<ProjectRoot>) -> Result<(), KetraError> {
+    for root in &roots {
+        root.validate()?;
+    }
+    let mut registry = registry();
+    registry.set_roots(roots);
+    registry.save()?;
+    watcher::reload();
     Ok(())
 }
 
+// Move pins/order from the webview's old localStorage keys into the registry
+#[tauri::command]
+async fn import_legacy_project_state(pinned: Vec<String>, order: Vec<String>) -> Result<usize, KetraError> {
+    if registry().legacy_imported() {
+        return Ok(0);
+    }
+
+    let roots = registry().roots();
+    let mut projects = scan_launcher_folders_fast(system(), &roots);
+    projects.extend(projects::scan_wsl_projects(system(), roots, false).await);
+
+    let mut registry = registry();
+    let imported = registry.import_legacy(&projects, &LegacyState { pinned, order });
+    registry.save()?;
+    Ok(imported)
+}
+
+#[tauri::command]
+async fn git_clone(app: AppHandle, repo_url: String, env: Environment, options: CloneOptions, operation_id: String) -> Result<String, KetraError> {
+    clone::git_clone(system(), env, repo_url, options, &EventProgress::new(app, operation_id)).await
+}
+
 #[tauri::command]
-async fn git_clone(repo_url: String, env: String) -> Result<String, String> {
-    use utils::get_default_folder;
-    let base_folder = get_default_folder(&env)?;
-    git::git_clone(repo_url, env, base_folder).await
+fn plan_clone(repo_url: String, env: Environment, options: CloneOptions) -> Result<clone::ClonePlan, KetraError> {
+    clone::plan_clone(system(), env, &repo_url, &options)
 }
 
 #[tauri::command]
-async fn paste_folder(env: String) -> Result<String, String> {
-    projects::paste_folder(env).await
+async fn paste_folder(env: Environment) -> Result<String, KetraError> {
+    projects::paste_folder(system(), env).await
 }
 
 #[tauri::command]
-fn open_ketra_folder(env: String) -> Result<(), String> {
-    projects::open_ketra_folder(env)
+fn open_ketra_folder(env: Environment) -> Result<(), KetraError> {
+    projects::open_ketra_folder(system(), env)
 }
 
 #[tauri::command]
-fn open_terminal(env: String) -> Result<(), String> {
-    projects::open_terminal(env)
+fn open_terminal(env: Environment) -> Result<(), KetraError> {
+    projects::open_terminal(system(), env)
 }
 
 #[tauri::command]
-fn get_branches(path: String, env: String) -> Result<Vec<String>, String> {
-    git::get_branches(path, env)
+fn get_branches(path: String, env: Environment) -> Result<Vec<branches::Branch>, KetraError> {
+    branches::list_branches(system(), path, env)
 }
 
 #[tauri::command]
-async fn switch_branch(path: String, env: String, branch: String) -> Result<String, String> {
-    git::switch_branch(path, env, branch).await
+async fn switch_branch(path: String, env: Environment, branch: String) -> Result<String, KetraError> {
+    git::switch_branch(system(), path, env, branch).await
 }
 
 #[tauri::command]
-async fn create_branch(path: String, env: String, branch_name: String) -> Result<String, String> {
-    git::create_branch(path, env, branch_name).await
+async fn create_branch(path: String, env: Environment, branch_name: String) -> Result<String, KetraError> {
+    git::create_branch(system(), path, env, branch_name).await
 }
 
 #[tauri::command]
-fn get_commit_history(path: String, env: String, limit: i32) -> Result<Vec<serde_json::Value>, String> {
-    git::get_commit_history(path, env, limit)
+fn preview_branch_delete(path: String, env: Environment, names: Vec<String>) -> Result<Vec<branches::DeletePreview>, KetraError> {
+    branches::preview_branch_delete(system(), path, env, names)
 }
 
 #[tauri::command]
-fn get_diff(path: String, env: String) -> Result<String, String> {
-    git::get_diff(path, env)
+fn delete_branch(path: String, env: Environment, name: String, confirmed_commit: Option<String>) -> Result<String, KetraError> {
+    branches::delete_branch(system(), path, env, name, confirmed_commit)
 }
 
 #[tauri::command]
-async fn git_stash(path: String, env: String) -> Result<String, String> {
-    git::git_stash(path, env).await
+fn rename_branch(path: String, env: Environment, old_name: String, new_name: String) -> Result<String, KetraError> {
+    branches::rename_branch(system(), path, env, old_name, new_name)
 }
 
 #[tauri::command]
-async fn git_stash_pop(path: String, env: String) -> Result<String, String> {
-    git::git_stash_pop(path, env).await
+fn checkout_remote_branch(path: String, env: Environment, remote_branch: String, local_name: Option<String>) -> Result<String, KetraError> {
+    branches::checkout_remote_branch(system(), path, env, remote_branch, local_name)
+}
+
+#[tauri::command]
+fn prune_merged_branches(path: String, env: Environment, dry_run: bool) -> Result<Vec<String>, KetraError> {
+    branches::prune_merged_branches(system(), path, env, dry_run)
+}
+
+#[tauri::command]
+fn get_commit_history(path: String, env: Environment, query: history::HistoryQuery) -> Result<history::HistoryPage, KetraError> {
+    history::get_commit_history(system(), path, env, query)
+}
+
+#[tauri::command]
+fn get_commit_detail(path: String, env: Environment, hash: String) -> Result<history::CommitDetail, KetraError> {
+    history::get_commit_detail(system(), path, env, hash)
+}
+
+// The whole commit's patch, or only `file`'s
+#[tauri::command]
+fn get_commit_patch(path: String, env: Environment, hash: String, file: Option<String>) -> Result<String, KetraError> {
+    history::get_commit_patch(system(), path, env, hash, file)
+}
+
+#[tauri::command]
+fn get_diff(path: String, env: Environment, target: diff::DiffTarget, options: diff::DiffOptions) -> Result<Vec<diff::DiffFile>, KetraError> {
+    diff::get_diff(system(), path, env, target, options)
+}
+
+#[tauri::command]
+fn get_changed_files(path: String, env: Environment) -> Result<Vec<changes::FileChange>, KetraError> {
+    changes::get_changed_files(system(), path, env)
+}
+
+#[tauri::command]
+fn stage_paths(path: String, env: Environment, paths: Vec<String>) -> Result<(), KetraError> {
+    changes::stage_paths(system(), path, env, paths)
+}
+
+#[tauri::command]
+fn unstage_paths(path: String, env: Environment, paths: Vec<String>) -> Result<(), KetraError> {
+    changes::unstage_paths(system(), path, env, paths)
+}
+
+#[tauri::command]
+fn stage_hunk(path: String, env: Environment, file: String, header: String, options: diff::DiffOptions) -> Result<(), KetraError> {
+    changes::stage_hunk(system(), path, env, file, header, options)
+}
+
+#[tauri::command]
+fn unstage_hunk(path: String, env: Environment, file: String, header: String, options: diff::DiffOptions) -> Result<(), KetraError> {
+    changes::unstage_hunk(system(), path, env, file, header, options)
+}
+
+#[tauri::command]
+fn discard_file(path: String, env: Environment, file: String) -> Result<(), KetraError> {
+    changes::discard_file(system(), path, env, file)
+}
+
+#[tauri::command]
+fn commit_staged(path: String, env: Environment, options: changes::CommitOptions) -> Result<String, KetraError> {
+    changes::commit_staged(system(), path, env, options)
+}
+
+#[tauri::command]
+async fn git_stash(path: String, env: Environment) -> Result<String, KetraError> {
+    git::git_stash(system(), path, env).await
+}
+
+#[tauri::command]
+async fn git_stash_pop(path: String, env: Environment) -> Result<String, KetraError> {
+    git::git_stash_pop(system(), path, env).await
 }
 
 #[tauri::command]
 async fn scan_wsl_projects() -> Vec<Project> {
-    projects::scan_wsl_projects().await
+    let roots = registry().roots();
+    let mut projects = projects::scan_wsl_projects(system(), roots, false).await;
+    registry().annotate(&mut projects);
+    sort_recent(&mut projects);
+    projects
+}
+
+// Git status of every WSL project from one wsl call, rather than one per project
+#[tauri::command]
+async fn get_wsl_git_statuses() -> Vec<Project> {
+    let roots = registry().roots();
+    projects::scan_wsl_projects(system(), roots, true).await
 }
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            // Push project and git status changes to the UI as they happen
+            watcher::start(app.handle().clone());
+            status_cache::start_autosave();
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_recent_projects,
             get_projects_fast,
             scan_wsl_projects,
+            get_wsl_git_statuses,
             get_project_git_status,
+            get_project_info,
+            get_environments,
             delete_project,
             delete_github_repo,
             launch_project,
@@ -173,20 +395,50 @@ fn main() {
             check_github_auth,
             github_login,
             git_pull,
+            git_fetch,
             git_push,
             toggle_pin,
+            set_project_order,
+            set_project_metadata,
+            get_project_roots,
+            set_project_roots,
+            import_legacy_project_state,
             git_clone,
+            plan_clone,
             paste_folder,
             open_ketra_folder,
             open_terminal,
             get_branches,
             switch_branch,
             create_branch,
+            preview_branch_delete,
+            delete_branch,
+            rename_branch,
+            checkout_remote_branch,
+            prune_merged_branches,
             get_commit_history,
+            get_commit_detail,
+            get_commit_patch,
             get_diff,
+            get_changed_files,
+            stage_paths,
+            unstage_paths,
+            stage_hunk,
+            unstage_hunk,
+            discard_file,
+            commit_staged,
+            push_commits,
+            list_operations,
+            cancel_operation,
             git_stash,
             git_stash_pop
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            // Don't leave clones or installs running after the window closes
+            if let tauri::RunEvent::Exit = event {
+                operations::cancel_all(system());
+            }
+        });
 }
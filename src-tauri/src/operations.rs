@@ -0,0 +1,355 @@
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::{CommandRunner, CommandSpec};
+use crate::utils::unix_now;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+// WSL processes are started through `setsid` so the whole tree can be killed
+// from inside WSL; the shell reports its process group on stderr first
+pub const WSL_PID_MARKER: &str = "ketra-pid:";
+const WSL_WRAPPER: &str = "echo \"ketra-pid:$$\" >&2; exec \"$@\"";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationKind {
+    Clone,
+    Pull,
+    Push,
+    Fetch,
+    TemplateInit,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Operation {
+    pub id: String,
+    pub kind: OperationKind,
+    // What the UI shows, e.g. the repo being cloned
+    pub label: String,
+    pub env: Environment,
+    pub started_at: u64,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pid {
+    // A process we spawned; on Windows that is `wsl.exe` for WSL commands
+    Host(u32),
+    // The process group a WSL command runs in
+    Wsl(u32),
+}
+
+struct Running {
+    operation: Operation,
+    pids: Vec<Pid>,
+    // Folder the operation is creating, removed if it is cancelled
+    cleanup: Option<String>,
+}
+
+// Clones, pulls, pushes, fetches and template inits that are still running
+#[derive(Default)]
+pub struct Operations {
+    running: HashMap<String, Running>,
+}
+
+impl Operations {
+    fn begin(&mut self, operation: Operation) -> bool {
+        if self.running.contains_key(&operation.id) {
+            return false;
+        }
+        self.running.insert(
+            operation.id.clone(),
+            Running {
+                operation,
+                pids: Vec::new(),
+                cleanup: None,
+            },
+        );
+        true
+    }
+
+    // Record a process of the operation. Returns false if the operation was
+    // cancelled already, in which case the caller should stop the process.
+    pub fn attach(&mut self, id: &str, pid: Pid) -> bool {
+        match self.running.get_mut(id) {
+            Some(running) if !running.operation.cancelled => {
+                running.pids.push(pid);
+                true
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    // The operation's processes exited
+    pub fn detach(&mut self, id: &str) {
+        if let Some(running) = self.running.get_mut(id) {
+            running.pids.clear();
+        }
+    }
+
+    pub fn list(&self) -> Vec<Operation> {
+        let mut operations: Vec<Operation> = self.running.values().map(|r| r.operation.clone()).collect();
+        operations.sort_by_key(|o| o.started_at);
+        operations
+    }
+
+    fn is_cancelled(&self, id: &str) -> bool {
+        self.running.get(id).is_some_and(|r| r.operation.cancelled)
+    }
+
+    // Mark the operation cancelled and hand back what has to be killed
+    fn cancel(&mut self, id: &str) -> Option<(Environment, Vec<Pid>)> {
+        let running = self.running.get_mut(id)?;
+        running.operation.cancelled = true;
+        Some((running.operation.env, std::mem::take(&mut running.pids)))
+    }
+}
+
+static OPERATIONS: OnceLock<Mutex<Operations>> = OnceLock::new();
+
+pub fn operations() -> MutexGuard<'static, Operations> {
+    OPERATIONS
+        .get_or_init(|| Mutex::new(Operations::default()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// Keeps an operation registered while it runs; dropping it unregisters it
+#[derive(Debug)]
+pub struct OperationGuard {
+    id: String,
+}
+
+// Register an operation under the id the UI picked
+pub fn begin(id: &str, kind: OperationKind, label: &str, env: Environment) -> Result<OperationGuard, KetraError> {
+    let operation = Operation {
+        id: id.to_string(),
+        kind,
+        label: label.to_string(),
+        env,
+        started_at: unix_now(),
+        cancelled: false,
+    };
+    if !operations().begin(operation) {
+        return Err(KetraError::new(ErrorKind::AlreadyExists, format!("Operation {} is already running", id)));
+    }
+    Ok(OperationGuard { id: id.to_string() })
+}
+
+impl OperationGuard {
+    // Remove `path` if the operation gets cancelled. Only for folders the
+    // operation creates itself.
+    pub fn cleanup_on_cancel(&self, path: &str) {
+        if let Some(running) = operations().running.get_mut(&self.id) {
+            running.cleanup = Some(path.to_string());
+        }
+    }
+
+    // Err(Cancelled) once the operation was cancelled, to stop between steps
+    pub fn check(&self) -> Result<(), KetraError> {
+        if operations().is_cancelled(&self.id) {
+            return Err(cancelled());
+        }
+        Ok(())
+    }
+
+    // The operation's result, unless it was cancelled meanwhile: then its
+    // folder is cleaned up and the result is Cancelled, whatever the killed
+    // process reported
+    pub fn settle<T>(&self, runner: &dyn CommandRunner, result: Result<T, KetraError>) -> Result<T, KetraError> {
+        let cancelled_state = {
+            let operations = operations();
+            operations
+                .running
+                .get(&self.id)
+                .filter(|r| r.operation.cancelled)
+                .map(|r| (r.operation.env, r.cleanup.clone()))
+        };
+        match cancelled_state {
+            None => result,
+            Some((env, cleanup)) => {
+                if let Some(path) = cleanup {
+                    if let Err(e) = env.remove_dir_all(runner, &path) {
+                        println!("[DEBUG] Failed to clean up {} after cancel: {}", path, e);
+                    }
+                }
+                Err(cancelled())
+            }
+        }
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        operations().running.remove(&self.id);
+    }
+}
+
+fn cancelled() -> KetraError {
+    KetraError::new(ErrorKind::Cancelled, "Cancelled")
+}
+
+// `spec` (built by Environment::command) registered under the operation, so
+// the runner records its process and cancel can kill it with its children
+pub fn tracked(mut spec: CommandSpec, env: Environment, id: &str) -> CommandSpec {
+    if env == Environment::Wsl {
+        if let Some(exec) = spec.args.iter().position(|a| a == "--exec") {
+            let wrapper = ["setsid", "-w", "sh", "-c", WSL_WRAPPER, "sh"].map(str::to_string);
+            spec.args.splice(exec + 1..exec + 1, wrapper);
+        }
+    }
+    spec.operation = Some(id.to_string());
+    spec
+}
+
+pub fn list_operations() -> Vec<Operation> {
+    operations().list()
+}
+
+// Kill the operation's processes and their children. The operation itself
+// finishes (and cleans up) once its command returns.
+pub fn cancel(runner: &dyn CommandRunner, id: &str) -> Result<(), KetraError> {
+    let (env, pids) = operations()
+        .cancel(id)
+        .ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, format!("No running operation {}", id)))?;
+
+    // Inside WSL first: killing wsl.exe alone leaves the Linux processes running
+    for pid in pids.iter().filter_map(|p| if let Pid::Wsl(pid) = p { Some(pid) } else { None }) {
+        let group = format!("-{}", pid);
+        if let Err(e) = runner.output(&env.command("kill", &["-TERM", "--", &group], None)) {
+            println!("[DEBUG] Failed to kill WSL process group {}: {}", pid, e);
+        }
+    }
+    for pid in pids.iter().filter_map(|p| if let Pid::Host(pid) = p { Some(pid) } else { None }) {
+        if let Err(e) = runner.output(&kill_tree(*pid)) {
+            println!("[DEBUG] Failed to kill process {}: {}", pid, e);
+        }
+    }
+    Ok(())
+}
+
+// Cancel everything, e.g. when the app exits
+pub fn cancel_all(runner: &dyn CommandRunner) {
+    let ids: Vec<String> = operations().running.keys().cloned().collect();
+    for id in ids {
+        cancel(runner, &id).ok();
+    }
+}
+
+// Tracked processes lead their own process group on Unix
+fn kill_tree(pid: u32) -> CommandSpec {
+    let pid = pid.to_string();
+    if cfg!(windows) {
+        CommandSpec::new("taskkill").args(&["/PID", &pid, "/T", "/F"])
+    } else {
+        CommandSpec::new("kill").args(&["-TERM", "--", &format!("-{}", pid)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    fn id(name: &str) -> String {
+        format!("{}-{}", name, std::process::id())
+    }
+
+    #[test]
+    fn operations_are_listed_until_their_guard_drops() {
+        let id = id("list");
+        let guard = begin(&id, OperationKind::Pull, "app", Environment::Linux).unwrap();
+        assert!(list_operations().iter().any(|o| o.id == id && o.kind == OperationKind::Pull));
+        assert!(begin(&id, OperationKind::Pull, "app", Environment::Linux).unwrap_err().is(&ErrorKind::AlreadyExists));
+
+        drop(guard);
+        assert!(!list_operations().iter().any(|o| o.id == id));
+    }
+
+    #[test]
+    fn cancel_kills_wsl_groups_then_host_trees() {
+        let id = id("kill");
+        let _guard = begin(&id, OperationKind::Clone, "ketra", Environment::Wsl).unwrap();
+        assert!(operations().attach(&id, Pid::Host(4242)));
+        assert!(operations().attach(&id, Pid::Wsl(77)));
+
+        let runner = FakeRunner::new();
+        cancel(&runner, &id).unwrap();
+
+        let lines = runner.lines();
+        assert_eq!(lines[0], "wsl --exec kill -TERM -- -77");
+        assert!(lines[1].contains("4242"));
+        assert!(list_operations().iter().any(|o| o.id == id && o.cancelled));
+
+        // Processes started after the cancel are refused
+        assert!(!operations().attach(&id, Pid::Host(4343)));
+        assert!(cancel(&runner, "no-such-operation").unwrap_err().is(&ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn cancelled_operations_clean_up_their_folder() {
+        let id = id("cleanup");
+        let guard = begin(&id, OperationKind::Clone, "ketra", Environment::Wsl).unwrap();
+        guard.cleanup_on_cancel("/home/dev/ketra/app");
+        assert!(guard.check().is_ok());
+
+        let runner = FakeRunner::new();
+        cancel(&runner, &id).unwrap();
+        assert!(guard.check().unwrap_err().is(&ErrorKind::Cancelled));
+
+        let failed: Result<(), KetraError> = Err(KetraError::new(ErrorKind::Failed, "git clone: signal 15"));
+        let err = guard.settle(&runner, failed).unwrap_err();
+        assert!(err.is(&ErrorKind::Cancelled));
+        assert!(runner.ran("wsl --exec rm -rf -- /home/dev/ketra/app"));
+        drop(guard);
+        assert!(!list_operations().iter().any(|o| o.id == id));
+    }
+
+    #[test]
+    fn finished_operations_keep_their_result() {
+        let id = id("settle");
+        let guard = begin(&id, OperationKind::TemplateInit, "app", Environment::Wsl).unwrap();
+        guard.cleanup_on_cancel("/home/dev/ketra/app");
+
+        let runner = FakeRunner::new().on("rm", CommandOutput::fail("should not run"));
+        assert_eq!(guard.settle(&runner, Ok(7)).unwrap(), 7);
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn wsl_commands_run_in_their_own_session() {
+        let spec = tracked(Environment::Wsl.command("git", &["clone", "url"], Some("/home/dev")), Environment::Wsl, "op");
+        assert_eq!(spec.args, ["--cd", "/home/dev", "--exec", "setsid", "-w", "sh", "-c", WSL_WRAPPER, "sh", "git", "clone", "url"]);
+        assert_eq!(spec.operation.as_deref(), Some("op"));
+
+        let spec = tracked(Environment::Linux.command("git", &["pull"], Some("/srv/app")), Environment::Linux, "op");
+        assert_eq!(spec.args, ["pull"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancel_stops_a_real_process_tree() {
+        use crate::exec::system;
+        use std::time::{Duration, Instant};
+
+        let id = id("real");
+        let guard = begin(&id, OperationKind::TemplateInit, "sleep", Environment::Linux).unwrap();
+        let spec = tracked(CommandSpec::new("sh").args(&["-c", "sleep 30 & sleep 30; wait"]), Environment::Linux, &id);
+
+        let started = Instant::now();
+        let worker = std::thread::spawn(move || system().output(&spec).unwrap());
+        while operations().running.get(&id).is_none_or(|r| r.pids.is_empty()) {
+            assert!(started.elapsed() < Duration::from_secs(5), "process never registered");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        cancel(system(), &id).unwrap();
+        let output = worker.join().unwrap();
+        assert!(!output.success);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(guard.settle(system(), Ok(())).unwrap_err().is(&ErrorKind::Cancelled));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::environment::Environment;
+use crate::exec::{CommandOutput, CommandRunner};
+use crate::operations::tracked;
+use serde::Serialize;
+use std::io;
+use tauri::{AppHandle, Emitter};
+
+pub const OPERATION_PROGRESS: &str = "operation-progress";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ProgressEvent {
+    // A git progress line, e.g. "Receiving objects:  45% (450/1000), 1.2 MiB"
+    Progress {
+        phase: String,
+        percent: Option<u8>,
+        current: Option<u64>,
+        total: Option<u64>,
+        // Set on the last line of a phase ("..., done.")
+        done: bool,
+    },
+    // Any other output
+    Line { text: String },
+    // The process exited; `log` is its output with each progress phase
+    // collapsed to its final line
+    Finished { success: bool, log: String },
+}
+
+// Where an operation's progress goes: Tauri events in the app, a list in tests
+pub trait ProgressSink: Send + Sync {
+    // The operation the events belong to, which can also be cancelled by it
+    fn operation_id(&self) -> &str;
+
+    fn send(&self, event: ProgressEvent);
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OperationEvent {
+    operation_id: String,
+    #[serde(flatten)]
+    event: ProgressEvent,
+}
+
+// Emits OPERATION_PROGRESS events for the operation the UI started
+pub struct EventProgress {
+    app: AppHandle,
+    operation_id: String,
+}
+
+impl EventProgress {
+    pub fn new(app: AppHandle, operation_id: String) -> Self {
+        EventProgress { app, operation_id }
+    }
+}
+
+impl ProgressSink for EventProgress {
+    fn operation_id(&self) -> &str {
+        &self.operation_id
+    }
+
+    fn send(&self, event: ProgressEvent) {
+        let payload = OperationEvent {
+            operation_id: self.operation_id.clone(),
+            event,
+        };
+        if let Err(e) = self.app.emit(OPERATION_PROGRESS, payload) {
+            println!("[DEBUG] Failed to emit progress event: {}", e);
+        }
+    }
+}
+
+// Run git with `--progress` added after the subcommand, reporting each line,
+// as a process of the sink's operation. The returned stderr has the progress
+// redraws removed so errors built from it stay readable.
+pub fn run_git_with_progress(
+    runner: &dyn CommandRunner,
+    env: Environment,
+    dir: Option<&str>,
+    args: &[&str],
+    sink: &dyn ProgressSink,
+) -> io::Result<CommandOutput> {
+    let mut full = args.to_vec();
+    full.insert(1, "--progress");
+
+    let mut log: Vec<String> = Vec::new();
+    let mut last_phase: Option<String> = None;
+    let spec = tracked(env.command("git", &full, dir), env, sink.operation_id());
+    let mut output = runner.stream(&spec, &mut |line| {
+        let event = parse_line(line);
+        match &event {
+            ProgressEvent::Progress { phase, .. } => {
+                // A redraw of the same phase replaces its previous line
+                if last_phase.as_deref() == Some(phase.as_str()) {
+                    log.pop();
+                }
+                last_phase = Some(phase.clone());
+            }
+            _ => last_phase = None,
+        }
+        log.push(line.to_string());
+        sink.send(event);
+    })?;
+
+    output.stderr = output
+        .stderr
+        .split(['\r', '\n'])
+        .filter(|line| !line.is_empty() && !matches!(parse_line(line), ProgressEvent::Progress { .. }))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    sink.send(ProgressEvent::Finished {
+        success: output.success,
+        log: log.join("\n"),
+    });
+    Ok(output)
+}
+
+// Classify a line of git output. Progress lines look like
+// "Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s" or
+// "Enumerating objects: 12, done.", optionally prefixed with "remote: ".
+pub fn parse_line(line: &str) -> ProgressEvent {
+    let text = line.trim_end();
+    parse_progress(text).unwrap_or_else(|| ProgressEvent::Line { text: text.to_string() })
+}
+
+fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (phase, rest) = line.split_once(": ")?;
+    if phase.is_empty() || !phase.starts_with(|c: char| c.is_ascii_uppercase()) || !phase.chars().all(|c| c.is_ascii_alphabetic() || c == ' ') {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let done = rest.contains(", done");
+
+    let (percent, current, total) = match rest.split_once('%') {
+        Some((percent, after)) => {
+            let percent = percent.trim().parse::<u8>().ok()?;
+            // " (450/1000), 1.20 MiB ..."
+            let counts = after.trim_start().strip_prefix('(').and_then(|c| c.split_once(')')).and_then(|(c, _)| c.split_once('/'));
+            let (current, total) = match counts {
+                Some((current, total)) => (current.parse().ok(), total.parse().ok()),
+                None => (None, None),
+            };
+            (Some(percent), current, total)
+        }
+        None => {
+            let count: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            (None, Some(count.parse().ok()?), None)
+        }
+    };
+
+    Some(ProgressEvent::Progress {
+        phase: phase.to_string(),
+        percent,
+        current,
+        total,
+        done,
+    })
+}
+
+#[cfg(test)]
+pub mod record {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    // Keeps every event it is sent, under an operation id of its own
+    pub struct Recorder {
+        id: String,
+        events: Mutex<Vec<ProgressEvent>>,
+    }
+
+    impl Default for Recorder {
+        fn default() -> Self {
+            Recorder {
+                id: format!("test-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+                events: Mutex::default(),
+            }
+        }
+    }
+
+    impl Recorder {
+        pub fn events(&self) -> Vec<ProgressEvent> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    impl ProgressSink for Recorder {
+        fn operation_id(&self) -> &str {
+            &self.id
+        }
+
+        fn send(&self, event: ProgressEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::record::Recorder;
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+
+    fn progress(phase: &str, percent: Option<u8>, current: Option<u64>, total: Option<u64>, done: bool) -> ProgressEvent {
+        ProgressEvent::Progress {
+            phase: phase.to_string(),
+            percent,
+            current,
+            total,
+            done,
+        }
+    }
+
+    #[test]
+    fn parses_git_progress_lines() {
+        let cases = [
+            ("Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s", progress("Receiving objects", Some(45), Some(450), Some(1000), false)),
+            ("Resolving deltas: 100% (12/12), done.", progress("Resolving deltas", Some(100), Some(12), Some(12), true)),
+            ("remote: Enumerating objects: 1234, done.", progress("Enumerating objects", None, Some(1234), None, true)),
+            ("remote: Counting objects:   3% (4/120)", progress("Counting objects", Some(3), Some(4), Some(120), false)),
+            ("Writing objects: 100% (3/3), 290 bytes | 290.00 KiB/s, done.", progress("Writing objects", Some(100), Some(3), Some(3), true)),
+            ("Updating files:  50% (1/2)", progress("Updating files", Some(50), Some(1), Some(2), false)),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_line(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn other_output_is_a_line() {
+        for line in [
+            "Cloning into 'ketra'...",
+            "To github.com:ketra/app.git",
+            "   1a2b3c4..5d6e7f8  main -> main",
+            "fatal: repository 'x' not found",
+            "error: failed to push some refs to 'github.com:ketra/app.git'",
+            "hint: Updates were rejected: 12 commits",
+        ] {
+            assert_eq!(parse_line(line), ProgressEvent::Line { text: line.to_string() });
+        }
+    }
+
+    #[test]
+    fn streams_progress_and_collapses_the_log() {
+        let stderr = "Cloning into 'app'...\nReceiving objects:  50% (1/2)\rReceiving objects: 100% (2/2), done.\nfatal: early EOF\n";
+        let runner = FakeRunner::new().on("clone", CommandOutput { success: false, stdout: String::new(), stderr: stderr.to_string() });
+        let sink = Recorder::default();
+
+        let output = run_git_with_progress(&runner, Environment::Wsl, Some("/home/dev"), &["clone", "--", "url"], &sink).unwrap();
+        assert!(runner.ran("git clone --progress -- url"));
+        assert_eq!(output.stderr, "Cloning into 'app'...\nfatal: early EOF\n");
+
+        let events = sink.events();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[1], progress("Receiving objects", Some(50), Some(1), Some(2), false));
+        assert_eq!(
+            events[4],
+            ProgressEvent::Finished {
+                success: false,
+                log: "Cloning into 'app'...\nReceiving objects: 100% (2/2), done.\nfatal: early EOF".to_string(),
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn system_runner_streams_carriage_return_lines() {
+        use crate::exec::{system, CommandSpec};
+
+        let spec = CommandSpec::new("sh").args(&["-c", "printf 'a: 1%% (1/9)\\rb\\n' >&2; printf 'out\\n'"]);
+        let mut lines = Vec::new();
+        let output = system().stream(&spec, &mut |line| lines.push(line.to_string())).unwrap();
+
+        lines.sort();
+        assert_eq!(lines, ["a: 1% (1/9)", "b", "out"]);
+        assert!(output.success);
+        assert_eq!((output.stdout.as_str(), output.stderr.as_str()), ("out\n", "a: 1% (1/9)\rb\n"));
+    }
+}
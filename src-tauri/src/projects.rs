@@ -1,88 +1,63 @@
+use crate::detect::detect_project;
+use crate::discovery::discover;
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
 use crate::types::Project;
-use crate::git::{get_git_status, get_git_status_wsl};
+use crate::roots::{roots_for, ProjectRoot};
+use crate::status_cache::{cached_git_status, status_cache};
+use crate::wsl_scan;
 use crate::utils::get_default_folder;
-use std::fs;
-use std::process::Command;
 
-pub async fn scan_launcher_folders_async() -> Vec<Project> {
-    let mut project_futures = Vec::new();
+// Folders under every root of `env`, without git status
+pub fn scan_roots(runner: &dyn CommandRunner, env: Environment, configured: &[ProjectRoot]) -> Vec<Project> {
+    if env == Environment::Wsl {
+        return wsl_scan::scan(runner, configured, false);
+    }
+
+    let mut projects: Vec<Project> = Vec::new();
+
+    for root in roots_for(env, configured) {
+        let base = match root.resolve(runner) {
+            Ok(base) => base,
+            Err(_) => continue,
+        };
 
-    // Scan Windows projects folder
-    if let Ok(windows_folder) = get_default_folder("windows") {
-        if let Ok(entries) = fs::read_dir(&windows_folder) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_dir() {
-                        if let Some(name) = entry.file_name().to_str() {
-                            let path = entry.path();
-                            let path_str = path.to_string_lossy().to_string();
-                            let name = name.to_string();
-
-                            // Get last modified time
-                            let last_opened = metadata.modified()
-                                .ok()
-                                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
-                                .map(|d| d.as_secs())
-                                .unwrap_or(0);
-
-                            // Spawn async task to get git status in parallel
-                            let future = tokio::task::spawn_blocking(move || {
-                                let git_status = get_git_status(&path_str);
-                                Project {
-                                    name,
-                                    path: path_str,
-                                    last_opened,
-                                    git_status,
-                                    is_pinned: false,
-                                }
-                            });
-                            project_futures.push(future);
-                        }
-                    }
-                }
+        for dir in discover(env, &base, root.depth) {
+            // Overlapping roots (~/code and ~/code/clients) can find the same folder twice
+            if !root.accepts(&dir.relative) || projects.iter().any(|p| p.path == dir.path) {
+                continue;
             }
+            projects.push(Project::new(dir.name, dir.path, env, base.clone(), dir.modified));
         }
     }
 
-    // Scan WSL projects folder
-    if let Ok(wsl_folder) = get_default_folder("wsl") {
-        let wsl_projects_path = wsl_folder.clone();
-
-        let wsl_result = Command::new("wsl")
-            .args(&["bash", "-c", &format!("find {} -maxdepth 1 -type d -not -path {}", wsl_projects_path, wsl_projects_path)])
-            .output();
-
-        if let Ok(output) = wsl_result {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    let path = line.trim();
-                    if !path.is_empty() {
-                        if let Some(name) = path.split('/').last() {
-                            let path_owned = path.to_string();
-                            let name_owned = name.to_string();
-
-                            // Spawn async task for WSL git status in parallel
-                            let future = tokio::task::spawn_blocking(move || {
-                                let git_status = get_git_status_wsl(&path_owned);
-                                Project {
-                                    name: name_owned,
-                                    path: path_owned,
-                                    last_opened: 0,
-                                    git_status,
-                                    is_pinned: false,
-                                }
-                            });
-                            project_futures.push(future);
-                        }
-                    }
-                }
-            }
+    projects
+}
+
+pub async fn scan_launcher_folders_async(runner: &'static dyn CommandRunner, roots: Vec<ProjectRoot>) -> Vec<Project> {
+    let mut project_futures = Vec::new();
+
+    // Scan the project roots of every environment on this machine
+    for env in Environment::available() {
+        if env == Environment::Wsl {
+            continue;
+        }
+        for mut project in scan_roots(runner, env, &roots) {
+            // Spawn async task to get git status in parallel
+            let future = tokio::task::spawn_blocking(move || {
+                project.git_status = cached_git_status(runner, &project.path, env);
+                project.info = detect_project(runner, env, &project.path);
+                project
+            });
+            project_futures.push(future);
         }
     }
 
+    // WSL projects come with their status from a single wsl call
+    let mut all_projects = scan_wsl_projects(runner, roots, true).await;
+
     // Wait for all git status checks to complete in parallel
-    let mut all_projects = Vec::new();
     for future in project_futures {
         if let Ok(project) = future.await {
             all_projects.push(project);
@@ -92,284 +67,171 @@ pub async fn scan_launcher_folders_async() -> Vec<Project> {
     all_projects
 }
 
-pub fn scan_launcher_folders_fast() -> Vec<Project> {
-    // Only scan Windows folder synchronously - it's instant
+pub fn scan_launcher_folders_fast(runner: &dyn CommandRunner, roots: &[ProjectRoot]) -> Vec<Project> {
+    // Only scan the host environment synchronously - it's instant
     // WSL scanning will happen in background to avoid blocking
-    let mut all_projects = Vec::new();
-
-    if let Ok(windows_folder) = get_default_folder("windows") {
-        if let Ok(entries) = fs::read_dir(&windows_folder) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    if metadata.is_dir() {
-                        if let Some(name) = entry.file_name().to_str() {
-                            let path = entry.path().to_string_lossy().to_string();
-                            let last_opened = metadata.modified()
-                                .ok()
-                                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
-                                .map(|d| d.as_secs())
-                                .unwrap_or(0);
-
-                            all_projects.push(Project {
-                                name: name.to_string(),
-                                path,
-                                last_opened,
-                                git_status: None,
-                                is_pinned: false,
-                            });
-                        }
-                    }
-                }
-            }
-        }
-    }
+    let env = Environment::host();
+    let mut projects = scan_roots(runner, env, roots);
+    status_cache().fill_last_known(&mut projects);
 
-    all_projects
+    // Reading a few local manifests is cheap; WSL projects are detected lazily
+    for project in &mut projects {
+        project.info = detect_project(runner, env, &project.path);
+    }
+    projects
 }
 
-// New async function to scan WSL in background
-pub async fn scan_wsl_projects() -> Vec<Project> {
-    let mut wsl_projects = Vec::new();
-
-    if let Ok(wsl_folder) = get_default_folder("wsl") {
-        // Use wsl.exe directly with ls command (faster than bash -c)
-        let wsl_result = tokio::process::Command::new("wsl.exe")
-            .args(&["ls", "-1", &wsl_folder])
-            .output()
-            .await;
-
-        if let Ok(output) = wsl_result {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for name in stdout.lines() {
-                    let name = name.trim();
-                    if !name.is_empty() {
-                        let path = format!("{}/{}", wsl_folder, name);
-                        wsl_projects.push(Project {
-                            name: name.to_string(),
-                            path,
-                            last_opened: 0,
-                            git_status: None,
-                            is_pinned: false,
-                        });
-                    }
-                }
-            }
-        }
+// WSL projects in the background. Without `with_status` they come with the
+// last known git status, which is quick; with it, the status is checked too.
+pub async fn scan_wsl_projects(runner: &'static dyn CommandRunner, roots: Vec<ProjectRoot>, with_status: bool) -> Vec<Project> {
+    if !Environment::available().contains(&Environment::Wsl) {
+        return Vec::new();
     }
 
-    wsl_projects
+    let mut projects = tokio::task::spawn_blocking(move || wsl_scan::scan(runner, &roots, with_status))
+        .await
+        .unwrap_or_default();
+
+    let mut cache = status_cache();
+    if with_status {
+        for project in &projects {
+            cache.remember(project.env, &project.path, project.git_status.clone());
+        }
+    } else {
+        cache.fill_last_known(&mut projects);
+    }
+    projects
 }
 
-pub async fn delete_project(path: String, name: String) -> Result<(), String> {
+pub async fn delete_project(runner: &dyn CommandRunner, path: String, name: String) -> Result<(), KetraError> {
     use crate::github::delete_github_repo;
 
     // Try to delete GitHub repo first (if it exists)
-    match delete_github_repo(name.clone()).await {
+    match delete_github_repo(runner, name.clone()).await {
         Ok(_) => println!("GitHub repo '{}' deleted or didn't exist", name),
         Err(e) => println!("Warning: Could not delete GitHub repo '{}': {}", name, e),
     }
 
-    // Check if path exists first
-    let exists = if path.starts_with("/home/") || path.starts_with("/mnt/") {
-        // WSL path
-        let check = Command::new("wsl")
-            .args(&["test", "-d", &path])
-            .status();
-
-        match check {
-            Ok(status) => status.success(),
-            Err(_) => false
-        }
-    } else {
-        // Windows path
-        std::path::Path::new(&path).exists()
-    };
+    let env = Environment::from_path(&path);
 
-    if !exists {
-        return Err(format!("Project folder does not exist: {}", path));
+    // Check if path exists first
+    if !env.dir_exists(runner, &path) {
+        return Err(KetraError::new(ErrorKind::PathNotFound, format!("Project folder does not exist: {}", path)));
     }
 
     // Delete the local project folder
-    if path.starts_with("/home/") || path.starts_with("/mnt/") {
-        // WSL path - use WSL command to delete
-        let result = Command::new("wsl")
-            .args(&["rm", "-rf", &path])
-            .output()
-            .map_err(|e| format!("Failed to delete WSL project: {}", e))?;
-
-        if !result.status.success() {
-            let stderr = String::from_utf8_lossy(&result.stderr);
-            return Err(format!("Failed to delete WSL project: {}", stderr));
-        }
-    } else {
-        // Windows path - just delete the folder
-        fs::remove_dir_all(&path)
-            .map_err(|e| {
-                // Check if it's a permission/access error (likely because folder is open)
-                if e.kind() == std::io::ErrorKind::PermissionDenied {
-                    format!("Cannot delete project - please close VSCode or any programs using this folder first")
-                } else {
-                    format!("Failed to delete project folder '{}': {}", path, e)
-                }
-            })?;
-    }
-
-    Ok(())
+    env.remove_dir_all(runner, &path)
 }
 
-pub fn check_project_exists(name: String, env: String) -> Result<bool, String> {
-    let base_folder = get_default_folder(&env)?;
-
-    let full_path = if env == "wsl" {
-        format!("{}/{}", base_folder, name)
-    } else {
-        format!("{}\\{}", base_folder, name)
-    };
+pub fn check_project_exists(runner: &dyn CommandRunner, name: String, env: Environment) -> Result<bool, KetraError> {
+    let base_folder = get_default_folder(runner, env)?;
+    let full_path = env.join(&base_folder, &name);
 
     // Check if path exists
-    let exists = if env == "wsl" {
-        let result = Command::new("wsl")
-            .args(&["test", "-d", &full_path])
-            .status();
-
-        match result {
-            Ok(status) => status.success(),
-            Err(_) => false
-        }
-    } else {
-        std::path::Path::new(&full_path).exists()
-    };
-
-    Ok(exists)
+    Ok(env.dir_exists(runner, &full_path))
 }
 
-pub fn open_existing_project(path: String, env: String) -> Result<(), String> {
+pub fn open_existing_project(runner: &dyn CommandRunner, path: String, env: Environment) -> Result<(), KetraError> {
     // Just open VSCode for an existing project
-    let ps_script = if env == "wsl" {
-        format!(
-            r#"code --folder-uri 'vscode-remote://wsl+Ubuntu{}'"#,
-            path
-        )
-    } else {
-        format!(
-            r#"code '{}'"#,
-            path
-        )
-    };
-
-    Command::new("powershell")
-        .args(&["-NoProfile", "-Command", &ps_script])
-        .spawn()
-        .map_err(|e| format!("Failed to open VSCode: {}", e))?;
-
-    Ok(())
+    env.open_editor(runner, &path)
 }
 
-pub async fn paste_folder(env: String) -> Result<String, String> {
+#[cfg(windows)]
+fn clipboard_folder() -> Result<String, KetraError> {
     use clipboard_win::{formats, get_clipboard};
-    use crate::utils::get_default_folder;
 
     // Get clipboard contents (Windows file paths)
     let clipboard_data: Vec<String> = get_clipboard(formats::FileList)
         .unwrap_or_default();
 
-    if clipboard_data.is_empty() {
-        return Err("No folder in clipboard".to_string());
-    }
+    clipboard_data.into_iter().next().ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, "No folder in clipboard"))
+}
 
-    let source_path = clipboard_data.first().ok_or("No folder in clipboard")?;
+#[cfg(not(windows))]
+fn clipboard_folder() -> Result<String, KetraError> {
+    Err(KetraError::new(ErrorKind::ToolMissing, "Pasting folders from the clipboard is only supported on Windows"))
+}
+
+pub async fn paste_folder(runner: &dyn CommandRunner, env: Environment) -> Result<String, KetraError> {
+    let source_path = clipboard_folder()?;
 
     // Check if it's a directory
-    if !std::path::Path::new(source_path).is_dir() {
-        return Err("Clipboard contains a file, not a folder".to_string());
+    if !std::path::Path::new(&source_path).is_dir() {
+        return Err(KetraError::new(ErrorKind::InvalidInput, "Clipboard contains a file, not a folder"));
     }
 
     // Get folder name
-    let folder_name = std::path::Path::new(source_path)
+    let folder_name = std::path::Path::new(&source_path)
         .file_name()
-        .ok_or("Invalid folder name")?
-        .to_str()
-        .ok_or("Invalid folder name")?;
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| KetraError::new(ErrorKind::InvalidInput, "Invalid folder name"))?
+        .to_string();
 
-    let base_folder = get_default_folder(&env)?;
-    let dest_path = if env == "wsl" {
-        format!("{}/{}", base_folder, folder_name)
-    } else {
-        format!("{}\\{}", base_folder, folder_name)
-    };
+    let base_folder = get_default_folder(runner, env)?;
+    let dest_path = env.join(&base_folder, &folder_name);
 
     // Check if destination already exists
-    let exists = if env == "wsl" {
-        let check = Command::new("wsl")
-            .args(&["test", "-d", &dest_path])
-            .status();
-        match check {
-            Ok(status) => status.success(),
-            Err(_) => false
-        }
-    } else {
-        std::path::Path::new(&dest_path).exists()
-    };
-
-    if exists {
-        return Err(format!("Project '{}' already exists", folder_name));
+    if env.dir_exists(runner, &dest_path) {
+        return Err(KetraError::new(ErrorKind::AlreadyExists, format!("Project '{}' already exists", folder_name)));
     }
 
     // Copy folder
-    if env == "wsl" {
-        // Convert Windows path to WSL path for source
-        let wsl_source = source_path.replace("\\", "/").replace("C:", "/mnt/c");
-        Command::new("wsl")
-            .args(&["cp", "-r", &wsl_source, &dest_path])
-            .output()
-            .map_err(|e| format!("Failed to copy folder: {}", e))?;
-    } else {
-        // Use robocopy for Windows
-        Command::new("robocopy")
-            .args(&[source_path, &dest_path, "/E", "/NFL", "/NDL", "/NJH", "/NJS"])
-            .output()
-            .map_err(|e| format!("Failed to copy folder: {}", e))?;
-    }
+    env.copy_dir_from_host(runner, &source_path, &dest_path)?;
 
-    Ok(folder_name.to_string())
+    Ok(folder_name)
 }
 
-pub fn open_ketra_folder(env: String) -> Result<(), String> {
-    let base_folder = get_default_folder(&env)?;
-
-    if env == "wsl" {
-        // Open WSL folder in Windows Explorer
-        Command::new("explorer.exe")
-            .arg(format!("\\\\wsl$\\Ubuntu{}", base_folder))
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    } else {
-        Command::new("explorer")
-            .arg(&base_folder)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    }
-
-    Ok(())
+pub fn open_ketra_folder(runner: &dyn CommandRunner, env: Environment) -> Result<(), KetraError> {
+    let base_folder = get_default_folder(runner, env)?;
+    env.open_folder(runner, &base_folder)
 }
 
-pub fn open_terminal(env: String) -> Result<(), String> {
-    let base_folder = get_default_folder(&env)?;
+pub fn open_terminal(runner: &dyn CommandRunner, env: Environment) -> Result<(), KetraError> {
+    let base_folder = get_default_folder(runner, env)?;
+    env.open_terminal(runner, &base_folder)
+}
 
-    if env == "wsl" {
-        // Open Windows Terminal in WSL
-        Command::new("wt")
-            .args(&["-d", &base_folder])
-            .spawn()
-            .map_err(|e| format!("Failed to open terminal: {}", e))?;
-    } else {
-        // Open Windows Terminal in Windows
-        Command::new("wt")
-            .args(&["-d", &base_folder])
-            .spawn()
-            .map_err(|e| format!("Failed to open terminal: {}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    #[test]
+    fn scans_every_wsl_root() {
+        let output = [
+            r#"{"root":0,"base":"/home/dev/ketra","path":"/home/dev/ketra/app","modified":1,"git":null}"#,
+            r#"{"root":1,"base":"/srv/work","path":"/srv/work/clients/acme","modified":1,"git":null}"#,
+            r#"{"root":1,"base":"/srv/work","path":"/srv/work/clients/acme-old","modified":1,"git":null}"#,
+            r#"{"root":1,"base":"/srv/work","path":"/srv/work/scratch/tmp","modified":1,"git":null}"#,
+        ];
+        let runner = FakeRunner::new().on("--exec sh -c", CommandOutput::ok(&output.join("\n")));
+
+        let work = ProjectRoot {
+            depth: 2,
+            include: vec!["clients/*".to_string()],
+            exclude: vec!["*-old".to_string(), "*/*-old".to_string()],
+            ..ProjectRoot::new(Environment::Wsl, "/srv/work")
+        };
+        let roots = vec![ProjectRoot::default_for(Environment::Wsl), work, ProjectRoot::new(Environment::Linux, "/elsewhere")];
+
+        let projects = scan_roots(&runner, Environment::Wsl, &roots);
+        let found: Vec<(&str, &str)> = projects.iter().map(|p| (p.path.as_str(), p.root.as_str())).collect();
+        assert_eq!(found, [("/home/dev/ketra/app", "/home/dev/ketra"), ("/srv/work/clients/acme", "/srv/work")]);
+        assert_eq!(runner.calls().len(), 1);
+        assert!(runner.ran("3 ~/ketra 2 /srv/work"));
+        assert!(!runner.ran("/elsewhere"));
     }
 
-    Ok(())
+    #[test]
+    fn overlapping_roots_list_a_project_once() {
+        let output = [
+            r#"{"root":0,"base":"/srv/code","path":"/srv/code/app","modified":1,"git":null}"#,
+            r#"{"root":1,"base":"/srv/code","path":"/srv/code/app","modified":1,"git":null}"#,
+        ];
+        let runner = FakeRunner::new().on("--exec sh -c", CommandOutput::ok(&output.join("\n")));
+        let roots = vec![ProjectRoot::new(Environment::Wsl, "/srv/code"), ProjectRoot::new(Environment::Wsl, "/srv/code/")];
+
+        assert_eq!(scan_roots(&runner, Environment::Wsl, &roots).len(), 1);
+    }
 }
@@ -0,0 +1,426 @@
+use crate::environment::Environment;
+use crate::error::KetraError;
+use crate::roots::ProjectRoot;
+use crate::types::Project;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+// Bump when the file layout changes and add a step to `migrate`
+pub const SCHEMA_VERSION: u32 = 2;
+
+// What the user has told us about a project, as opposed to what a scan finds
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectEntry {
+    pub pinned: bool,
+    pub order: Option<u32>,
+    pub last_opened: u64,
+    pub metadata: BTreeMap<String, String>,
+}
+
+// Projects are identified by environment + canonical path, so `app` on
+// Windows and `app` in WSL are two different entries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectKey {
+    pub env: Environment,
+    pub path: String,
+}
+
+impl ProjectKey {
+    pub fn new(env: Environment, path: &str) -> Self {
+        ProjectKey {
+            env,
+            path: canonical_path(env, path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredProject {
+    #[serde(flatten)]
+    key: ProjectKey,
+    #[serde(flatten)]
+    entry: ProjectEntry,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    version: u32,
+    #[serde(default)]
+    legacy_imported: bool,
+    #[serde(default)]
+    projects: Vec<StoredProject>,
+    #[serde(default)]
+    roots: Vec<ProjectRoot>,
+}
+
+// Pins and order as the webview used to keep them in localStorage
+// (`ketra_pinned_projects`, `ketra_project_order`), keyed by project name
+#[derive(Debug, Default, Deserialize)]
+pub struct LegacyState {
+    pub pinned: Vec<String>,
+    pub order: Vec<String>,
+}
+
+pub struct Registry {
+    file: PathBuf,
+    data: RegistryFile,
+}
+
+impl Registry {
+    // A missing file is an empty registry. An unreadable one is moved aside
+    // so the next save doesn't destroy it.
+    pub fn load(file: PathBuf) -> Self {
+        let data = match fs::read_to_string(&file) {
+            Ok(text) => match serde_json::from_str(&text).map_err(|e| e.to_string()).and_then(migrate) {
+                Ok(data) => data,
+                Err(e) => {
+                    println!("[DEBUG] Project registry {} is unreadable ({}), starting fresh", file.display(), e);
+                    fs::rename(&file, file.with_extension("json.bak")).ok();
+                    RegistryFile::default()
+                }
+            },
+            Err(_) => RegistryFile::default(),
+        };
+
+        Registry { file, data }
+    }
+
+    pub fn save(&self) -> Result<(), KetraError> {
+        let path = self.file.to_string_lossy().to_string();
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| KetraError::io("Failed to create config folder", &path, e))?;
+        }
+
+        let data = RegistryFile {
+            version: SCHEMA_VERSION,
+            legacy_imported: self.data.legacy_imported,
+            projects: self.data.projects.clone(),
+            roots: self.data.roots.clone(),
+        };
+        let json = serde_json::to_string_pretty(&data)
+            .map_err(|e| KetraError::failed(format!("Failed to serialize project registry: {}", e)))?;
+
+        // Write then rename so a crash never leaves a half-written file
+        let tmp = self.file.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| KetraError::io("Failed to save project registry", &path, e))?;
+        fs::rename(&tmp, &self.file).map_err(|e| KetraError::io("Failed to save project registry", &path, e))
+    }
+
+    pub fn get(&self, key: &ProjectKey) -> Option<&ProjectEntry> {
+        self.data.projects.iter().find(|p| &p.key == key).map(|p| &p.entry)
+    }
+
+    fn entry_mut(&mut self, key: ProjectKey) -> &mut ProjectEntry {
+        let index = match self.data.projects.iter().position(|p| p.key == key) {
+            Some(index) => index,
+            None => {
+                self.data.projects.push(StoredProject { key, entry: ProjectEntry::default() });
+                self.data.projects.len() - 1
+            }
+        };
+        &mut self.data.projects[index].entry
+    }
+
+    // Returns the new pinned state
+    pub fn toggle_pin(&mut self, key: ProjectKey) -> bool {
+        let entry = self.entry_mut(key);
+        entry.pinned = !entry.pinned;
+        entry.pinned
+    }
+
+    // Number the given projects in order; projects not listed keep their place
+    pub fn set_order(&mut self, keys: Vec<ProjectKey>) {
+        for (index, key) in keys.into_iter().enumerate() {
+            self.entry_mut(key).order = Some(index as u32);
+        }
+    }
+
+    // Record that the project was just opened
+    pub fn touch(&mut self, key: ProjectKey, timestamp: u64) {
+        self.entry_mut(key).last_opened = timestamp;
+    }
+
+    // Set or (with `None`) remove a user metadata field
+    pub fn set_metadata(&mut self, key: ProjectKey, field: String, value: Option<String>) {
+        let entry = self.entry_mut(key);
+        match value {
+            Some(value) => entry.metadata.insert(field, value),
+            None => entry.metadata.remove(&field),
+        };
+    }
+
+    // Copy registry state onto freshly scanned projects
+    pub fn annotate(&self, projects: &mut [Project]) {
+        for project in projects {
+            if let Some(entry) = self.get(&ProjectKey::new(project.env, &project.path)) {
+                project.is_pinned = entry.pinned;
+                project.order = entry.order;
+                project.metadata = entry.metadata.clone();
+                project.last_opened = entry.last_opened;
+            }
+        }
+    }
+
+    // Configured project roots of every environment
+    pub fn roots(&self) -> Vec<ProjectRoot> {
+        self.data.roots.clone()
+    }
+
+    pub fn set_roots(&mut self, roots: Vec<ProjectRoot>) {
+        self.data.roots = roots;
+    }
+
+    pub fn legacy_imported(&self) -> bool {
+        self.data.legacy_imported
+    }
+
+    // One-time import of the old name-keyed localStorage state. A name that
+    // exists in several environments applies to all of them, which is what
+    // the old UI showed.
+    pub fn import_legacy(&mut self, projects: &[Project], legacy: &LegacyState) -> usize {
+        let mut imported = 0;
+        for project in projects {
+            let key = ProjectKey::new(project.env, &project.path);
+            let pinned = legacy.pinned.contains(&project.name);
+            let order = legacy.order.iter().position(|n| n == &project.name);
+            if !pinned && order.is_none() {
+                continue;
+            }
+
+            let entry = self.entry_mut(key);
+            entry.pinned |= pinned;
+            if entry.order.is_none() {
+                entry.order = order.map(|i| i as u32);
+            }
+            imported += 1;
+        }
+        self.data.legacy_imported = true;
+        imported
+    }
+}
+
+// Upgrade a registry file written by an older version of ketra
+fn migrate(mut value: serde_json::Value) -> Result<RegistryFile, String> {
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    match version {
+        1 => {
+            // v2 added configurable project roots; v1 only knew ~/ketra
+            value["roots"] = serde_json::json!([]);
+            value["version"] = serde_json::json!(2);
+            migrate(value)
+        }
+        2 => serde_json::from_value(value).map_err(|e| e.to_string()),
+        v if v > SCHEMA_VERSION as u64 => {
+            // Written by a newer ketra; read what we understand
+            serde_json::from_value(value).map_err(|e| e.to_string())
+        }
+        v => Err(format!("unknown registry version {}", v)),
+    }
+}
+
+// Normalize a project path so the same folder always maps to the same key
+pub fn canonical_path(env: Environment, path: &str) -> String {
+    let sep = env.separator();
+    let trimmed = match path.trim_end_matches(sep) {
+        "" => path,
+        trimmed => trimmed,
+    };
+
+    if !env.is_local() {
+        return trimmed.to_string();
+    }
+
+    match fs::canonicalize(trimmed) {
+        Ok(canonical) => {
+            let canonical = canonical.to_string_lossy().to_string();
+            // Drop the verbatim prefix Windows adds (\\?\C:\...)
+            canonical.strip_prefix(r"\\?\").map(|s| s.to_string()).unwrap_or(canonical)
+        }
+        Err(_) => trimmed.to_string(),
+    }
+}
+
+#[cfg(not(test))]
+fn default_file() -> PathBuf {
+    // Same folder Tauri uses as the app config dir
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("com.vscode.launcher")
+        .join("projects.json")
+}
+
+// Tests must never touch the user's real registry
+#[cfg(test)]
+fn default_file() -> PathBuf {
+    std::env::temp_dir().join(format!("ketra-test-registry-{}", std::process::id())).join("projects.json")
+}
+
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+// The registry used by the Tauri commands, loaded on first use
+pub fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY
+        .get_or_init(|| Mutex::new(Registry::load(default_file())))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ketra-registry-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir.join("projects.json")
+    }
+
+    fn project(name: &str, path: &str, env: Environment) -> Project {
+        Project::new(name.to_string(), path.to_string(), env, String::new(), 100)
+    }
+
+    #[test]
+    fn same_name_in_two_environments_does_not_collide() {
+        let mut registry = Registry::load(temp_file("collide"));
+        assert!(registry.toggle_pin(ProjectKey::new(Environment::Wsl, "/home/dev/ketra/app")));
+
+        let mut projects = vec![
+            project("app", "/home/dev/ketra/app/", Environment::Wsl),
+            project("app", "/nonexistent/ketra/app", Environment::Linux),
+        ];
+        registry.annotate(&mut projects);
+
+        assert!(projects[0].is_pinned);
+        assert!(!projects[1].is_pinned);
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let file = temp_file("roundtrip");
+        let key = ProjectKey::new(Environment::Wsl, "/home/dev/ketra/app");
+
+        let mut registry = Registry::load(file.clone());
+        registry.toggle_pin(key.clone());
+        registry.set_order(vec![ProjectKey::new(Environment::Wsl, "/home/dev/ketra/b"), key.clone()]);
+        registry.set_metadata(key.clone(), "note".to_string(), Some("client work".to_string()));
+        registry.save().unwrap();
+
+        let text = fs::read_to_string(&file).unwrap();
+        assert!(text.contains("\"version\": 2"));
+
+        let registry = Registry::load(file.clone());
+        let entry = registry.get(&key).unwrap();
+        assert!(entry.pinned);
+        assert_eq!(entry.order, Some(1));
+        assert_eq!(entry.metadata["note"], "client work");
+
+        fs::remove_dir_all(file.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn unpinning_and_clearing_metadata() {
+        let mut registry = Registry::load(temp_file("unpin"));
+        let key = ProjectKey::new(Environment::Wsl, "/home/dev/ketra/app");
+
+        assert!(registry.toggle_pin(key.clone()));
+        assert!(!registry.toggle_pin(key.clone()));
+
+        registry.set_metadata(key.clone(), "note".to_string(), Some("x".to_string()));
+        registry.set_metadata(key.clone(), "note".to_string(), None);
+        assert!(registry.get(&key).unwrap().metadata.is_empty());
+    }
+
+    #[test]
+    fn last_opened_comes_from_the_registry_only() {
+        let mut registry = Registry::load(temp_file("touch"));
+        registry.touch(ProjectKey::new(Environment::Wsl, "/home/dev/ketra/app"), 1_700_000_000);
+
+        let mut projects = vec![
+            project("app", "/home/dev/ketra/app", Environment::Wsl),
+            project("web", "/home/dev/ketra/web", Environment::Wsl),
+        ];
+        registry.annotate(&mut projects);
+
+        assert_eq!(projects[0].last_opened, 1_700_000_000);
+        assert_eq!(projects[0].last_modified, 100);
+        assert_eq!(projects[1].last_opened, 0);
+    }
+
+    #[test]
+    fn imports_legacy_local_storage_by_name() {
+        let mut registry = Registry::load(temp_file("legacy"));
+        let projects = vec![
+            project("api", "/home/dev/ketra/api", Environment::Wsl),
+            project("web", "/home/dev/ketra/web", Environment::Wsl),
+            project("web", "/nonexistent/ketra/web", Environment::Linux),
+            project("other", "/home/dev/ketra/other", Environment::Wsl),
+        ];
+        let legacy = LegacyState {
+            pinned: vec!["web".to_string(), "gone".to_string()],
+            order: vec!["api".to_string(), "web".to_string()],
+        };
+
+        assert_eq!(registry.import_legacy(&projects, &legacy), 3);
+        assert!(registry.legacy_imported());
+
+        let web = registry.get(&ProjectKey::new(Environment::Linux, "/nonexistent/ketra/web")).unwrap();
+        assert!(web.pinned);
+        assert_eq!(web.order, Some(1));
+        let api = registry.get(&ProjectKey::new(Environment::Wsl, "/home/dev/ketra/api")).unwrap();
+        assert!(!api.pinned);
+        assert_eq!(api.order, Some(0));
+        assert!(registry.get(&ProjectKey::new(Environment::Wsl, "/home/dev/ketra/other")).is_none());
+    }
+
+    #[test]
+    fn migrates_and_tolerates_versions() {
+        let v1 = serde_json::json!({
+            "version": 1,
+            "projects": [{ "env": "wsl", "path": "/home/dev/app", "pinned": true }]
+        });
+        let data = migrate(v1).unwrap();
+        assert!(data.projects[0].entry.pinned);
+        assert_eq!(data.projects[0].entry.order, None);
+        assert!(data.roots.is_empty());
+
+        let v2 = serde_json::json!({
+            "version": 2,
+            "roots": [{ "env": "wsl", "path": "~/work", "depth": 2, "exclude": ["archive"] }]
+        });
+        let data = migrate(v2).unwrap();
+        assert_eq!(data.roots[0].depth, 2);
+        assert!(data.roots[0].include.is_empty());
+
+        let newer = serde_json::json!({ "version": 7, "projects": [], "future_field": 1 });
+        assert!(migrate(newer).is_ok());
+
+        assert!(migrate(serde_json::json!({ "projects": [] })).is_err());
+    }
+
+    #[test]
+    fn unreadable_files_are_kept_aside() {
+        let file = temp_file("corrupt");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "{ not json").unwrap();
+
+        let registry = Registry::load(file.clone());
+        assert!(registry.data.projects.is_empty());
+        assert!(file.with_extension("json.bak").exists());
+
+        fs::remove_dir_all(file.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn canonical_paths_ignore_trailing_separators() {
+        assert_eq!(canonical_path(Environment::Wsl, "/home/dev/app/"), "/home/dev/app");
+        assert_eq!(canonical_path(Environment::Wsl, "/"), "/");
+
+        let dir = std::env::temp_dir();
+        let dotted = format!("{}/./", dir.display());
+        assert_eq!(canonical_path(Environment::Linux, &dotted), fs::canonicalize(&dir).unwrap().to_string_lossy());
+    }
+}
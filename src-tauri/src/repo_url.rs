@@ -0,0 +1,249 @@
+use crate::error::{ErrorKind, KetraError};
+use serde::Serialize;
+
+// Forges whose repos are always `<owner>/<name>`, so anything after that in a
+// browser URL (`/tree/main`, `/src/main/docs`, ...) can be dropped
+const TWO_SEGMENT_HOSTS: &[&str] = &["github.com", "bitbucket.org"];
+// Forges that get a canonical `https://<host>/<path>.git` clone URL
+const KNOWN_HOSTS: &[&str] = &["github.com", "gitlab.com", "bitbucket.org"];
+
+// A repository as typed or pasted by the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepoUrl {
+    // Empty for local paths
+    pub host: String,
+    // Everything between the host and the name, e.g. "group/subgroup" on GitLab
+    pub owner: String,
+    pub name: String,
+    // What to pass to `git clone`
+    pub clone_url: String,
+}
+
+impl RepoUrl {
+    // Accepts:
+    // - https://github.com/owner/repo(.git), including browser URLs like .../tree/main
+    // - ssh://git@host:22/owner/repo.git and git://host/owner/repo
+    // - git@host:owner/repo.git (scp-style)
+    // - owner/repo (GitHub), gh:owner/repo and gl:group/repo
+    // - file:// URLs and local paths
+    pub fn parse(input: &str) -> Result<RepoUrl, KetraError> {
+        let input = input.trim();
+        if input.is_empty() || input.starts_with('-') || input.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(invalid(input));
+        }
+
+        if let Some(path) = input.strip_prefix("gh:") {
+            return forge("https", "github.com", path).ok_or_else(|| invalid(input));
+        }
+        if let Some(path) = input.strip_prefix("gl:") {
+            return forge("https", "gitlab.com", path).ok_or_else(|| invalid(input));
+        }
+
+        let parsed = if let Some((scheme, rest)) = input.split_once("://") {
+            parse_scheme(&scheme.to_ascii_lowercase(), rest)
+        } else if is_local_path(input) {
+            local(input)
+        } else if let Some((authority, path)) = scp_parts(input) {
+            remote(authority, path, input)
+        } else {
+            // Bare `owner/repo` means GitHub
+            match input.split('/').count() {
+                2 => forge("https", "github.com", input),
+                _ => None,
+            }
+        };
+        parsed.ok_or_else(|| invalid(input))
+    }
+}
+
+fn invalid(input: &str) -> KetraError {
+    KetraError::new(ErrorKind::InvalidInput, format!("Not a repository URL: '{}'", input))
+}
+
+fn parse_scheme(scheme: &str, rest: &str) -> Option<RepoUrl> {
+    match scheme {
+        "file" => local(rest).map(|repo| RepoUrl { clone_url: format!("file://{}", rest.trim_end_matches('/')), ..repo }),
+        "http" | "https" => {
+            let (authority, path) = rest.split_once('/')?;
+            // Browser URLs may carry a query or an anchor
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            forge(scheme, authority, path)
+        }
+        "ssh" | "git" | "git+ssh" | "ssh+git" => {
+            let (authority, path) = rest.split_once('/')?;
+            remote(authority, path, &format!("{}://{}", scheme, rest))
+        }
+        _ => None,
+    }
+}
+
+// An HTTPS repo; browser paths are cut back to the repo itself
+fn forge(scheme: &str, authority: &str, path: &str) -> Option<RepoUrl> {
+    let host = host_of(authority)?;
+    let mut segments = segments(path)?;
+    if TWO_SEGMENT_HOSTS.contains(&host.as_str()) {
+        segments.truncate(2);
+    }
+    let (owner, name) = split_name(&segments)?;
+    if owner.is_empty() {
+        return None;
+    }
+
+    let clone_url = if KNOWN_HOSTS.contains(&host.as_str()) {
+        // Keep a token in the user info, drop any port
+        let user = authority.rsplit_once('@').map(|(user, _)| format!("{}@", user)).unwrap_or_default();
+        format!("https://{}{}/{}/{}.git", user, host, owner, name)
+    } else {
+        format!("{}://{}/{}", scheme, authority, segments.join("/"))
+    };
+    Some(RepoUrl { host, owner, name, clone_url })
+}
+
+// An SSH or git:// repo, cloned exactly as given. The owner may be empty, as
+// in `server:app.git` for a repo in the SSH user's home.
+fn remote(authority: &str, path: &str, url: &str) -> Option<RepoUrl> {
+    let host = host_of(authority)?;
+    let (owner, name) = split_name(&segments(path)?)?;
+    Some(RepoUrl {
+        host,
+        owner,
+        name,
+        clone_url: url.trim_end_matches('/').to_string(),
+    })
+}
+
+fn local(path: &str) -> Option<RepoUrl> {
+    let name = path.trim_end_matches(['/', '\\']).rsplit(['/', '\\']).next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    if name.is_empty() || name == "." || name == ".." || name == "~" {
+        return None;
+    }
+    Some(RepoUrl {
+        host: String::new(),
+        owner: String::new(),
+        name: name.to_string(),
+        clone_url: path.to_string(),
+    })
+}
+
+// `[user@]host:path`, which git reads as SSH when the colon comes before any slash
+fn scp_parts(input: &str) -> Option<(&str, &str)> {
+    let (authority, path) = input.split_once(':')?;
+    if authority.contains('/') || path.starts_with('/') {
+        return None;
+    }
+    Some((authority, path))
+}
+
+fn is_local_path(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && (bytes.len() == 2 || matches!(bytes[2], b'\\' | b'/'));
+    drive || input.starts_with(['/', '\\', '.', '~'])
+}
+
+// The host without user info or port
+fn host_of(authority: &str) -> Option<String> {
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_ascii_lowercase();
+    let valid = !host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    valid.then_some(host)
+}
+
+fn segments(path: &str) -> Option<Vec<&str>> {
+    // GitLab (and self-hosted instances) put pages below `/-/`
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).take_while(|s| *s != "-").collect();
+    if segments.iter().any(|s| *s == "." || *s == ".." || s.starts_with('-')) {
+        return None;
+    }
+    Some(segments)
+}
+
+fn split_name(segments: &[&str]) -> Option<(String, String)> {
+    let (name, owner) = segments.split_last()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    Some((owner.join("/"), name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repository_urls() {
+        let cases = [
+            // HTTPS
+            ("https://github.com/ketra/app", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("https://github.com/ketra/app.git", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("https://github.com/ketra/app/", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("http://GitHub.com/ketra/app", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("https://token@github.com/ketra/app", "github.com", "ketra", "app", "https://token@github.com/ketra/app.git"),
+            ("https://git.example.com/team/app.git", "git.example.com", "team", "app", "https://git.example.com/team/app.git"),
+            ("https://git.example.com:8443/a/b/c", "git.example.com", "a/b", "c", "https://git.example.com:8443/a/b/c"),
+            // Browser URLs
+            ("https://github.com/ketra/app/tree/main", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("https://github.com/ketra/app/blob/main/src/lib.rs#L10", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("https://github.com/ketra/app/pull/12?tab=files", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("https://gitlab.com/group/sub/app/-/tree/main", "gitlab.com", "group/sub", "app", "https://gitlab.com/group/sub/app.git"),
+            ("https://gitlab.example.com/group/app/-/merge_requests/3", "gitlab.example.com", "group", "app", "https://gitlab.example.com/group/app"),
+            ("https://bitbucket.org/team/app/src/main/", "bitbucket.org", "team", "app", "https://bitbucket.org/team/app.git"),
+            // SSH
+            ("ssh://git@github.com/ketra/app.git", "github.com", "ketra", "app", "ssh://git@github.com/ketra/app.git"),
+            ("ssh://git@git.example.com:2222/team/app.git/", "git.example.com", "team", "app", "ssh://git@git.example.com:2222/team/app.git"),
+            ("git://git.kernel.org/pub/scm/git/git.git", "git.kernel.org", "pub/scm/git", "git", "git://git.kernel.org/pub/scm/git/git.git"),
+            ("git@github.com:ketra/app.git", "github.com", "ketra", "app", "git@github.com:ketra/app.git"),
+            ("git@gitlab.com:group/sub/app", "gitlab.com", "group/sub", "app", "git@gitlab.com:group/sub/app"),
+            ("github.com:ketra/app.git/", "github.com", "ketra", "app", "github.com:ketra/app.git"),
+            ("build-box:app.git", "build-box", "", "app", "build-box:app.git"),
+            // Shorthand
+            ("ketra/app", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("ketra/app.git", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("gh:ketra/app", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            ("gl:group/sub/app", "gitlab.com", "group/sub", "app", "https://gitlab.com/group/sub/app.git"),
+            ("  gh:ketra/app  ", "github.com", "ketra", "app", "https://github.com/ketra/app.git"),
+            // Local
+            ("file:///srv/git/app.git", "", "", "app", "file:///srv/git/app.git"),
+            ("/srv/git/app.git/", "", "", "app", "/srv/git/app.git/"),
+            ("../app", "", "", "app", "../app"),
+            ("C:\\src\\app", "", "", "app", "C:\\src\\app"),
+        ];
+        for (input, host, owner, name, clone_url) in cases {
+            let repo = RepoUrl::parse(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(
+                (repo.host.as_str(), repo.owner.as_str(), repo.name.as_str(), repo.clone_url.as_str()),
+                (host, owner, name, clone_url),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_repository() {
+        for input in [
+            "",
+            "   ",
+            "--upload-pack=touch /tmp/pwned",
+            "app",
+            "a/b/c",
+            "https://",
+            "https://github.com",
+            "https://github.com/ketra",
+            "https://github.com/ketra/.git",
+            "https://github.com/ketra/-app",
+            "https://github.com/../app",
+            "ftp://example.com/ketra/app",
+            "gh:app",
+            "gl:",
+            "git@github.com:",
+            "ketra/app name",
+            "/",
+            ".",
+        ] {
+            let err = RepoUrl::parse(input).unwrap_err();
+            assert!(err.is(&ErrorKind::InvalidInput), "{}", input);
+        }
+    }
+}
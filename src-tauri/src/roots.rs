@@ -0,0 +1,149 @@
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+
+// A folder that holds projects, e.g. `~/code` in WSL or `D:\src` on Windows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectRoot {
+    pub env: Environment,
+    // May start with `~`, expanded against the environment's home folder
+    pub path: String,
+    // How many folder levels below the root to look for projects
+    #[serde(default = "default_depth")]
+    pub depth: u32,
+    // Globs matched against the project path relative to the root
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+// Deep enough for `clients/<client>/<repo>`
+fn default_depth() -> u32 {
+    3
+}
+
+impl ProjectRoot {
+    pub fn new(env: Environment, path: &str) -> Self {
+        ProjectRoot {
+            env,
+            path: path.to_string(),
+            depth: default_depth(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    // `~/ketra`, used when no roots are configured for an environment
+    pub fn default_for(env: Environment) -> Self {
+        Self::new(env, &format!("~{}ketra", env.separator()))
+    }
+
+    pub fn resolve(&self, runner: &dyn CommandRunner) -> Result<String, KetraError> {
+        let sep = self.env.separator();
+        match self.path.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with(sep) || rest.starts_with('/') => {
+                let home = self.env.home_dir(runner)?;
+                Ok(self.env.join(&home, rest.trim_start_matches([sep, '/'])).trim_end_matches(sep).to_string())
+            }
+            _ => Ok(self.path.clone()),
+        }
+    }
+
+    // Should the folder at `relative` (to the root, `/`-separated) be listed?
+    pub fn accepts(&self, relative: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: self.env != Environment::Windows,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        let matches = |globs: &[String]| {
+            globs.iter().any(|g| Pattern::new(g).map(|p| p.matches_with(relative, options)).unwrap_or(false))
+        };
+
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+
+    pub fn validate(&self) -> Result<(), KetraError> {
+        if self.path.trim().is_empty() {
+            return Err(KetraError::new(ErrorKind::InvalidInput, "Project root path is empty"));
+        }
+        if self.depth == 0 {
+            return Err(KetraError::new(ErrorKind::InvalidInput, format!("Scan depth for '{}' must be at least 1", self.path)));
+        }
+        for glob in self.include.iter().chain(&self.exclude) {
+            Pattern::new(glob)
+                .map_err(|e| KetraError::new(ErrorKind::InvalidInput, format!("Invalid glob '{}': {}", glob, e)))?;
+        }
+        Ok(())
+    }
+}
+
+// Configured roots for `env`, or the default root when there are none.
+// The first one is where new projects are created.
+pub fn roots_for(env: Environment, configured: &[ProjectRoot]) -> Vec<ProjectRoot> {
+    let roots: Vec<ProjectRoot> = configured.iter().filter(|r| r.env == env).cloned().collect();
+    if roots.is_empty() {
+        vec![ProjectRoot::default_for(env)]
+    } else {
+        roots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    #[test]
+    fn expands_home_in_wsl() {
+        let runner = FakeRunner::new().on("printenv HOME", CommandOutput::ok("/home/dev\n"));
+
+        assert_eq!(ProjectRoot::default_for(Environment::Wsl).resolve(&runner).unwrap(), "/home/dev/ketra");
+        assert_eq!(ProjectRoot::new(Environment::Wsl, "~").resolve(&runner).unwrap(), "/home/dev");
+        assert_eq!(ProjectRoot::new(Environment::Wsl, "/srv/code").resolve(&runner).unwrap(), "/srv/code");
+        assert_eq!(ProjectRoot::new(Environment::Wsl, "~other/code").resolve(&runner).unwrap(), "~other/code");
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let mut root = ProjectRoot::new(Environment::Wsl, "~/work");
+        root.depth = 2;
+        root.include = vec!["clients/*".to_string(), "oss/*".to_string()];
+        root.exclude = vec!["*/*-old".to_string(), "clients/archive".to_string()];
+
+        assert!(root.accepts("clients/acme"));
+        assert!(root.accepts("oss/ketra"));
+        assert!(!root.accepts("scratch/tmp"));
+        assert!(!root.accepts("clients/acme-old"));
+        assert!(!root.accepts("clients/archive"));
+        assert!(ProjectRoot::new(Environment::Wsl, "~/work").accepts("anything"));
+    }
+
+    #[test]
+    fn windows_globs_ignore_case() {
+        let mut root = ProjectRoot::new(Environment::Windows, "D:\\src");
+        root.exclude = vec!["Archive*".to_string()];
+        assert!(!root.accepts("archive-2020"));
+    }
+
+    #[test]
+    fn falls_back_to_the_default_root() {
+        let configured = vec![ProjectRoot::new(Environment::Windows, "D:\\src")];
+        assert_eq!(roots_for(Environment::Windows, &configured), configured);
+        assert_eq!(roots_for(Environment::Wsl, &configured), vec![ProjectRoot::default_for(Environment::Wsl)]);
+    }
+
+    #[test]
+    fn rejects_bad_roots() {
+        let mut root = ProjectRoot::new(Environment::Linux, "~/code");
+        assert!(root.validate().is_ok());
+        root.include = vec!["[".to_string()];
+        assert!(root.validate().is_err());
+        assert!(ProjectRoot { depth: 0, ..ProjectRoot::new(Environment::Linux, "~/code") }.validate().is_err());
+        assert!(ProjectRoot::new(Environment::Linux, " ").validate().is_err());
+    }
+}
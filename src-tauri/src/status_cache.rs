@@ -0,0 +1,493 @@
+use crate::discovery::SKIPPED;
+use crate::environment::Environment;
+use crate::error::KetraError;
+use crate::exec::CommandRunner;
+use crate::git::get_git_status;
+use crate::types::{GitStatus, Project};
+use crate::utils::unix_now;
+use git2::{Branch, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+// Files in the git dir whose modification time changes on checkout, staging
+// and stash. Commits, fetches and pushes show up in the HEAD and upstream
+// commits instead, whatever the branch or remote is called.
+const WORKTREE_FILES: &[&str] = &["HEAD", "index"];
+// Shared by every worktree of the repo
+const COMMON_FILES: &[&str] = &["logs/refs/stash"];
+
+// Working trees with more files than this are not fingerprinted; their
+// status is read every time
+const MAX_TREE_ENTRIES: usize = 20_000;
+
+// Bump to throw away caches written by older versions
+const CACHE_VERSION: u32 = 3;
+
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+// Modification times (seconds) of the git files above, in order, followed by
+// the newest file or folder in the working tree when it was walked
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    stamps: Vec<u64>,
+    // HEAD and upstream commits, "-" when there is none
+    commits: Vec<String>,
+    taken_at: u64,
+}
+
+impl Fingerprint {
+    fn new(stamps: Vec<u64>, commits: Vec<String>) -> Self {
+        Fingerprint { stamps, commits, taken_at: unix_now() }
+    }
+
+    // Same repo state, whenever each was taken
+    pub fn same(&self, other: &Fingerprint) -> bool {
+        self.stamps == other.stamps && self.commits == other.commits
+    }
+
+    // A change later in the same second as the newest stamp would not move
+    // it, so a status read that close to a change can't be trusted later
+    fn settled(&self) -> bool {
+        self.stamps.iter().all(|s| s + 1 < self.taken_at)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedStatus {
+    env: Environment,
+    path: String,
+    stamps: Vec<u64>,
+    commits: Vec<String>,
+    status: Option<GitStatus>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: Vec<CachedStatus>,
+}
+
+pub struct StatusCache {
+    file: PathBuf,
+    entries: HashMap<(Environment, String), CachedStatus>,
+    dirty: bool,
+}
+
+impl StatusCache {
+    // A missing, unreadable or outdated cache is just empty
+    pub fn load(file: PathBuf) -> Self {
+        let entries = fs::read_to_string(&file)
+            .ok()
+            .and_then(|text| serde_json::from_str::<CacheFile>(&text).ok())
+            .filter(|data| data.version == CACHE_VERSION)
+            .map(|data| data.entries)
+            .unwrap_or_default();
+
+        StatusCache {
+            file,
+            entries: entries.into_iter().map(|e| ((e.env, e.path.clone()), e)).collect(),
+            dirty: false,
+        }
+    }
+
+    pub fn save(&mut self) -> Result<(), KetraError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let path = self.file.to_string_lossy().to_string();
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| KetraError::io("Failed to create cache folder", &path, e))?;
+        }
+
+        let data = CacheFile {
+            version: CACHE_VERSION,
+            entries: self.entries.values().cloned().collect(),
+        };
+        let json = serde_json::to_string(&data)
+            .map_err(|e| KetraError::failed(format!("Failed to serialize git status cache: {}", e)))?;
+
+        let tmp = self.file.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| KetraError::io("Failed to save git status cache", &path, e))?;
+        fs::rename(&tmp, &self.file).map_err(|e| KetraError::io("Failed to save git status cache", &path, e))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    // The status from the last check, however old. Used for the first paint,
+    // before statuses are checked again.
+    pub fn last_known(&self, env: Environment, path: &str) -> Option<GitStatus> {
+        self.entries.get(&(env, path.to_string())).and_then(|e| e.status.clone())
+    }
+
+    // The cached status if nothing has changed since it was read
+    pub fn fresh(&self, env: Environment, path: &str, fingerprint: &Fingerprint) -> Option<Option<GitStatus>> {
+        self.entries
+            .get(&(env, path.to_string()))
+            .filter(|e| e.stamps == fingerprint.stamps && e.commits == fingerprint.commits)
+            .map(|e| e.status.clone())
+    }
+
+    pub fn insert(&mut self, env: Environment, path: &str, fingerprint: Fingerprint, status: Option<GitStatus>) {
+        // Keep it for first paint, but don't let it satisfy a fingerprint check
+        let (stamps, commits) = if fingerprint.settled() { (fingerprint.stamps, fingerprint.commits) } else { Default::default() };
+        self.entries.insert(
+            (env, path.to_string()),
+            CachedStatus { env, path: path.to_string(), stamps, commits, status },
+        );
+        self.dirty = true;
+    }
+
+    // A status read without a fingerprint: good for first paint only
+    pub fn remember(&mut self, env: Environment, path: &str, status: Option<GitStatus>) {
+        self.insert(env, path, Fingerprint { stamps: Vec::new(), commits: Vec::new(), taken_at: 0 }, status);
+    }
+
+    pub fn fill_last_known(&self, projects: &mut [Project]) {
+        for project in projects {
+            project.git_status = self.last_known(project.env, &project.path);
+        }
+    }
+
+    // Forget repos that are no longer found under any root
+    pub fn retain_projects(&mut self, projects: &[Project]) {
+        let before = self.entries.len();
+        self.entries.retain(|(env, path), _| projects.iter().any(|p| p.env == *env && &p.path == path));
+        self.dirty |= self.entries.len() != before;
+    }
+}
+
+// Git status, recomputed only when the repo's git files, commits or working
+// tree changed. Only local repos are fingerprinted: on WSL checking would cost
+// as much as reading the status itself.
+pub fn cached_git_status(runner: &dyn CommandRunner, path: &str, env: Environment) -> Option<GitStatus> {
+    let fingerprint = if env.is_local() { fingerprint(path) } else { None };
+    if let Some(fingerprint) = &fingerprint {
+        if let Some(status) = status_cache().fresh(env, path, fingerprint) {
+            return status;
+        }
+    }
+
+    // Not holding the lock here lets repos be checked in parallel
+    let status = get_git_status(runner, path, env);
+    match fingerprint {
+        Some(fingerprint) => status_cache().insert(env, path, fingerprint, status.clone()),
+        None => status_cache().remember(env, path, status.clone()),
+    }
+    status
+}
+
+fn fingerprint(path: &str) -> Option<Fingerprint> {
+    // Opening the repo also follows the `.git` file of worktrees and submodules
+    let repo = Repository::open(path).ok()?;
+    let mut stamps: Vec<u64> = WORKTREE_FILES.iter().map(|f| modified_secs(&repo.path().join(f))).collect();
+    stamps.extend(COMMON_FILES.iter().map(|f| modified_secs(&repo.commondir().join(f))));
+
+    let root = repo.workdir()?;
+    let mut budget = MAX_TREE_ENTRIES;
+    stamps.push(newest_in_tree(&repo, root, root, &mut budget)?);
+    Some(Fingerprint::new(stamps, commits(&repo)))
+}
+
+// HEAD and the commit its branch tracks, on any remote
+fn commits(repo: &Repository) -> Vec<String> {
+    let head = repo.head().ok();
+    let oid = head.as_ref().and_then(|h| h.target());
+    let upstream = head
+        .filter(|h| h.is_branch())
+        .and_then(|h| Branch::wrap(h).upstream().ok())
+        .and_then(|b| b.get().target());
+    [oid, upstream]
+        .iter()
+        .map(|oid| oid.map(|o| o.to_string()).unwrap_or_else(|| "-".to_string()))
+        .collect()
+}
+
+// Git files and commits of many WSL repos in one call, for polling. The
+// working tree isn't walked. None when the call fails.
+pub fn git_fingerprints(runner: &dyn CommandRunner, env: Environment, repos: &[String]) -> Option<HashMap<String, Fingerprint>> {
+    let git_paths: String = WORKTREE_FILES.iter().chain(COMMON_FILES).map(|f| format!(" --git-path {}", f)).collect();
+    // One line per repo: each file's mtime (0 when missing), HEAD, upstream, path
+    let script = format!(
+        r#"for repo in "$@"; do
+  cd "$repo" 2>/dev/null || continue
+  stamps=$(git rev-parse{} 2>/dev/null | while IFS= read -r f; do stat -c %Y "$f" 2>/dev/null || echo 0; done | tr '\n' ' ')
+  head=$(git rev-parse -q --verify HEAD 2>/dev/null || echo -)
+  upstream=$(git rev-parse -q --verify '@{{u}}' 2>/dev/null || echo -)
+  printf '%s%s %s %s\n' "$stamps" "$head" "$upstream" "$repo"
+done"#,
+        git_paths
+    );
+    let mut args = vec!["-c", script.as_str(), "sh"];
+    args.extend(repos.iter().map(|r| r.as_str()));
+    let output = runner.output(&env.command("sh", &args, None)).ok()?;
+    if !output.success {
+        return None;
+    }
+
+    let files = WORKTREE_FILES.len() + COMMON_FILES.len();
+    let fingerprints = output
+        .stdout
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(files + 3, ' ').collect();
+            if fields.len() != files + 3 {
+                return None;
+            }
+            let stamps = fields[..files].iter().map(|s| s.parse().ok()).collect::<Option<Vec<u64>>>()?;
+            let commits = fields[files..files + 2].iter().map(|c| c.to_string()).collect();
+            Some((fields[files + 2].to_string(), Fingerprint::new(stamps, commits)))
+        })
+        .collect();
+    Some(fingerprints)
+}
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::symlink_metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Newest modification time of any file or folder, outside `.git`, ignored
+// paths and the folders discovery skips. Folder times catch deletes and
+// renames. None once more than `budget` entries have been looked at.
+fn newest_in_tree(repo: &Repository, root: &Path, dir: &Path, budget: &mut usize) -> Option<u64> {
+    let mut newest = modified_secs(dir);
+    let Ok(entries) = fs::read_dir(dir) else { return Some(newest) };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name == ".git" || SKIPPED.contains(&name.as_ref()) {
+            continue;
+        }
+        let path = entry.path();
+        let ignored = path
+            .strip_prefix(root)
+            .ok()
+            .and_then(|rel| repo.is_path_ignored(rel).ok())
+            .unwrap_or(false);
+        if ignored {
+            continue;
+        }
+
+        *budget = budget.checked_sub(1)?;
+        let modified = match entry.file_type() {
+            Ok(t) if t.is_dir() => newest_in_tree(repo, root, &path, budget)?,
+            _ => modified_secs(&path),
+        };
+        newest = newest.max(modified);
+    }
+    Some(newest)
+}
+
+#[cfg(not(test))]
+fn default_file() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("com.vscode.launcher")
+        .join("git-status.json")
+}
+
+#[cfg(test)]
+fn default_file() -> PathBuf {
+    std::env::temp_dir().join(format!("ketra-test-cache-{}", std::process::id())).join("git-status.json")
+}
+
+static STATUS_CACHE: OnceLock<Mutex<StatusCache>> = OnceLock::new();
+
+pub fn status_cache() -> MutexGuard<'static, StatusCache> {
+    STATUS_CACHE
+        .get_or_init(|| Mutex::new(StatusCache::load(default_file())))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+// Statuses are checked from many places at once; write them out every few
+// seconds rather than after each one
+pub fn start_autosave() {
+    thread::spawn(|| loop {
+        thread::sleep(AUTOSAVE_INTERVAL);
+        if let Err(e) = status_cache().save() {
+            println!("[DEBUG] {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::{system, CommandOutput};
+
+    fn status(branch: &str) -> Option<GitStatus> {
+        Some(GitStatus {
+            branch: branch.to_string(),
+            is_clean: true,
+            last_commit_time: 1_700_000_000,
+            ..GitStatus::default()
+        })
+    }
+
+    fn settled(stamps: Vec<u64>) -> Fingerprint {
+        Fingerprint { stamps, commits: vec!["abc".into(), "-".into()], taken_at: 2_000_000_000 }
+    }
+
+    #[test]
+    fn fresh_only_while_the_fingerprint_matches() {
+        let mut cache = StatusCache::load(PathBuf::from("/nonexistent/git-status.json"));
+        cache.insert(Environment::Wsl, "/home/dev/app", settled(vec![1, 2, 3]), status("main"));
+
+        assert_eq!(cache.fresh(Environment::Wsl, "/home/dev/app", &settled(vec![1, 2, 3])), Some(status("main")));
+        assert_eq!(cache.fresh(Environment::Wsl, "/home/dev/app", &settled(vec![1, 2, 4])), None);
+        assert_eq!(cache.fresh(Environment::Linux, "/home/dev/app", &settled(vec![1, 2, 3])), None);
+    }
+
+    #[test]
+    fn just_changed_repos_are_not_trusted() {
+        let mut cache = StatusCache::load(PathBuf::from("/nonexistent/git-status.json"));
+        let now = unix_now();
+        let fingerprint = Fingerprint { stamps: vec![1, now], commits: Vec::new(), taken_at: now };
+        cache.insert(Environment::Wsl, "/home/dev/app", fingerprint.clone(), status("main"));
+
+        assert_eq!(cache.fresh(Environment::Wsl, "/home/dev/app", &fingerprint), None);
+        assert_eq!(cache.last_known(Environment::Wsl, "/home/dev/app"), status("main"));
+    }
+
+    #[test]
+    fn persists_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("ketra-cache-roundtrip-{}", std::process::id()));
+        let file = dir.join("git-status.json");
+        fs::remove_dir_all(&dir).ok();
+
+        let mut cache = StatusCache::load(file.clone());
+        cache.insert(Environment::Wsl, "/home/dev/app", settled(vec![5]), status("dev"));
+        cache.insert(Environment::Wsl, "/home/dev/gone", settled(vec![5]), None);
+        let mut projects = vec![Project::new("app".into(), "/home/dev/app".into(), Environment::Wsl, String::new(), 0)];
+        cache.retain_projects(&projects);
+        cache.save().unwrap();
+
+        let cache = StatusCache::load(file);
+        assert_eq!(cache.fresh(Environment::Wsl, "/home/dev/app", &settled(vec![5])), Some(status("dev")));
+        assert_eq!(cache.fresh(Environment::Wsl, "/home/dev/gone", &settled(vec![5])), None);
+
+        cache.fill_last_known(&mut projects);
+        assert_eq!(projects[0].git_status, status("dev"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    fn temp_repo(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ketra-cache-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "-q", "-b", "main"]);
+        dir
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+        full.extend_from_slice(args);
+        let output = system().output(&Environment::host().command("git", &full, Some(&dir.to_string_lossy()))).unwrap();
+        assert!(output.success, "git {:?}: {}", args, output.stderr);
+    }
+
+    #[test]
+    fn local_fingerprint_follows_the_working_tree() {
+        let dir = temp_repo("tree");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("node_modules")).unwrap();
+        fs::create_dir_all(dir.join("dist")).unwrap();
+        fs::write(dir.join(".gitignore"), "dist/\n").unwrap();
+        let path = dir.to_string_lossy().to_string();
+
+        let before = fingerprint(&path).unwrap();
+        assert_eq!(before.stamps.len(), WORKTREE_FILES.len() + COMMON_FILES.len() + 1);
+        assert!(before.stamps[0] > 0);
+        assert_eq!(before.stamps[1], 0);
+        assert_eq!(before.commits, ["-", "-"]);
+
+        // An edit deep in the tree counts, ignored and skipped folders don't
+        let future = unix_now() + 1000;
+        set_modified(&dir.join("src").join("main.rs"), future);
+        set_modified(&dir.join("node_modules").join("dep.js"), future + 1000);
+        set_modified(&dir.join("dist").join("app.js"), future + 1000);
+        assert_eq!(fingerprint(&path).unwrap().stamps.last(), Some(&future));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn local_fingerprint_follows_commits_on_any_branch_and_remote() {
+        let dir = temp_repo("commits");
+        git(&dir, &["checkout", "-q", "-b", "feature/x"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&dir, &["remote", "add", "fork", "https://example.com/fork.git"]);
+        git(&dir, &["update-ref", "refs/remotes/fork/feature/x", "HEAD"]);
+        git(&dir, &["branch", "-q", "--set-upstream-to=fork/feature/x"]);
+        let path = dir.to_string_lossy().to_string();
+
+        let mut cache = StatusCache::load(PathBuf::from("/nonexistent/git-status.json"));
+        let mut first = fingerprint(&path).unwrap();
+        assert_eq!(first.commits[0], first.commits[1]);
+        first.taken_at = u64::MAX / 2;
+        cache.insert(Environment::host(), &path, first.clone(), status("feature/x"));
+
+        // A commit only touches refs/heads/feature/x and the reflogs
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "second"]);
+        let committed = fingerprint(&path).unwrap();
+        assert_ne!(committed.commits[0], first.commits[0]);
+        assert_eq!(committed.commits[1], first.commits[1]);
+        assert_eq!(cache.fresh(Environment::host(), &path, &committed), None);
+
+        // A push to a remote other than origin moves the upstream
+        git(&dir, &["update-ref", "refs/remotes/fork/feature/x", "HEAD"]);
+        let pushed = fingerprint(&path).unwrap();
+        assert_eq!(pushed.commits[1], committed.commits[0]);
+        assert!(!pushed.same(&committed));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    fn set_modified(file: &Path, secs: u64) {
+        fs::write(file, "").unwrap();
+        let file = fs::File::options().write(true).open(file).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    }
+
+    #[test]
+    fn wsl_fingerprints_are_one_call() {
+        let runner = FakeRunner::new().on(
+            "--exec sh -c",
+            CommandOutput::ok("10 11 0 abc def /home/dev/app\n12 0 0 - - /home/dev/my app\n/home/dev/broken\n"),
+        );
+        let repos = ["/home/dev/app".to_string(), "/home/dev/my app".to_string(), "/home/dev/broken".to_string()];
+        let fingerprints = git_fingerprints(&runner, Environment::Wsl, &repos).unwrap();
+        assert_eq!(fingerprints.len(), 2);
+        assert_eq!(fingerprints["/home/dev/app"].stamps, [10, 11, 0]);
+        assert_eq!(fingerprints["/home/dev/app"].commits, ["abc", "def"]);
+        assert_eq!(fingerprints["/home/dev/my app"].commits, ["-", "-"]);
+        assert_eq!(runner.calls().len(), 1);
+        assert!(runner.ran("--git-path HEAD --git-path index --git-path logs/refs/stash"));
+        assert_eq!(runner.calls()[0].args[runner.calls()[0].args.len() - 3..], repos);
+
+        let runner = FakeRunner::new().on("--exec sh -c", CommandOutput::fail("wsl: not running"));
+        assert!(git_fingerprints(&runner, Environment::Wsl, &repos).is_none());
+    }
+
+    #[test]
+    fn wsl_status_is_read_without_a_fingerprint() {
+        let runner = FakeRunner::new().on("status --porcelain=v2", CommandOutput::ok("# branch.oid abc\n# branch.head main\n"));
+        let status = cached_git_status(&runner, "/home/dev/unfingerprinted", Environment::Wsl).unwrap();
+        assert_eq!(status.branch, "main");
+        assert!(!runner.ran("rev-parse --git-path"));
+        assert_eq!(status_cache().last_known(Environment::Wsl, "/home/dev/unfingerprinted"), Some(status));
+    }
+}
@@ -1,213 +1,187 @@
-use std::fs;
-use std::process::Command;
-use crate::utils::get_default_folder;
+use crate::environment::Environment;
+use crate::error::KetraError;
+use crate::exec::CommandRunner;
+use crate::utils::{get_default_folder, validate_project_name};
 use crate::github::create_github_repo;
+use crate::operations::{self, tracked, OperationKind};
 
-pub fn init_project_template(path: &str, template: &str, env: &str) -> Result<(), String> {
-    let is_wsl = env == "wsl";
+// Tool runs (npx, cargo, ...) belong to `operation_id` so they can be cancelled
+pub fn init_project_template(runner: &dyn CommandRunner, path: &str, template: &str, env: Environment, operation_id: &str) -> Result<(), KetraError> {
+    let name = env.file_name(path);
+    let tool = |program: &str, args: &[&str]| runner.output(&tracked(env.tool_command(program, args, Some(path)), env, operation_id));
 
     match template {
         "rust" => {
-            let cmd = if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("cd {} && cargo init", path)])
-                    .output()
-            } else {
-                Command::new("cmd")
-                    .args(&["/C", "cargo", "init", path])
-                    .output()
-            };
-            cmd.map_err(|e| format!("Failed to initialize Rust project: {}", e))?;
+            tool("cargo", &["init"])
+                .map_err(|e| KetraError::spawn("Failed to initialize Rust project", e))?;
         },
         "nextjs" => {
-            let cmd = if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("cd {} && npx create-next-app@latest . --typescript --tailwind --app --no-src-dir --import-alias '@/*' --yes", path)])
-                    .output()
-            } else {
-                Command::new("cmd")
-                    .args(&["/C", "cd", path, "&&", "npx", "create-next-app@latest", ".", "--typescript", "--tailwind", "--app", "--no-src-dir", "--import-alias", "@/*", "--yes"])
-                    .output()
-            };
-            cmd.map_err(|e| format!("Failed to initialize Next.js project: {}", e))?;
+            tool("npx", &["create-next-app@latest", ".", "--typescript", "--tailwind", "--app", "--no-src-dir", "--import-alias", "@/*", "--yes"])
+                .map_err(|e| KetraError::spawn("Failed to initialize Next.js project", e))?;
         },
         "python" => {
             // Create basic Python project structure
             let content = "#!/usr/bin/env python3\n\ndef main():\n    print(\"Hello, World!\")\n\nif __name__ == \"__main__\":\n    main()\n";
 
-            if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("echo '{}' > {}/main.py", content, path)])
-                    .output()
-                    .map_err(|e| format!("Failed to create Python file: {}", e))?;
-            } else {
-                let main_py = format!("{}\\main.py", path);
-                fs::write(&main_py, content)
-                    .map_err(|e| format!("Failed to create Python file: {}", e))?;
-            }
+            env.write_file(runner, &env.join(path, "main.py"), content)?;
 
             // Create requirements.txt
-            if is_wsl {
-                Command::new("wsl")
-                    .args(&["touch", &format!("{}/requirements.txt", path)])
-                    .output()
-                    .ok();
-            } else {
-                let req_file = format!("{}\\requirements.txt", path);
-                fs::write(&req_file, "").ok();
-            }
+            env.write_file(runner, &env.join(path, "requirements.txt"), "").ok();
         },
         "go" => {
-            let cmd = if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("cd {} && go mod init {}", path, path.split('/').last().unwrap_or("app"))])
-                    .output()
-            } else {
-                Command::new("cmd")
-                    .args(&["/C", "cd", path, "&&", "go", "mod", "init", path.split('\\').last().unwrap_or("app")])
-                    .output()
-            };
-            cmd.map_err(|e| format!("Failed to initialize Go project: {}", e))?;
+            tool("go", &["mod", "init", &name])
+                .map_err(|e| KetraError::spawn("Failed to initialize Go project", e))?;
 
             // Create main.go
             let content = "package main\n\nimport \"fmt\"\n\nfunc main() {\n    fmt.Println(\"Hello, World!\")\n}\n";
-
-            if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("echo '{}' > {}/main.go", content, path)])
-                    .output()
-                    .ok();
-            } else {
-                let main_go = format!("{}\\main.go", path);
-                fs::write(&main_go, content).ok();
-            }
+            env.write_file(runner, &env.join(path, "main.go"), content).ok();
         },
         "node" => {
-            let cmd = if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("cd {} && npm init -y", path)])
-                    .output()
-            } else {
-                Command::new("cmd")
-                    .args(&["/C", "cd", path, "&&", "npm", "init", "-y"])
-                    .output()
-            };
-            cmd.map_err(|e| format!("Failed to initialize Node project: {}", e))?;
+            tool("npm", &["init", "-y"])
+                .map_err(|e| KetraError::spawn("Failed to initialize Node project", e))?;
 
             // Create index.js
             let content = "console.log('Hello, World!');\n";
-
-            if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("echo \"{}\" > {}/index.js", content, path)])
-                    .output()
-                    .ok();
-            } else {
-                let index_js = format!("{}\\index.js", path);
-                fs::write(&index_js, content).ok();
-            }
+            env.write_file(runner, &env.join(path, "index.js"), content).ok();
         },
         _ => {
             // Empty project - just create a README
-            let content = format!("# {}\n\nA new project.\n", path.split(&['/', '\\'][..]).last().unwrap_or("project"));
-
-            if is_wsl {
-                Command::new("wsl")
-                    .args(&["bash", "-c", &format!("echo '{}' > {}/README.md", content, path)])
-                    .output()
-                    .ok();
-            } else {
-                let readme = format!("{}\\README.md", path);
-                fs::write(&readme, content).ok();
-            }
+            let content = format!("# {}\n\nA new project.\n", name);
+            env.write_file(runner, &env.join(path, "README.md"), &content).ok();
         }
     }
 
     Ok(())
 }
 
-pub async fn launch_project(env: String, name: String, template: String, create_repo: bool) -> Result<(), String> {
+// Returns the path of the new project
+pub async fn launch_project(
+    runner: &dyn CommandRunner,
+    env: Environment,
+    name: String,
+    template: String,
+    create_repo: bool,
+    operation_id: &str,
+) -> Result<String, KetraError> {
+    validate_project_name(&name)?;
+
     // Get the default base folder
-    let base_folder = get_default_folder(&env)?;
+    let base_folder = get_default_folder(runner, env)?;
 
     // Build full path
-    let full_path = if env == "wsl" {
-        format!("{}/{}", base_folder, name)
-    } else {
-        format!("{}\\{}", base_folder, name)
-    };
-
-    let is_wsl = env == "wsl";
-
-    // For WSL, we need to create the folder via WSL command
-    if is_wsl {
-        // Create folder in WSL
-        Command::new("wsl")
-            .args(&["mkdir", "-p", &full_path])
-            .output()
-            .map_err(|e| format!("Failed to create WSL directory: {}", e))?;
-    } else {
-        // Create folder in Windows
-        if !std::path::Path::new(&full_path).exists() {
-            fs::create_dir_all(&full_path)
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
-        }
+    let full_path = env.join(&base_folder, &name);
+
+    // Cancelling the template init removes the folder, unless it was already there
+    let operation = operations::begin(operation_id, OperationKind::TemplateInit, &name, env)?;
+    if !env.dir_exists(runner, &full_path) {
+        operation.cleanup_on_cancel(&full_path);
     }
 
+    env.create_dir_all(runner, &full_path)?;
+
     // Initialize project template
-    init_project_template(&full_path, &template, &env)?;
+    let initialized = init_project_template(runner, &full_path, &template, env, operation_id).and_then(|_| operation.check());
+    operation.settle(runner, initialized)?;
+
+    let git = |args: &[&str]| runner.output(&env.command("git", args, Some(&full_path)));
 
     // Initialize git repo with main as default branch
-    let git_init = if is_wsl {
-        Command::new("wsl")
-            .args(&["bash", "-c", &format!("cd {} && git init -b main && git add . && git commit -m 'Initial commit'", full_path)])
-            .output()
-    } else {
-        Command::new("cmd")
-            .args(&["/C", &format!("cd {} && git init -b main && git add . && git commit -m \"Initial commit\"", full_path)])
-            .output()
-    };
-
-    git_init.map_err(|e| format!("Failed to initialize git: {}", e))?;
+    git(&["init", "-b", "main"])
+        .and_then(|_| git(&["add", "."]))
+        .and_then(|_| git(&["commit", "-m", "Initial commit"]))
+        .map_err(|e| KetraError::spawn("Failed to initialize git", e))?;
 
     // Create GitHub repo if requested
     if create_repo {
-        let clone_url = create_github_repo(name.clone()).await?;
+        let clone_url = create_github_repo(runner, name.clone()).await?;
 
         // Add remote and push
-        let git_push = if is_wsl {
-            Command::new("wsl")
-                .args(&["bash", "-c", &format!("cd {} && git remote add origin {} && git branch -M main && git push -u origin main", full_path, clone_url)])
-                .output()
-        } else {
-            Command::new("cmd")
-                .args(&["/C", &format!("cd {} && git remote add origin {} && git branch -M main && git push -u origin main", full_path, clone_url)])
-                .output()
-        };
-
-        git_push.map_err(|e| format!("Failed to push to GitHub: {}", e))?;
+        git(&["remote", "add", "origin", &clone_url])
+            .and_then(|_| git(&["branch", "-M", "main"]))
+            .and_then(|_| git(&["push", "-u", "origin", "main"]))
+            .map_err(|e| KetraError::spawn("Failed to push to GitHub", e))?;
     }
 
-    // Create a PowerShell script to launch VSCode
-    let ps_script = if env == "wsl" {
-        format!(
-            r#"code --folder-uri 'vscode-remote://wsl+Ubuntu{}'"#,
-            full_path
-        )
-    } else {
-        format!(
-            r#"code '{}'"#,
-            full_path
-        )
-    };
-
-    // Execute the PowerShell script
-    let result = Command::new("powershell")
-        .args(&["-NoProfile", "-Command", &ps_script])
-        .spawn();
-
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Failed to launch VSCode: {}", e))
+    // Launch VSCode
+    env.open_editor(runner, &full_path)?;
+    Ok(full_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    #[tokio::test]
+    async fn launches_a_wsl_rust_project() {
+        let runner = FakeRunner::new().on("printenv HOME", CommandOutput::ok("/home/dev\n"));
+
+        launch_project(&runner, Environment::Wsl, "app".to_string(), "rust".to_string(), false, "launch-rust").await.unwrap();
+
+        assert_eq!(
+            runner.lines(),
+            vec![
+                "wsl --exec printenv HOME",
+                "wsl --exec test -d /home/dev/ketra/app",
+                "wsl --exec mkdir -p -- /home/dev/ketra/app",
+                "wsl --cd /home/dev/ketra/app --exec setsid -w sh -c echo \"ketra-pid:$$\" >&2; exec \"$@\" sh cargo init",
+                "wsl --cd /home/dev/ketra/app --exec git init -b main",
+                "wsl --cd /home/dev/ketra/app --exec git add .",
+                "wsl --cd /home/dev/ketra/app --exec git commit -m Initial commit",
+                "powershell -NoProfile -Command code --folder-uri 'vscode-remote://wsl+Ubuntu/home/dev/ketra/app'",
+            ]
+        );
+    }
+
+    // Cancels the operation the moment `npx` starts, as the UI would mid-install
+    struct CancelDuringNpx(FakeRunner);
+
+    impl crate::exec::CommandRunner for CancelDuringNpx {
+        fn output(&self, spec: &crate::exec::CommandSpec) -> std::io::Result<CommandOutput> {
+            if let (true, Some(id)) = (spec.to_string().contains("npx"), &spec.operation) {
+                crate::operations::cancel(&self.0, id).unwrap();
+            }
+            self.0.output(spec)
+        }
+
+        fn spawn(&self, spec: &crate::exec::CommandSpec) -> std::io::Result<()> {
+            self.0.spawn(spec)
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_template_init_removes_the_new_folder() {
+        let runner = CancelDuringNpx(
+            FakeRunner::new()
+                .on("printenv HOME", CommandOutput::ok("/home/dev\n"))
+                .on("test -d", CommandOutput::fail(""))
+                .on("npx", CommandOutput::fail("npm ERR! signal SIGTERM")),
+        );
+
+        let err = launch_project(&runner, Environment::Wsl, "web".to_string(), "nextjs".to_string(), false, "launch-cancelled").await.unwrap_err();
+        assert!(err.is(&crate::error::ErrorKind::Cancelled));
+        assert!(runner.0.ran("rm -rf -- /home/dev/ketra/web"));
+        assert!(!runner.0.ran("git init"));
+    }
+
+    #[tokio::test]
+    async fn rejects_names_that_would_reach_a_shell() {
+        let runner = FakeRunner::new();
+        let err = launch_project(&runner, Environment::Windows, "app & calc".to_string(), "go".to_string(), false, "launch-go").await.unwrap_err();
+        assert!(err.is(&crate::error::ErrorKind::InvalidInput));
+        assert!(runner.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn wsl_go_project_files_are_written_without_a_shell_string() {
+        let runner = FakeRunner::new().on("printenv HOME", CommandOutput::ok("/home/dev\n"));
+
+        launch_project(&runner, Environment::Wsl, "my app".to_string(), "go".to_string(), false, "launch-go").await.unwrap();
+
+        let calls = runner.calls();
+        assert!(calls.iter().any(|c| c.args.ends_with(&["sh", "go", "mod", "init", "my app"].map(String::from)) && c.operation.is_some()));
+        let write = calls.iter().find(|c| c.args.get(1).map(String::as_str) == Some("sh")).unwrap();
+        assert_eq!(write.args.last().unwrap(), "/home/dev/ketra/my app/main.go");
     }
 }
@@ -1,19 +1,77 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::detect::ProjectInfo;
+use crate::environment::Environment;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
     pub name: String,
     pub path: String,
+    pub env: Environment,
+    // When the project was last opened from the launcher; 0 if never
     pub last_opened: u64,
+    // Modification time of the project folder
+    pub last_modified: u64,
     pub git_status: Option<GitStatus>,
     pub is_pinned: bool,
+    pub order: Option<u32>,
+    pub metadata: BTreeMap<String, String>,
+    // The configured root folder the project was found under
+    pub root: String,
+    // Languages/frameworks; None until detection has run
+    pub info: Option<ProjectInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl Project {
+    // A freshly scanned project; registry state and git status are filled in later
+    pub fn new(name: String, path: String, env: Environment, root: String, last_modified: u64) -> Self {
+        Project {
+            name,
+            path,
+            env,
+            last_opened: 0,
+            last_modified,
+            git_status: None,
+            is_pinned: false,
+            order: None,
+            metadata: BTreeMap::new(),
+            root,
+            info: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct GitStatus {
+    // Empty on a detached HEAD
     pub branch: String,
     pub is_clean: bool,
+    // 0 without an upstream
     pub commits_ahead: i32,
     pub commits_behind: i32,
+    // Every changed, untracked or conflicted path
     pub uncommitted_files: i32,
+    // Committer time of HEAD; 0 before the first commit
+    pub last_commit_time: u64,
+    // A path can be both staged and modified
+    pub staged: i32,
+    pub modified: i32,
+    pub untracked: i32,
+    pub conflicted: i32,
+    pub stash_count: i32,
+    // Short name such as origin/main; None when the branch tracks nothing
+    pub upstream: Option<String>,
+    // Abbreviated HEAD commit when detached
+    pub detached_at: Option<String>,
+    pub operation: Option<GitOperation>,
+}
+
+// A multi-step command stopped part way, usually on a conflict
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Bisect,
 }
@@ -1,23 +1,48 @@
-use std::process::Command;
+use crate::environment::Environment;
+use crate::error::{ErrorKind, KetraError};
+use crate::exec::CommandRunner;
+use crate::registry::registry;
+use crate::roots::roots_for;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn get_default_folder(env: &str) -> Result<String, String> {
-    if env == "wsl" {
-        // WSL: Use ~/ketra folder
-        let username_result = Command::new("wsl")
-            .args(&["bash", "-c", "echo $USER"])
-            .output()
-            .map_err(|e| format!("Failed to get WSL username: {}", e))?;
+pub fn get_default_folder(runner: &dyn CommandRunner, env: Environment) -> Result<String, KetraError> {
+    // New projects go into the first root of the environment (~/ketra by default)
+    let configured = registry().roots();
+    let path = roots_for(env, &configured)[0].resolve(runner)?;
+    println!("[DEBUG] {:?} default project folder: {}", env, path);
+    Ok(path)
+}
+
+// Seconds since the Unix epoch, the unit used for every timestamp sent to the UI
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Project names become folder names and end up on `cmd /C` command lines on
+// Windows, so only allow characters that are inert in every shell we touch.
+pub fn validate_project_name(name: &str) -> Result<(), KetraError> {
+    let invalid = name.is_empty()
+        || name.starts_with('-')
+        || name.starts_with('.')
+        || name.chars().any(|c| c.is_control() || r#"/\:*?"<>|&^%!;$`'(){}[]"#.contains(c));
+
+    if invalid {
+        return Err(KetraError::new(ErrorKind::InvalidInput, format!("Invalid project name '{}'", name)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let username = String::from_utf8_lossy(&username_result.stdout).trim().to_string();
-        let path = format!("/home/{}/ketra", username);
-        println!("[DEBUG] WSL ketra path: {}", path);
-        Ok(path)
-    } else {
-        // Windows: Use %USERPROFILE%\ketra folder
-        let userprofile = std::env::var("USERPROFILE")
-            .map_err(|_| "Failed to get USERPROFILE environment variable".to_string())?;
-        let path = format!("{}\\ketra", userprofile);
-        println!("[DEBUG] Windows ketra path: {}", path);
-        Ok(path)
+    #[test]
+    fn rejects_names_that_could_escape_a_command_line() {
+        for name in ["", "-rf", "..", "a/b", "a\\b", "app & calc", "a;b", "$(id)", "`id`", "a|b", "100%"] {
+            assert!(validate_project_name(name).is_err(), "{}", name);
+        }
+        for name in ["app", "my-app", "my_app 2", "ketra.rs"] {
+            assert!(validate_project_name(name).is_ok(), "{}", name);
+        }
     }
 }
@@ -0,0 +1,503 @@
+use crate::detect::detect_project;
+use crate::discovery::SKIPPED;
+use crate::environment::Environment;
+use crate::exec::{system, CommandRunner};
+use crate::projects::scan_roots;
+use crate::registry::{registry, ProjectKey};
+use crate::roots::{roots_for, ProjectRoot};
+use crate::status_cache::{cached_git_status, git_fingerprints, Fingerprint};
+use crate::types::{GitStatus, Project};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+pub const PROJECT_ADDED: &str = "project-added";
+pub const PROJECT_REMOVED: &str = "project-removed";
+pub const GIT_STATUS_CHANGED: &str = "git-status-changed";
+
+// Bursts of filesystem events (a checkout touches many files) become one batch
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+// WSL folders can't be watched from Windows, so they are polled instead
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GitStatusChange {
+    pub path: String,
+    pub env: Environment,
+    pub status: Option<GitStatus>,
+}
+
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    ProjectAdded(Project),
+    ProjectRemoved(ProjectKey),
+    GitStatusChanged(GitStatusChange),
+}
+
+// What a changed path means for the UI
+#[derive(Debug, PartialEq)]
+enum Change {
+    // Something appeared or disappeared where projects live
+    Projects(Environment),
+    // HEAD, index or refs of the repo at this path changed
+    Git(Environment, String),
+}
+
+// The projects the UI knows about and what has to be watched to keep them current
+#[derive(Default)]
+struct Tracker {
+    envs: Vec<Environment>,
+    roots: Vec<ProjectRoot>,
+    // Resolved root folders with their scan depth
+    bases: Vec<(Environment, PathBuf, u32)>,
+    projects: Vec<Project>,
+    // Folders created since the last scan that may still receive a project
+    extra: Vec<PathBuf>,
+    statuses: HashMap<String, Option<GitStatus>>,
+    // Git files and commits of the WSL repos; None until the first poll
+    fingerprints: Option<HashMap<String, Fingerprint>>,
+}
+
+impl Tracker {
+    fn new(envs: Vec<Environment>) -> Self {
+        Tracker { envs, ..Default::default() }
+    }
+
+    // Rescan everything quietly; the UI loads its own list on start and after root changes
+    fn reload(&mut self, runner: &dyn CommandRunner, roots: Vec<ProjectRoot>) {
+        self.bases = self
+            .envs
+            .iter()
+            .flat_map(|env| roots_for(*env, &roots))
+            .filter_map(|root| root.resolve(runner).ok().map(|base| (root.env, PathBuf::from(base), root.depth)))
+            .collect();
+        self.projects = self.envs.iter().flat_map(|env| scan_roots(runner, *env, &roots)).collect();
+        self.roots = roots;
+        self.extra.clear();
+        self.statuses.clear();
+        self.fingerprints = None;
+    }
+
+    // Roots and every folder between a root and one of its projects
+    fn containers(&self, env: Environment) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self.bases.iter().filter(|(e, _, _)| *e == env).map(|(_, base, _)| base.clone()).collect();
+        for project in self.projects.iter().filter(|p| p.env == env) {
+            for dir in Path::new(&project.path).ancestors().skip(1).take_while(|d| d.starts_with(&project.root)) {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+        dirs.extend(self.extra.iter().cloned());
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    // Folders to hand to the filesystem watcher for the local environments
+    fn watch_list(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut list = Vec::new();
+        for env in self.envs.iter().filter(|e| e.is_local()) {
+            list.extend(self.containers(*env).into_iter().map(|dir| (dir, RecursiveMode::NonRecursive)));
+            for project in self.projects.iter().filter(|p| p.env == *env) {
+                let git = Path::new(&project.path).join(".git");
+                list.push((git.join("refs"), RecursiveMode::Recursive));
+                list.push((git, RecursiveMode::NonRecursive));
+            }
+        }
+        list.retain(|(path, _)| path.is_dir());
+        list
+    }
+
+    fn classify(&mut self, path: &Path) -> Option<Change> {
+        for project in self.projects.iter().filter(|p| p.env.is_local()) {
+            if path.starts_with(Path::new(&project.path).join(".git")) {
+                return Some(Change::Git(project.env, project.path.clone()));
+            }
+        }
+
+        for env in self.envs.clone().into_iter().filter(|e| e.is_local()) {
+            let containers = self.containers(env);
+            let Some(parent) = containers.iter().find(|dir| path == dir.as_path() || path.parent() == Some(dir.as_path())) else {
+                continue;
+            };
+
+            // Remember new folders so a project cloned into them later is noticed
+            if path != parent && path.is_dir() && self.can_hold_projects(env, path) {
+                self.extra.push(path.to_path_buf());
+            }
+            return Some(Change::Projects(env));
+        }
+        None
+    }
+
+    // Is `dir` shallow enough below its root to have projects below it?
+    fn can_hold_projects(&self, env: Environment, dir: &Path) -> bool {
+        let name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if name.starts_with('.') || SKIPPED.contains(&name.as_str()) {
+            return false;
+        }
+        self.bases.iter().filter(|(e, _, _)| *e == env).any(|(_, base, depth)| {
+            dir.strip_prefix(base).map(|rel| (rel.components().count() as u32) < *depth).unwrap_or(false)
+        })
+    }
+
+    // Turn a batch of changed local paths into events
+    fn changed(&mut self, runner: &dyn CommandRunner, paths: &[PathBuf]) -> Vec<WatchEvent> {
+        let mut rescans = Vec::new();
+        let mut repos = Vec::new();
+        for path in paths {
+            match self.classify(path) {
+                Some(Change::Projects(env)) if !rescans.contains(&env) => rescans.push(env),
+                Some(Change::Git(env, repo)) if !repos.contains(&(env, repo.clone())) => repos.push((env, repo)),
+                _ => {}
+            }
+        }
+
+        let mut events = Vec::new();
+        for env in rescans {
+            events.extend(self.rescan(runner, env));
+        }
+        events.extend(self.status_events(runner, repos));
+        events
+    }
+
+    // Scan the roots of `env` again and report what came and went
+    fn rescan(&mut self, runner: &dyn CommandRunner, env: Environment) -> Vec<WatchEvent> {
+        let found = scan_roots(runner, env, &self.roots);
+        let mut events = Vec::new();
+
+        for old in self.projects.iter().filter(|p| p.env == env && !found.iter().any(|f| f.path == p.path)) {
+            self.statuses.remove(&old.path);
+            events.push(WatchEvent::ProjectRemoved(ProjectKey { env, path: old.path.clone() }));
+        }
+
+        let known: Vec<String> = self.projects.iter().filter(|p| p.env == env).map(|p| p.path.clone()).collect();
+        for mut project in found.iter().filter(|f| !known.contains(&f.path)).cloned() {
+            project.git_status = cached_git_status(runner, &project.path, env);
+            project.info = detect_project(runner, env, &project.path);
+            self.statuses.insert(project.path.clone(), project.git_status.clone());
+            events.push(WatchEvent::ProjectAdded(project));
+        }
+
+        self.projects.retain(|p| p.env != env);
+        self.projects.extend(found);
+        self.extra.retain(|dir| dir.is_dir());
+        events
+    }
+
+    // Status of each repo, reported only when it differs from the last one sent
+    fn status_events(&mut self, runner: &dyn CommandRunner, repos: Vec<(Environment, String)>) -> Vec<WatchEvent> {
+        let mut events = Vec::new();
+        for (env, path) in repos {
+            let status = cached_git_status(runner, &path, env);
+            if self.statuses.get(&path) == Some(&status) {
+                continue;
+            }
+            self.statuses.insert(path.clone(), status.clone());
+            events.push(WatchEvent::GitStatusChanged(GitStatusChange { path, env, status }));
+        }
+        events
+    }
+
+    // One WSL call scans every root for added/removed projects, and one more
+    // reads the git files and commits of every repo
+    fn poll_wsl(&mut self, runner: &dyn CommandRunner) -> Vec<WatchEvent> {
+        let env = Environment::Wsl;
+        if !self.envs.contains(&env) {
+            return Vec::new();
+        }
+
+        let mut events = self.rescan(runner, env);
+
+        let repos: Vec<String> = self.projects.iter().filter(|p| p.env == env).map(|p| p.path.clone()).collect();
+        if repos.is_empty() {
+            return events;
+        }
+        let Some(fingerprints) = git_fingerprints(runner, env, &repos) else {
+            return events;
+        };
+
+        let changed = match &self.fingerprints {
+            Some(old) => repos
+                .into_iter()
+                .filter(|repo| match (old.get(repo), fingerprints.get(repo)) {
+                    (Some(old), Some(new)) => !old.same(new),
+                    (old, new) => old.is_some() != new.is_some(),
+                })
+                .map(|repo| (env, repo))
+                .collect(),
+            None => Vec::new(),
+        };
+        self.fingerprints = Some(fingerprints);
+
+        events.extend(self.status_events(runner, changed));
+        events
+    }
+}
+
+enum Message {
+    Changed(Vec<PathBuf>),
+    Reload,
+}
+
+static CONTROL: OnceLock<Sender<Message>> = OnceLock::new();
+
+// Start watching the configured roots; events go to every window of `app`
+pub fn start(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    if CONTROL.set(tx.clone()).is_err() {
+        return;
+    }
+    thread::spawn(move || run(app, tx, rx));
+}
+
+// Pick up changed project roots
+pub fn reload() {
+    if let Some(tx) = CONTROL.get() {
+        let _ = tx.send(Message::Reload);
+    }
+}
+
+fn run(app: AppHandle, tx: Sender<Message>, rx: Receiver<Message>) {
+    let runner = system();
+    let mut debouncer = match new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        if let Ok(events) = result {
+            let _ = tx.send(Message::Changed(events.into_iter().map(|e| e.path).collect()));
+        }
+    }) {
+        Ok(debouncer) => Some(debouncer),
+        Err(e) => {
+            println!("[DEBUG] Filesystem watcher unavailable: {}", e);
+            None
+        }
+    };
+
+    let mut tracker = Tracker::new(Environment::available());
+    let mut watched = Vec::new();
+    tracker.reload(runner, registry().roots());
+    sync_watches(debouncer.as_mut(), &mut watched, tracker.watch_list());
+
+    // A deadline of its own, so a steady stream of local changes (a build,
+    // `npm install`) can't hold off the WSL poll
+    let mut next_poll = Instant::now() + POLL_INTERVAL;
+    loop {
+        let mut events = match rx.recv_timeout(next_poll.saturating_duration_since(Instant::now())) {
+            Ok(Message::Changed(paths)) => tracker.changed(runner, &paths),
+            Ok(Message::Reload) => {
+                tracker.reload(runner, registry().roots());
+                Vec::new()
+            }
+            Err(RecvTimeoutError::Timeout) => Vec::new(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if Instant::now() >= next_poll {
+            events.extend(tracker.poll_wsl(runner));
+            next_poll = Instant::now() + POLL_INTERVAL;
+        }
+        sync_watches(debouncer.as_mut(), &mut watched, tracker.watch_list());
+        emit(&app, events);
+    }
+}
+
+fn sync_watches(
+    debouncer: Option<&mut Debouncer<RecommendedWatcher>>,
+    watched: &mut Vec<(PathBuf, RecursiveMode)>,
+    wanted: Vec<(PathBuf, RecursiveMode)>,
+) {
+    let Some(debouncer) = debouncer else { return };
+    for (path, _) in watched.iter().filter(|w| !wanted.contains(w)) {
+        let _ = debouncer.watcher().unwatch(path);
+    }
+    for (path, mode) in wanted.iter().filter(|w| !watched.contains(w)) {
+        if let Err(e) = debouncer.watcher().watch(path, *mode) {
+            println!("[DEBUG] Cannot watch {}: {}", path.display(), e);
+        }
+    }
+    *watched = wanted;
+}
+
+fn emit(app: &AppHandle, events: Vec<WatchEvent>) {
+    for event in events {
+        let result = match event {
+            WatchEvent::ProjectAdded(mut project) => {
+                registry().annotate(std::slice::from_mut(&mut project));
+                app.emit(PROJECT_ADDED, project)
+            }
+            WatchEvent::ProjectRemoved(key) => app.emit(PROJECT_REMOVED, key),
+            WatchEvent::GitStatusChanged(change) => app.emit(GIT_STATUS_CHANGED, change),
+        };
+        if let Err(e) = result {
+            println!("[DEBUG] Failed to emit watcher event: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+    use std::fs;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ketra-watch-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn local_tracker(base: &Path) -> Tracker {
+        let env = Environment::host();
+        let mut tracker = Tracker::new(vec![env]);
+        tracker.reload(&FakeRunner::new(), vec![ProjectRoot::new(env, &base.to_string_lossy())]);
+        tracker
+    }
+
+    #[test]
+    fn classifies_local_changes() {
+        let base = temp_root("classify");
+        fs::create_dir_all(base.join("clients").join("acme").join(".git").join("refs")).unwrap();
+        let mut tracker = local_tracker(&base);
+        let repo = base.join("clients").join("acme");
+        let env = Environment::host();
+
+        assert_eq!(
+            tracker.classify(&repo.join(".git").join("HEAD")),
+            Some(Change::Git(env, repo.to_string_lossy().to_string()))
+        );
+        assert_eq!(tracker.classify(&base.join("clients").join("globex")), Some(Change::Projects(env)));
+        assert_eq!(tracker.classify(&repo.join("src").join("main.rs")), None);
+
+        let watched = tracker.watch_list();
+        assert!(watched.contains(&(base.join("clients"), RecursiveMode::NonRecursive)));
+        assert!(watched.contains(&(repo.join(".git").join("refs"), RecursiveMode::Recursive)));
+        assert!(!watched.iter().any(|(p, _)| p == &repo));
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn reports_added_and_removed_projects() {
+        let base = temp_root("rescan");
+        fs::create_dir_all(base.join("old").join(".git")).unwrap();
+        let mut tracker = local_tracker(&base);
+
+        fs::remove_dir_all(base.join("old")).unwrap();
+        fs::create_dir_all(base.join("new")).unwrap();
+        fs::write(base.join("new").join("Cargo.toml"), "[package]\nname = \"new\"\n").unwrap();
+
+        let events = tracker.changed(&FakeRunner::new(), &[base.join("old"), base.join("new")]);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], WatchEvent::ProjectRemoved(key) if key.path.ends_with("old")));
+        assert!(matches!(&events[1], WatchEvent::ProjectAdded(p) if p.name == "new" && p.info.is_some()));
+
+        // A second batch for the same change reports nothing new
+        assert!(tracker.changed(&FakeRunner::new(), &[base.join("new")]).is_empty());
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn new_folders_are_watched_for_later_projects() {
+        let base = temp_root("extra");
+        let mut tracker = local_tracker(&base);
+
+        fs::create_dir_all(base.join("clients")).unwrap();
+        assert!(tracker.changed(&FakeRunner::new(), &[base.join("clients")]).is_empty());
+        assert!(tracker.watch_list().contains(&(base.join("clients"), RecursiveMode::NonRecursive)));
+
+        fs::create_dir_all(base.join("clients").join("acme").join(".git")).unwrap();
+        let events = tracker.changed(&FakeRunner::new(), &[base.join("clients").join("acme")]);
+        assert!(matches!(&events[..], [WatchEvent::ProjectAdded(p)] if p.name == "acme"));
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    fn wsl_runner(fingerprint: &str, branch: &str) -> FakeRunner {
+        FakeRunner::new()
+            .on(
+                "find_projects()",
+                CommandOutput::ok(r#"{"root":0,"base":"/home/dev/ketra","path":"/home/dev/ketra/app","modified":1,"git":null}"#),
+            )
+            .on("rev-parse --git-path", CommandOutput::ok(&format!("{} /home/dev/ketra/app\n", fingerprint)))
+            .on("status --porcelain=v2", CommandOutput::ok(&format!("# branch.oid abc\n# branch.head {}\n", branch)))
+    }
+
+    #[test]
+    fn polls_wsl_git_files() {
+        let mut tracker = Tracker::new(vec![Environment::Wsl]);
+        tracker.reload(&wsl_runner("1 1 0 abc -", "main"), Vec::new());
+        assert_eq!(tracker.projects.len(), 1);
+
+        // The first poll only records the current state
+        assert!(tracker.poll_wsl(&wsl_runner("1 1 0 abc -", "main")).is_empty());
+        assert!(tracker.poll_wsl(&wsl_runner("1 1 0 abc -", "main")).is_empty());
+
+        let runner = wsl_runner("2 1 0 abc -", "main");
+        let events = tracker.poll_wsl(&runner);
+        assert!(matches!(
+            &events[..],
+            [WatchEvent::GitStatusChanged(c)] if c.path == "/home/dev/ketra/app" && c.status.as_ref().unwrap().branch == "main"
+        ));
+        assert!(runner.calls().iter().any(|c| c.args.last().map(String::as_str) == Some("/home/dev/ketra/app")));
+
+        // Same status again after another touch: nothing to report
+        assert!(tracker.poll_wsl(&wsl_runner("3 1 0 abc -", "main")).is_empty());
+
+        // A commit on feature/x or a fetch from any remote only moves commits
+        let events = tracker.poll_wsl(&wsl_runner("3 1 0 def -", "feature/x"));
+        assert!(matches!(&events[..], [WatchEvent::GitStatusChanged(c)] if c.status.as_ref().unwrap().branch == "feature/x"));
+        let events = tracker.poll_wsl(&wsl_runner("3 1 0 def 123", "feature/y"));
+        assert!(matches!(&events[..], [WatchEvent::GitStatusChanged(c)] if c.status.as_ref().unwrap().branch == "feature/y"));
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+        full.extend_from_slice(args);
+        let output = system().output(&Environment::host().command("git", &full, Some(&dir.to_string_lossy()))).unwrap();
+        assert!(output.success, "git {:?}: {}", args, output.stderr);
+    }
+
+    #[test]
+    fn reports_commits_on_slash_named_branches() {
+        let base = temp_root("feature");
+        let repo = base.join("app");
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q", "-b", "main"]);
+        git(&repo, &["checkout", "-q", "-b", "feature/x"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&repo, &["remote", "add", "fork", "https://example.com/fork.git"]);
+        git(&repo, &["update-ref", "refs/remotes/fork/feature/x", "HEAD"]);
+        git(&repo, &["branch", "-q", "--set-upstream-to=fork/feature/x"]);
+        let mut tracker = local_tracker(&base);
+        let path = repo.to_string_lossy().to_string();
+        let runner = system();
+        let status_of = |events: &[WatchEvent]| match events {
+            [WatchEvent::GitStatusChanged(c)] if c.path == path => c.status.clone().unwrap(),
+            _ => panic!("expected one status change, got {:?}", events),
+        };
+
+        let events = tracker.changed(runner, &[repo.join(".git").join("HEAD")]);
+        assert_eq!(status_of(&events).commits_ahead, 0);
+
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "second"]);
+        let ref_file = repo.join(".git").join("refs").join("heads").join("feature").join("x");
+        assert_eq!(tracker.classify(&ref_file), Some(Change::Git(Environment::host(), path.clone())));
+        let events = tracker.changed(runner, &[ref_file]);
+        assert_eq!(status_of(&events).commits_ahead, 1);
+
+        // Pushed to a remote other than origin
+        git(&repo, &["update-ref", "refs/remotes/fork/feature/x", "HEAD"]);
+        let events = tracker.changed(runner, &[repo.join(".git").join("refs").join("remotes").join("fork").join("feature").join("x")]);
+        assert_eq!(status_of(&events).commits_ahead, 0);
+
+        fs::remove_dir_all(&base).ok();
+    }
+}
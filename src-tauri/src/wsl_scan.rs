@@ -0,0 +1,221 @@
+use crate::discovery::{MARKERS, SKIPPED};
+use crate::environment::Environment;
+use crate::exec::CommandRunner;
+use crate::roots::{roots_for, ProjectRoot};
+use crate::types::{GitStatus, Project};
+use serde::Deserialize;
+
+// Starting wsl.exe costs far more than anything the scan does inside WSL, so
+// every root and every repo is handled by this one script
+const SCAN_SCRIPT: &str = include_str!("wsl_scan.sh");
+
+#[derive(Debug, Deserialize)]
+struct ScannedProject {
+    // Index into the roots passed to the script
+    root: usize,
+    // The root with ~ expanded
+    base: String,
+    path: String,
+    modified: u64,
+    git: Option<GitStatus>,
+}
+
+// Projects under every WSL root, with their git status when `with_status`
+pub fn scan(runner: &dyn CommandRunner, configured: &[ProjectRoot], with_status: bool) -> Vec<Project> {
+    let roots = roots_for(Environment::Wsl, configured);
+    let mut projects: Vec<Project> = Vec::new();
+
+    for scanned in run_script(runner, Environment::Wsl, &roots, with_status) {
+        let Some(root) = roots.get(scanned.root) else { continue };
+        let relative = scanned.path.strip_prefix(&scanned.base).unwrap_or(&scanned.path).trim_start_matches('/');
+        // Overlapping roots (~/code and ~/code/clients) can find the same folder twice
+        if !root.accepts(relative) || projects.iter().any(|p| p.path == scanned.path) {
+            continue;
+        }
+
+        let name = Environment::Wsl.file_name(&scanned.path);
+        let mut project = Project::new(name, scanned.path, Environment::Wsl, scanned.base, scanned.modified);
+        project.git_status = scanned.git;
+        projects.push(project);
+    }
+
+    projects
+}
+
+// `env` is only ever WSL outside of tests, which run the script on the host
+fn run_script(runner: &dyn CommandRunner, env: Environment, roots: &[ProjectRoot], with_status: bool) -> Vec<ScannedProject> {
+    let markers: Vec<&str> = MARKERS.iter().copied().filter(|m| *m != ".git").collect();
+    let markers = markers.join(" ");
+    let skipped = SKIPPED.join(" ");
+    let depths: Vec<String> = roots.iter().map(|r| r.depth.to_string()).collect();
+
+    let mut args = vec!["-c", SCAN_SCRIPT, "sh", if with_status { "status" } else { "list" }, &markers, &skipped];
+    for (root, depth) in roots.iter().zip(&depths) {
+        args.extend_from_slice(&[depth, &root.path]);
+    }
+
+    match runner.output(&env.command("sh", &args, None)) {
+        Ok(output) => parse_scan_output(&output.stdout),
+        Err(e) => {
+            println!("[DEBUG] WSL scan failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// One JSON object per line; anything else (e.g. a login banner) is skipped
+fn parse_scan_output(stdout: &str) -> Vec<ScannedProject> {
+    stdout.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::fake::FakeRunner;
+    use crate::exec::CommandOutput;
+
+    const OUTPUT: &str = r#"{"root":0,"base":"/home/dev/ketra","path":"/home/dev/ketra/app","modified":1700000000,"git":{"branch":"main","is_clean":false,"commits_ahead":1,"commits_behind":0,"uncommitted_files":2,"last_commit_time":1690000000,"staged":0,"modified":1,"untracked":1,"conflicted":0,"stash_count":0,"upstream":"origin/main","detached_at":null,"operation":null}}
+{"root":0,"base":"/home/dev/ketra","path":"/home/dev/ketra/notes","modified":1700000001,"git":null}
+{"root":1,"base":"/srv/work","path":"/srv/work/clients/acme","modified":1,"git":null}
+{"root":1,"base":"/srv/work","path":"/srv/work/clients/acme-old","modified":1,"git":null}
+{"root":2,"base":"/srv/work/clients","path":"/srv/work/clients/acme","modified":1,"git":null}
+"#;
+
+    fn roots() -> Vec<ProjectRoot> {
+        let work = ProjectRoot {
+            depth: 2,
+            include: vec!["clients/*".to_string()],
+            exclude: vec!["*/*-old".to_string()],
+            ..ProjectRoot::new(Environment::Wsl, "/srv/work")
+        };
+        vec![
+            ProjectRoot::default_for(Environment::Wsl),
+            work,
+            ProjectRoot::new(Environment::Wsl, "/srv/work/clients"),
+            ProjectRoot::new(Environment::Linux, "/elsewhere"),
+        ]
+    }
+
+    #[test]
+    fn one_wsl_call_for_every_root_and_repo() {
+        let runner = FakeRunner::new().on("--exec sh -c", CommandOutput::ok(OUTPUT));
+
+        let projects = scan(&runner, &roots(), true);
+        let found: Vec<(&str, &str)> = projects.iter().map(|p| (p.path.as_str(), p.root.as_str())).collect();
+        assert_eq!(
+            found,
+            [
+                ("/home/dev/ketra/app", "/home/dev/ketra"),
+                ("/home/dev/ketra/notes", "/home/dev/ketra"),
+                ("/srv/work/clients/acme", "/srv/work"),
+            ]
+        );
+
+        let status = projects[0].git_status.as_ref().unwrap();
+        assert_eq!((status.branch.as_str(), status.uncommitted_files, status.commits_ahead), ("main", 2, 1));
+        assert!(!status.is_clean);
+        assert!(projects[1].git_status.is_none());
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].args[5..7], ["status".to_string(), "Cargo.toml package.json go.mod pyproject.toml".to_string()]);
+        assert_eq!(calls[0].args[8..], ["3", "~/ketra", "2", "/srv/work", "3", "/srv/work/clients"]);
+    }
+
+    #[test]
+    fn ignores_lines_that_are_not_projects() {
+        assert_eq!(parse_scan_output("Welcome to Ubuntu\n{\"root\":0}\n\n").len(), 0);
+    }
+
+    // Runs the real script against folders on the host
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn script_finds_projects_and_their_status() {
+        use crate::exec::system;
+        use std::fs;
+
+        let base = std::env::temp_dir().join(format!("ketra-wsl-scan-{}", std::process::id()));
+        fs::remove_dir_all(&base).ok();
+        let repo = base.join("it's \"quoted\"");
+        fs::create_dir_all(repo.join("packages").join("ui")).unwrap();
+        fs::write(repo.join("packages").join("ui").join("package.json"), "{}").unwrap();
+        fs::create_dir_all(base.join("clients").join("acme")).unwrap();
+        fs::write(base.join("clients").join("acme").join("go.mod"), "module acme\n").unwrap();
+        fs::create_dir_all(base.join("node_modules").join("dep")).unwrap();
+        fs::write(base.join("node_modules").join("dep").join("package.json"), "{}").unwrap();
+        fs::create_dir_all(base.join("a-x")).unwrap();
+        fs::write(base.join("a-x").join("Cargo.toml"), "").unwrap();
+        // Control characters must still make valid JSON
+        let odd = base.join("odd\nname\t\u{1b}[0m");
+        fs::create_dir_all(&odd).unwrap();
+        fs::write(odd.join("Cargo.toml"), "").unwrap();
+
+        let run = |args: &[&str]| {
+            let spec = Environment::host().command("git", args, Some(&repo.to_string_lossy()));
+            assert!(system().output(&spec).unwrap().success, "git {:?}", args);
+        };
+        run(&["init", "-q", "-b", "main"]);
+        fs::write(repo.join("README.md"), "").unwrap();
+
+        let root = ProjectRoot::new(Environment::Wsl, &format!("{}/", base.display()));
+        let mut found = run_script(system(), Environment::host(), &[root], true);
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let paths: Vec<String> = found.iter().map(|p| p.path.strip_prefix(&format!("{}/", base.display())).unwrap().to_string()).collect();
+        assert_eq!(paths, ["a-x", "clients/acme", "it's \"quoted\"", "odd\nname\t\u{1b}[0m"]);
+        assert_eq!(found[3].path, odd.to_string_lossy());
+        assert_eq!(found[2].base, base.to_string_lossy());
+        assert!(found[0].modified > 0);
+        assert!(found[0].git.is_none());
+
+        // README.md and the untracked packages/ folder
+        let git = found[2].git.as_ref().unwrap();
+        assert_eq!((git.branch.as_str(), git.uncommitted_files, git.last_commit_time), ("main", 2, 0));
+        assert_eq!((git.untracked, git.staged, git.upstream.as_deref(), git.operation), (2, 0, None, None));
+
+        // Matches what get_git_status reports for the same repo
+        let repo_path = repo.to_string_lossy();
+        assert_eq!(Some(git.clone()), crate::git::get_git_status(system(), &repo_path, Environment::host()));
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn script_reads_worktrees_and_reports_failed_statuses() {
+        use crate::exec::system;
+        use std::fs;
+
+        let base = std::env::temp_dir().join(format!("ketra-wsl-worktree-{}", std::process::id()));
+        fs::remove_dir_all(&base).ok();
+        let main = base.join("main");
+        fs::create_dir_all(&main).unwrap();
+        let git = |args: &[&str]| {
+            let mut full = vec!["-c", "user.name=Ketra", "-c", "user.email=ketra@example.com", "-c", "commit.gpgsign=false"];
+            full.extend_from_slice(args);
+            let output = system().output(&Environment::host().command("git", &full, Some(&main.to_string_lossy()))).unwrap();
+            assert!(output.success, "git {:?}: {}", args, output.stderr);
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        git(&["worktree", "add", "-q", "-b", "feature/x", "../linked"]);
+
+        // git refuses to read this one
+        fs::create_dir_all(base.join("broken").join(".git")).unwrap();
+
+        let root = ProjectRoot::new(Environment::Wsl, &base.to_string_lossy());
+        let mut found = run_script(system(), Environment::host(), &[root], true);
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let statuses: Vec<(String, Option<String>)> = found
+            .iter()
+            .map(|p| (Environment::Wsl.file_name(&p.path), p.git.as_ref().map(|g| g.branch.clone())))
+            .collect();
+        assert_eq!(
+            statuses,
+            [("broken".into(), None), ("linked".into(), Some("feature/x".into())), ("main".into(), Some("main".into()))]
+        );
+
+        fs::remove_dir_all(&base).ok();
+    }
+}
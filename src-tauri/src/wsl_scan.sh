@@ -0,0 +1,105 @@
+# Lists the projects under each root as JSON lines, in one WSL session.
+#
+# Arguments: <list|status> "<marker files>" "<skipped folders>", then a
+# <depth> <root> pair per root. Roots may start with ~. Prints, per project:
+#   {"root":0,"base":"/home/dev/ketra","path":"/home/dev/ketra/app","modified":1700000000,"git":null}
+# where "git" is filled in when the mode is `status` and the project is a repo.
+
+export LC_ALL=C
+mode=$1
+markers=$2
+skipped=$3
+shift 3
+
+# A JSON string with every control character escaped
+json() {
+    JSON_TEXT=$1 awk 'BEGIN {
+        for (i = 1; i < 32; i++) esc[sprintf("%c", i)] = sprintf("\\u%04x", i)
+        esc["\\"] = "\\\\"; esc["\""] = "\\\""; esc["\n"] = "\\n"; esc["\t"] = "\\t"
+        s = ENVIRON["JSON_TEXT"]
+        for (i = 1; i <= length(s); i++) { c = substr(s, i, 1); out = out ((c in esc) ? esc[c] : c) }
+        printf "\"%s\"", out
+    }'
+}
+
+# Newlines in folder names travel through the line-based pipes below as this
+SOH=$(printf '\001')
+
+# Folders holding a marker, parents before children, nested projects dropped
+find_projects() {
+    set -- "$1" -mindepth 1 -maxdepth $(($2 + 1)) \
+        \( -name .git -printf '%h\0' -prune \) -o \( -type d \( -name '.*'
+    for name in $skipped; do set -- "$@" -o -name "$name"; done
+    set -- "$@" \) -prune \) -o \( -type f \( -name '*.sln'
+    for name in $markers; do set -- "$@" -o -name "$name"; done
+    set -- "$@" \) -printf '%h\0' \)
+
+    find "$@" 2>/dev/null | tr '\n\0' '\001\n' | sort -u | awk '
+        { for (i = 1; i <= n; i++) if (index($0, kept[i] "/") == 1) next }
+        { kept[++n] = $0; print }'
+}
+
+# Same answers as get_git_status in git.rs, printed as its GitStatus JSON, or
+# null when git can't read the repo (a failed status is not a clean repo)
+git_status() {
+    porcelain=$(git -C "$1" status --porcelain=v2 --branch 2>/dev/null) || { printf null; return; }
+    status=$(printf '%s\n' "$porcelain" | awk '
+        /^# branch\.oid / { oid = $3 }
+        /^# branch\.head / { head = substr($0, 15) }
+        /^# branch\.upstream / { upstream = substr($0, 19) }
+        /^# branch\.ab / { ahead = substr($3, 2); behind = substr($4, 2) }
+        /^[12] / { changed++; if (substr($2, 1, 1) != ".") staged++; if (substr($2, 2, 1) != ".") modified++ }
+        /^u / { conflicted++ }
+        /^\? / { untracked++ }
+        END { printf "%s\n%s\n%s\n%d %d %d %d %d %d %d\n", head, oid, upstream, ahead, behind, changed, staged, modified, untracked, conflicted }')
+    { read -r head; read -r oid; read -r upstream; read -r ahead behind changed staged modified untracked conflicted; } <<EOF
+$status
+EOF
+    stashes=$(git -C "$1" rev-list --walk-reflogs --count refs/stash 2>/dev/null) || stashes=0
+    commit=$(git -C "$1" log -1 --format=%ct 2>/dev/null) || commit=0
+
+    branch=$(json "$head") detached=null
+    if [ "$head" = "(detached)" ]; then
+        branch='""' detached=$(json "$(printf '%.7s' "$oid")")
+    fi
+    [ -n "$upstream" ] && upstream=$(json "$upstream") || upstream=null
+
+    dir=$(git -C "$1" rev-parse --absolute-git-dir 2>/dev/null)
+    if [ -e "$dir/rebase-merge" ] || [ -e "$dir/rebase-apply" ]; then operation='"rebase"'
+    elif [ -e "$dir/MERGE_HEAD" ]; then operation='"merge"'
+    elif [ -e "$dir/CHERRY_PICK_HEAD" ]; then operation='"cherry-pick"'
+    elif [ -e "$dir/BISECT_LOG" ]; then operation='"bisect"'
+    else operation=null
+    fi
+
+    total=$((changed + untracked + conflicted))
+    [ "$total" -eq 0 ] && clean=true || clean=false
+    printf '{"branch":%s,"is_clean":%s,"commits_ahead":%d,"commits_behind":%d,"uncommitted_files":%d,"last_commit_time":%d,' \
+        "$branch" "$clean" "${ahead:-0}" "${behind:-0}" "$total" "${commit:-0}"
+    printf '"staged":%d,"modified":%d,"untracked":%d,"conflicted":%d,"stash_count":%d,"upstream":%s,"detached_at":%s,"operation":%s}' \
+        "$staged" "$modified" "$untracked" "$conflicted" "${stashes:-0}" "$upstream" "$detached" "$operation"
+}
+
+index=0
+while [ $# -ge 2 ]; do
+    depth=$1
+    base=$2
+    shift 2
+    case $base in
+        "~") base=$HOME ;;
+        "~/"*) base=$HOME/${base#"~/"} ;;
+    esac
+    [ "$base" = / ] || base=${base%/}
+
+    find_projects "$base" "$depth" | while IFS= read -r dir; do
+        case $dir in *"$SOH"*) dir=$(printf '%s' "$dir" | tr '\001' '\n') ;; esac
+        git=null
+        # A folder in a plain repo, a file in worktrees and submodules
+        if [ "$mode" = status ] && [ -e "$dir/.git" ]; then
+            git=$(git_status "$dir")
+        fi
+        printf '{"root":%d,"base":%s,"path":%s,"modified":%d,"git":%s}\n' \
+            "$index" "$(json "$base")" "$(json "$dir")" "$(stat -c %Y -- "$dir")" "$git"
+    done
+    index=$((index + 1))
+done
@@ -8,6 +8,8 @@ import { ContextMenu } from './ts/ui/contextMenu';
 import { AppContextMenu } from './ts/ui/appContextMenu';
 import { ContextMenuManager } from './ts/ui/contextMenuManager';
 import { API } from './ts/api';
+import { Storage } from './ts/storage';
+import { SortBy } from './ts/types';
 
 async function init() {
   // Initialize UI components
@@ -18,6 +20,7 @@ async function init() {
   EventHandlers.setupCardActions();
   EventHandlers.setupFabButtons();
   setupSearch();
+  setupSort();
 
   // Setup drag and drop
   DragHandler.init();
@@ -34,7 +37,10 @@ async function init() {
   });
 
   // Load initial data
+  state.setEnvironments(await API.getEnvironments());
+  await migrateLegacyProjectState();
   await Renderer.loadProjects();
+  await Renderer.watchChanges();
 
   // Check GitHub authentication
   try {
@@ -47,6 +53,20 @@ async function init() {
   console.log('App initialized');
 }
 
+// Pins and order used to be kept in localStorage by project name; hand them
+// to the backend registry once, then forget them
+async function migrateLegacyProjectState() {
+  const legacy = Storage.loadLegacyProjectState();
+  if (!legacy) return;
+
+  try {
+    await API.importLegacyProjectState(legacy.pinned, legacy.order);
+    Storage.clearLegacyProjectState();
+  } catch (error) {
+    console.error('Failed to migrate pinned projects:', error);
+  }
+}
+
 function setupSearch() {
   const searchInput = document.getElementById('searchInput') as HTMLInputElement;
   searchInput?.addEventListener('input', (e) => {
@@ -55,6 +75,20 @@ function setupSearch() {
   });
 }
 
+function setupSort() {
+  const sortSelect = document.getElementById('sortSelect') as HTMLSelectElement;
+  if (!sortSelect) return;
+
+  sortSelect.value = state.getSettings().sortBy;
+  sortSelect.addEventListener('change', () => {
+    state.setSettings({ sortBy: sortSelect.value as SortBy });
+  });
+  // Dragging a card switches back to the custom order
+  state.subscribe(() => {
+    sortSelect.value = state.getSettings().sortBy;
+  });
+}
+
 function filterProjects(query: string) {
   const pinnedCards = document.querySelectorAll('#pinnedProjects .project-card');
   const recentCards = document.querySelectorAll('#recentProjects .project-card');
@@ -62,8 +96,10 @@ function filterProjects(query: string) {
   [...pinnedCards, ...recentCards].forEach(card => {
     const projectName = card.querySelector('.project-name')?.textContent?.toLowerCase() || '';
     const projectPath = card.querySelector('.project-path')?.textContent?.toLowerCase() || '';
+    // Also match detected languages/frameworks, e.g. "rust" or "next.js"
+    const badges = card.querySelector('.project-badges')?.textContent?.toLowerCase() || '';
 
-    if (projectName.includes(query) || projectPath.includes(query)) {
+    if (projectName.includes(query) || projectPath.includes(query) || badges.includes(query)) {
       (card as HTMLElement).style.display = '';
     } else {
       (card as HTMLElement).style.display = 'none';
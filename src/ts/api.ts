@@ -1,5 +1,28 @@
 import { invoke } from '@tauri-apps/api/core';
-import { Project } from './types';
+import { Project, ProjectKey, ProjectRoot, ProjectInfo, Environment, ErrorKind, KetraError, FileChange, CommitOptions, Branch, DeletePreview, Operation, CloneOptions, ClonePlan, HistoryQuery, HistoryPage, CommitDetail, DiffTarget, DiffOptions, DiffFile } from './types';
+
+function toKetraError(error: unknown): KetraError {
+  if (error && typeof error === 'object' && 'kind' in error && 'message' in error) {
+    return error as KetraError;
+  }
+  return { kind: 'Failed', message: String(error), stderr: '' };
+}
+
+// Error thrown by API calls; keeps the backend's error kind so the UI can
+// offer a targeted fix (e.g. a login button for AuthMissing)
+export class ApiError extends Error {
+  readonly kind: ErrorKind;
+  readonly stderr: string;
+  readonly status?: number;
+
+  constructor(context: string, error: unknown) {
+    const err = toKetraError(error);
+    super(context ? `${context}: ${err.message}` : err.message);
+    this.kind = err.kind;
+    this.stderr = err.stderr;
+    this.status = err.status;
+  }
+}
 
 export class API {
   static async getRecentProjects(): Promise<Project[]> {
@@ -7,7 +30,7 @@ export class API {
       return await invoke<Project[]>('get_recent_projects');
     } catch (error) {
       console.error('Failed to get recent projects:', error);
-      throw new Error(`Failed to load projects: ${error}`);
+      throw new ApiError('Failed to load projects', error);
     }
   }
 
@@ -16,7 +39,7 @@ export class API {
       return await invoke<Project[]>('get_projects_fast');
     } catch (error) {
       console.error('Failed to get projects:', error);
-      throw new Error(`Failed to load projects: ${error}`);
+      throw new ApiError('Failed to load projects', error);
     }
   }
 
@@ -29,6 +52,25 @@ export class API {
     }
   }
 
+  // The same scan with each repo's git status, still a single wsl call
+  static async getWslGitStatuses(): Promise<Project[]> {
+    try {
+      return await invoke<Project[]>('get_wsl_git_statuses');
+    } catch (error) {
+      console.error('Failed to get WSL git statuses:', error);
+      return [];
+    }
+  }
+
+  static async getEnvironments(): Promise<Environment[]> {
+    try {
+      return await invoke<Environment[]>('get_environments');
+    } catch (error) {
+      console.error('Failed to get environments:', error);
+      return ['windows', 'wsl'];
+    }
+  }
+
   static async getProjectGitStatus(path: string): Promise<any> {
     try {
       return await invoke('get_project_git_status', { path });
@@ -38,17 +80,27 @@ export class API {
     }
   }
 
+  static async getProjectInfo(path: string, env: string): Promise<ProjectInfo | null> {
+    try {
+      return await invoke<ProjectInfo | null>('get_project_info', { path, env });
+    } catch (error) {
+      console.error('Failed to detect project info:', error);
+      return null;
+    }
+  }
+
   static async launchProject(params: {
     env: string;
     name: string;
     template: string;
     createRepo: boolean;
+    operationId: string;
   }): Promise<void> {
     try {
       await invoke('launch_project', params);
     } catch (error) {
       console.error('Failed to launch project:', error);
-      throw new Error(`Failed to create project: ${error}`);
+      throw new ApiError('Failed to create project', error);
     }
   }
 
@@ -57,7 +109,7 @@ export class API {
       await invoke('open_existing_project', { path, env });
     } catch (error) {
       console.error('Failed to open project:', error);
-      throw new Error(`Failed to open project: ${error}`);
+      throw new ApiError('Failed to open project', error);
     }
   }
 
@@ -66,34 +118,127 @@ export class API {
       await invoke('delete_project', { path, name });
     } catch (error) {
       console.error('Failed to delete project:', error);
-      throw new Error(`Failed to delete project: ${error}`);
+      throw new ApiError('Failed to delete project', error);
     }
   }
 
-  static async gitPull(path: string, env: string): Promise<void> {
+  // Long-running git commands report progress as "operation-progress" events
+  // tagged with `operationId`; see Operations.track
+
+  static async gitPull(path: string, env: string, operationId: string): Promise<void> {
     try {
-      await invoke('git_pull', { path, env });
+      await invoke('git_pull', { path, env, operationId });
     } catch (error) {
       console.error('Failed to pull:', error);
-      throw new Error(`Git pull failed: ${error}`);
+      throw new ApiError('Git pull failed', error);
+    }
+  }
+
+  static async listOperations(): Promise<Operation[]> {
+    try {
+      return await invoke<Operation[]>('list_operations');
+    } catch (error) {
+      console.error('Failed to list operations:', error);
+      throw new ApiError('Failed to list operations', error);
+    }
+  }
+
+  static async cancelOperation(operationId: string): Promise<void> {
+    try {
+      await invoke('cancel_operation', { operationId });
+    } catch (error) {
+      console.error('Failed to cancel operation:', error);
+      throw new ApiError('Failed to cancel operation', error);
+    }
+  }
+
+  static async gitFetch(path: string, env: string, operationId: string): Promise<void> {
+    try {
+      await invoke('git_fetch', { path, env, operationId });
+    } catch (error) {
+      console.error('Failed to fetch:', error);
+      throw new ApiError('Git fetch failed', error);
     }
   }
 
-  static async gitPush(path: string, env: string, message: string): Promise<void> {
+  static async gitPush(path: string, env: string, message: string, operationId: string): Promise<void> {
     try {
-      await invoke('git_push', { path, env, message });
+      await invoke('git_push', { path, env, message, operationId });
     } catch (error) {
       console.error('Failed to push:', error);
-      throw new Error(`Git push failed: ${error}`);
+      throw new ApiError('Git push failed', error);
+    }
+  }
+
+  static async planClone(repoUrl: string, env: string, options: CloneOptions): Promise<ClonePlan> {
+    try {
+      return await invoke<ClonePlan>('plan_clone', { repoUrl, env, options });
+    } catch (error) {
+      console.error('Failed to plan clone:', error);
+      throw new ApiError('Failed to check clone destination', error);
     }
   }
 
-  static async gitClone(repoUrl: string, env: string): Promise<string> {
+  static async gitClone(repoUrl: string, env: string, options: CloneOptions, operationId: string): Promise<string> {
     try {
-      return await invoke<string>('git_clone', { repoUrl, env });
+      return await invoke<string>('git_clone', { repoUrl, env, options, operationId });
     } catch (error) {
       console.error('Failed to clone:', error);
-      throw new Error(`Git clone failed: ${error}`);
+      throw new ApiError('Git clone failed', error);
+    }
+  }
+
+  static async togglePin(path: string, env: string): Promise<boolean> {
+    try {
+      return await invoke<boolean>('toggle_pin', { path, env });
+    } catch (error) {
+      console.error('Failed to toggle pin:', error);
+      throw new ApiError('Failed to pin project', error);
+    }
+  }
+
+  static async setProjectOrder(projects: ProjectKey[]): Promise<void> {
+    try {
+      await invoke('set_project_order', { projects });
+    } catch (error) {
+      console.error('Failed to save project order:', error);
+      throw new ApiError('Failed to save project order', error);
+    }
+  }
+
+  static async setProjectMetadata(path: string, env: string, field: string, value: string | null): Promise<void> {
+    try {
+      await invoke('set_project_metadata', { path, env, field, value });
+    } catch (error) {
+      console.error('Failed to save project metadata:', error);
+      throw new ApiError('Failed to save project metadata', error);
+    }
+  }
+
+  static async getProjectRoots(): Promise<ProjectRoot[]> {
+    try {
+      return await invoke<ProjectRoot[]>('get_project_roots');
+    } catch (error) {
+      console.error('Failed to get project roots:', error);
+      return [];
+    }
+  }
+
+  static async setProjectRoots(roots: ProjectRoot[]): Promise<void> {
+    try {
+      await invoke('set_project_roots', { roots });
+    } catch (error) {
+      console.error('Failed to save project roots:', error);
+      throw new ApiError('Failed to save project roots', error);
+    }
+  }
+
+  static async importLegacyProjectState(pinned: string[], order: string[]): Promise<number> {
+    try {
+      return await invoke<number>('import_legacy_project_state', { pinned, order });
+    } catch (error) {
+      console.error('Failed to import pinned projects:', error);
+      throw new ApiError('Failed to import pinned projects', error);
     }
   }
 
@@ -119,7 +264,7 @@ export class API {
       await invoke('github_login');
     } catch (error) {
       console.error('Failed to open GitHub login:', error);
-      throw new Error(`Failed to open GitHub login: ${error}`);
+      throw new ApiError('Failed to open GitHub login', error);
     }
   }
 
@@ -128,7 +273,7 @@ export class API {
       return await invoke<string>('paste_folder', { env });
     } catch (error) {
       console.error('Failed to paste folder:', error);
-      throw new Error(`${error}`);
+      throw new ApiError('', error);
     }
   }
 
@@ -137,7 +282,7 @@ export class API {
       await invoke('open_ketra_folder', { env });
     } catch (error) {
       console.error('Failed to open ketra folder:', error);
-      throw new Error(`Failed to open ketra folder: ${error}`);
+      throw new ApiError('Failed to open ketra folder', error);
     }
   }
 
@@ -146,16 +291,16 @@ export class API {
       await invoke('open_terminal', { env });
     } catch (error) {
       console.error('Failed to open terminal:', error);
-      throw new Error(`Failed to open terminal: ${error}`);
+      throw new ApiError('Failed to open terminal', error);
     }
   }
 
-  static async getBranches(path: string, env: string): Promise<string[]> {
+  static async getBranches(path: string, env: string): Promise<Branch[]> {
     try {
-      return await invoke<string[]>('get_branches', { path, env });
+      return await invoke<Branch[]>('get_branches', { path, env });
     } catch (error) {
       console.error('Failed to get branches:', error);
-      throw new Error(`Failed to get branches: ${error}`);
+      throw new ApiError('Failed to get branches', error);
     }
   }
 
@@ -164,7 +309,7 @@ export class API {
       return await invoke<string>('switch_branch', { path, env, branch });
     } catch (error) {
       console.error('Failed to switch branch:', error);
-      throw new Error(`Failed to switch branch: ${error}`);
+      throw new ApiError('Failed to switch branch', error);
     }
   }
 
@@ -173,25 +318,159 @@ export class API {
       return await invoke<string>('create_branch', { path, env, branchName });
     } catch (error) {
       console.error('Failed to create branch:', error);
-      throw new Error(`Failed to create branch: ${error}`);
+      throw new ApiError('Failed to create branch', error);
     }
   }
 
-  static async getCommitHistory(path: string, env: string, limit: number = 10): Promise<any[]> {
+  static async previewBranchDelete(path: string, env: string, names: string[]): Promise<DeletePreview[]> {
     try {
-      return await invoke<any[]>('get_commit_history', { path, env, limit });
+      return await invoke<DeletePreview[]>('preview_branch_delete', { path, env, names });
+    } catch (error) {
+      console.error('Failed to preview branch delete:', error);
+      throw new ApiError('Failed to preview branch delete', error);
+    }
+  }
+
+  static async deleteBranch(path: string, env: string, name: string, confirmedCommit?: string): Promise<string> {
+    try {
+      return await invoke<string>('delete_branch', { path, env, name, confirmedCommit: confirmedCommit ?? null });
+    } catch (error) {
+      console.error('Failed to delete branch:', error);
+      throw new ApiError('Failed to delete branch', error);
+    }
+  }
+
+  static async renameBranch(path: string, env: string, oldName: string, newName: string): Promise<string> {
+    try {
+      return await invoke<string>('rename_branch', { path, env, oldName, newName });
+    } catch (error) {
+      console.error('Failed to rename branch:', error);
+      throw new ApiError('Failed to rename branch', error);
+    }
+  }
+
+  static async checkoutRemoteBranch(path: string, env: string, remoteBranch: string, localName?: string): Promise<string> {
+    try {
+      return await invoke<string>('checkout_remote_branch', { path, env, remoteBranch, localName: localName ?? null });
+    } catch (error) {
+      console.error('Failed to check out remote branch:', error);
+      throw new ApiError('Failed to check out remote branch', error);
+    }
+  }
+
+  static async pruneMergedBranches(path: string, env: string, dryRun: boolean): Promise<string[]> {
+    try {
+      return await invoke<string[]>('prune_merged_branches', { path, env, dryRun });
+    } catch (error) {
+      console.error('Failed to prune merged branches:', error);
+      throw new ApiError('Failed to prune merged branches', error);
+    }
+  }
+
+  static async getCommitHistory(path: string, env: string, query: HistoryQuery = {}): Promise<HistoryPage> {
+    try {
+      return await invoke<HistoryPage>('get_commit_history', { path, env, query });
     } catch (error) {
       console.error('Failed to get commit history:', error);
-      throw new Error(`Failed to get commit history: ${error}`);
+      throw new ApiError('Failed to get commit history', error);
     }
   }
 
-  static async getDiff(path: string, env: string): Promise<string> {
+  static async getCommitDetail(path: string, env: string, hash: string): Promise<CommitDetail> {
     try {
-      return await invoke<string>('get_diff', { path, env });
+      return await invoke<CommitDetail>('get_commit_detail', { path, env, hash });
+    } catch (error) {
+      console.error('Failed to get commit detail:', error);
+      throw new ApiError('Failed to load commit', error);
+    }
+  }
+
+  // The whole commit's patch when `file` is null
+  static async getCommitPatch(path: string, env: string, hash: string, file: string | null = null): Promise<string> {
+    try {
+      return await invoke<string>('get_commit_patch', { path, env, hash, file });
+    } catch (error) {
+      console.error('Failed to get commit patch:', error);
+      throw new ApiError('Failed to load commit diff', error);
+    }
+  }
+
+  static async getDiff(path: string, env: string, target: DiffTarget, options: DiffOptions = {}): Promise<DiffFile[]> {
+    try {
+      return await invoke<DiffFile[]>('get_diff', { path, env, target, options });
     } catch (error) {
       console.error('Failed to get diff:', error);
-      throw new Error(`Failed to get diff: ${error}`);
+      throw new ApiError('Failed to get diff', error);
+    }
+  }
+
+  static async getChangedFiles(path: string, env: string): Promise<FileChange[]> {
+    try {
+      return await invoke<FileChange[]>('get_changed_files', { path, env });
+    } catch (error) {
+      console.error('Failed to get changed files:', error);
+      throw new ApiError('Failed to get changed files', error);
+    }
+  }
+
+  static async stagePaths(path: string, env: string, paths: string[]): Promise<void> {
+    try {
+      await invoke('stage_paths', { path, env, paths });
+    } catch (error) {
+      throw new ApiError('Failed to stage', error);
+    }
+  }
+
+  static async unstagePaths(path: string, env: string, paths: string[]): Promise<void> {
+    try {
+      await invoke('unstage_paths', { path, env, paths });
+    } catch (error) {
+      throw new ApiError('Failed to unstage', error);
+    }
+  }
+
+  // `header` is the hunk's "@@ -a,b +c,d @@" line; `options` are the ones
+  // the hunk was shown with
+  static async stageHunk(path: string, env: string, file: string, header: string, options: DiffOptions = {}): Promise<void> {
+    try {
+      await invoke('stage_hunk', { path, env, file, header, options });
+    } catch (error) {
+      throw new ApiError('Failed to stage hunk', error);
+    }
+  }
+
+  static async unstageHunk(path: string, env: string, file: string, header: string, options: DiffOptions = {}): Promise<void> {
+    try {
+      await invoke('unstage_hunk', { path, env, file, header, options });
+    } catch (error) {
+      throw new ApiError('Failed to unstage hunk', error);
+    }
+  }
+
+  static async discardFile(path: string, env: string, file: string): Promise<void> {
+    try {
+      await invoke('discard_file', { path, env, file });
+    } catch (error) {
+      throw new ApiError('Failed to discard changes', error);
+    }
+  }
+
+  static async commitStaged(path: string, env: string, options: CommitOptions): Promise<string> {
+    try {
+      return await invoke<string>('commit_staged', { path, env, options });
+    } catch (error) {
+      console.error('Failed to commit:', error);
+      throw new ApiError('Commit failed', error);
+    }
+  }
+
+  // Push existing commits only; nothing is staged or committed
+  static async pushCommits(path: string, env: string, operationId: string): Promise<string> {
+    try {
+      return await invoke<string>('push_commits', { path, env, operationId });
+    } catch (error) {
+      console.error('Failed to push:', error);
+      throw new ApiError('Git push failed', error);
     }
   }
 
@@ -200,7 +479,7 @@ export class API {
       return await invoke<string>('git_stash', { path, env });
     } catch (error) {
       console.error('Failed to stash:', error);
-      throw new Error(`Failed to stash: ${error}`);
+      throw new ApiError('Failed to stash', error);
     }
   }
 
@@ -209,7 +488,7 @@ export class API {
       return await invoke<string>('git_stash_pop', { path, env });
     } catch (error) {
       console.error('Failed to unstash:', error);
-      throw new Error(`Failed to unstash: ${error}`);
+      throw new ApiError('Failed to unstash', error);
     }
   }
 }
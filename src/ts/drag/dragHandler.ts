@@ -1,4 +1,7 @@
 import { state } from '../state';
+import { API } from '../api';
+import { Toast } from '../ui/toast';
+import { sortProjects } from '../ui/renderer';
 
 export class DragHandler {
   private static isInitialized = false;
@@ -79,7 +82,7 @@ export class DragHandler {
         const draggingCard = document.querySelector('.dragging');
         if (draggingCard && draggingCard !== card) {
           const data = JSON.parse(e.dataTransfer!.getData('text/plain'));
-          this.reorderProjects(data.name, card.dataset.name!);
+          this.reorderProjects(data.path, card.dataset.path!);
         }
 
         card.classList.remove('drag-over');
@@ -89,11 +92,13 @@ export class DragHandler {
     this.isInitialized = true;
   }
 
-  private static reorderProjects(draggedName: string, targetName: string): void {
-    const currentOrder = state.getProjects().map(p => p.name);
+  private static reorderProjects(draggedPath: string, targetPath: string): void {
+    const projects = state.getProjects();
+    // Start from what is on screen, whichever sort is active
+    const currentOrder = sortProjects(projects, state.getSettings().sortBy).map(p => p.path);
 
-    const draggedIndex = currentOrder.indexOf(draggedName);
-    const targetIndex = currentOrder.indexOf(targetName);
+    const draggedIndex = currentOrder.indexOf(draggedPath);
+    const targetIndex = currentOrder.indexOf(targetPath);
 
     if (draggedIndex === -1 || targetIndex === -1) return;
     if (draggedIndex === targetIndex) return;
@@ -103,11 +108,18 @@ export class DragHandler {
 
     // Find where to insert it
     // After removing, we need to find the target's new index
-    const newTargetIndex = currentOrder.indexOf(targetName);
+    const newTargetIndex = currentOrder.indexOf(targetPath);
 
     // Insert at the target's position (this will push target to the right)
-    currentOrder.splice(newTargetIndex, 0, draggedName);
+    currentOrder.splice(newTargetIndex, 0, draggedPath);
 
     state.setProjectOrder(currentOrder);
+    // Dragging only makes sense in the custom order
+    if (state.getSettings().sortBy !== 'custom') {
+      state.setSettings({ sortBy: 'custom' });
+    }
+
+    const keys = currentOrder.map(path => ({ path, env: projects.find(p => p.path === path)!.env }));
+    API.setProjectOrder(keys).catch(error => Toast.error((error as Error).message));
   }
 }
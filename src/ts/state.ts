@@ -4,14 +4,14 @@ import { Storage } from './storage';
 class StateManager {
   private state: AppState = {
     projects: [],
-    pinnedProjects: new Set(),
-    projectOrder: [],
     settings: {
       defaultEnv: 'windows',
       defaultTemplate: 'empty',
       autoCreateGithub: false,
+      sortBy: 'custom',
     },
     activeEnv: 'windows', // Default to Windows
+    environments: ['windows', 'wsl'],
     isGithubAuthenticated: false,
   };
 
@@ -24,8 +24,6 @@ class StateManager {
   private loadFromStorage(): void {
     const savedSettings = Storage.loadSettings();
     this.state.settings = { ...this.state.settings, ...savedSettings };
-    this.state.pinnedProjects = Storage.loadPinnedProjects();
-    this.state.projectOrder = Storage.loadProjectOrder();
     // Set activeEnv to match the saved defaultEnv
     this.state.activeEnv = this.state.settings.defaultEnv;
   }
@@ -43,12 +41,8 @@ class StateManager {
     return this.state.activeEnv;
   }
 
-  getPinnedProjects(): Set<string> {
-    return this.state.pinnedProjects;
-  }
-
-  getProjectOrder(): string[] {
-    return this.state.projectOrder;
+  getEnvironments(): Environment[] {
+    return this.state.environments;
   }
 
   isAuthenticated(): boolean {
@@ -76,19 +70,25 @@ class StateManager {
     this.notify();
   }
 
-  togglePin(projectName: string): void {
-    if (this.state.pinnedProjects.has(projectName)) {
-      this.state.pinnedProjects.delete(projectName);
-    } else {
-      this.state.pinnedProjects.add(projectName);
+  setEnvironments(environments: Environment[]): void {
+    this.state.environments = environments;
+    // Fall back to the first available environment (e.g. 'linux' on a Linux desktop)
+    if (environments.length > 0 && !environments.includes(this.state.settings.defaultEnv)) {
+      this.setSettings({ defaultEnv: environments[0] });
     }
-    Storage.savePinnedProjects(this.state.pinnedProjects);
+  }
+
+  updateProject(path: string, changes: Partial<Project>): void {
+    this.state.projects = this.state.projects.map(p => p.path === path ? { ...p, ...changes } : p);
     this.notify();
   }
 
-  setProjectOrder(order: string[]): void {
-    this.state.projectOrder = order;
-    Storage.saveProjectOrder(order);
+  // Reorder by project path; the backend registry persists the result
+  setProjectOrder(paths: string[]): void {
+    this.state.projects = this.state.projects.map(p => {
+      const index = paths.indexOf(p.path);
+      return index === -1 ? p : { ...p, order: index };
+    });
     this.notify();
   }
 
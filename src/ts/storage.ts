@@ -25,39 +25,25 @@ export class Storage {
     }
   }
 
-  static loadPinnedProjects(): Set<string> {
+  // Pins and order used to live here, keyed by project name. They are now
+  // kept by the backend; this is only read once to migrate them.
+  static loadLegacyProjectState(): { pinned: string[]; order: string[] } | null {
     try {
       const pinned = localStorage.getItem(STORAGE_KEYS.PINNED);
-      return pinned ? new Set(JSON.parse(pinned)) : new Set();
-    } catch (error) {
-      console.error('Failed to load pinned projects:', error);
-      return new Set();
-    }
-  }
-
-  static savePinnedProjects(pinned: Set<string>): void {
-    try {
-      localStorage.setItem(STORAGE_KEYS.PINNED, JSON.stringify([...pinned]));
-    } catch (error) {
-      console.error('Failed to save pinned projects:', error);
-    }
-  }
-
-  static loadProjectOrder(): string[] {
-    try {
       const order = localStorage.getItem(STORAGE_KEYS.ORDER);
-      return order ? JSON.parse(order) : [];
+      if (pinned === null && order === null) return null;
+      return {
+        pinned: pinned ? JSON.parse(pinned) : [],
+        order: order ? JSON.parse(order) : [],
+      };
     } catch (error) {
-      console.error('Failed to load project order:', error);
-      return [];
+      console.error('Failed to load legacy project state:', error);
+      return null;
     }
   }
 
-  static saveProjectOrder(order: string[]): void {
-    try {
-      localStorage.setItem(STORAGE_KEYS.ORDER, JSON.stringify(order));
-    } catch (error) {
-      console.error('Failed to save project order:', error);
-    }
+  static clearLegacyProjectState(): void {
+    localStorage.removeItem(STORAGE_KEYS.PINNED);
+    localStorage.removeItem(STORAGE_KEYS.ORDER);
   }
 }
@@ -1,33 +1,334 @@
 export interface GitStatus {
+  // Empty on a detached HEAD
   branch: string;
   is_clean: boolean;
   commits_ahead: number;
   commits_behind: number;
+  uncommitted_files: number;
+  last_commit_time: number;
+  staged: number;
+  modified: number;
+  untracked: number;
+  conflicted: number;
+  stash_count: number;
+  // e.g. "origin/main"; null when the branch tracks nothing
+  upstream: string | null;
+  detached_at: string | null;
+  operation: GitOperation | null;
+}
+
+export type GitOperation = 'merge' | 'rebase' | 'cherry-pick' | 'bisect';
+
+export interface LineCounts {
+  added: number;
+  deleted: number;
+}
+
+// One row of a project's changes panel
+export interface FileChange {
+  path: string;
+  // Set for renames and copies
+  original_path: string | null;
+  // Porcelain codes: M, T, A, D, R, C, U, '?' for untracked, '.' for unchanged
+  index_status: string;
+  worktree_status: string;
+  conflicted: boolean;
+  binary: boolean;
+  // null when that side is unchanged, binary, or the file is untracked
+  staged_lines: LineCounts | null;
+  unstaged_lines: LineCounts | null;
+}
+
+export interface Branch {
+  // "main" locally, "origin/main" for a remote branch
+  name: string;
+  refname: string;
+  remote: string | null;
+  is_current: boolean;
+  upstream: string | null;
+  upstream_gone: boolean;
+  ahead: number;
+  behind: number;
+  commit: string;
+  subject: string;
+  commit_time: number;
+  // Contained in the default branch
+  merged: boolean;
+}
+
+// What deleting a branch would lose; pass `commit` back to confirm
+export interface DeletePreview {
+  name: string;
+  remote: string | null;
+  commit: string;
+  merged: boolean;
+  // "abc1234 Subject", newest first
+  unmerged_commits: string[];
+}
+
+// Everything optional; unset fields clone the default branch with full
+// history into <first root>/<repo name>
+export interface CloneOptions {
+  name?: string;
+  // May start with ~
+  root?: string;
+  // Branch or tag
+  reference?: string;
+  depth?: number;
+  submodules?: boolean;
+  // 'blob:none', 'blob:limit=<size>' or 'tree:<depth>'
+  filter?: string;
+  // Directories to check out; everything else stays sparse
+  sparse_paths?: string[];
+}
+
+// Where a clone would go; `suggestion` is a free name when `exists`
+export interface ClonePlan {
+  // The URL git will clone, with shorthands and browser URLs expanded
+  url: string;
+  name: string;
+  root: string;
+  path: string;
+  exists: boolean;
+  suggestion: string | null;
+}
+
+// Every filter is optional; the default is the first page of HEAD's history
+export interface HistoryQuery {
+  // Branch, tag or commit to start from
+  branch?: string;
+  author?: string;
+  path?: string;
+  // Unix seconds
+  since?: number;
+  until?: number;
+  grep?: string;
+  limit?: number;
+  // next_cursor of the previous page
+  cursor?: string;
+}
+
+export interface CommitRef {
+  name: string;
+  kind: 'head' | 'branch' | 'remote' | 'tag';
+}
+
+export interface Commit {
+  hash: string;
+  // First parent first; enough to draw the commit graph
+  parents: string[];
+  author_name: string;
+  author_email: string;
+  // Unix seconds
+  author_time: number;
+  committer_name: string;
+  committer_email: string;
+  commit_time: number;
+  subject: string;
+  body: string;
+  refs: CommitRef[];
+}
+
+// A file changed by a commit, compared with its first parent
+export interface CommitFile {
+  path: string;
+  original_path: string | null;
+  status: 'A' | 'M' | 'D' | 'R' | 'C' | 'T';
+  binary: boolean;
+  lines: LineCounts | null;
+}
+
+export interface CommitDetail {
+  commit: Commit;
+  // The first parent; null for a root commit
+  base: string | null;
+  files: CommitFile[];
+}
+
+export interface HistoryPage {
+  // Newest first, children before parents
+  commits: Commit[];
+  next_cursor: string | null;
+}
+
+// What get_diff compares
+export type DiffTarget =
+  // The index against HEAD
+  | { mode: 'staged' }
+  // The working tree against the index, plus untracked files
+  | { mode: 'unstaged' }
+  // The working tree against a branch, tag or commit, plus untracked files
+  | { mode: 'working-tree'; reference: string }
+  // What `to` changed since it branched off `from`
+  | { mode: 'range'; from: string; to: string };
+
+export interface DiffOptions {
+  // Only this file or folder
+  path?: string;
+  ignore_whitespace?: boolean;
+  // Unchanged lines around each change; 3 when unset
+  context?: number;
+  // Split modified lines into changed and unchanged words
+  word_diff?: boolean;
+}
+
+export interface WordSpan {
+  text: string;
+  changed: boolean;
+}
+
+export interface DiffLine {
+  kind: 'context' | 'added' | 'deleted';
+  // 1-based; null on the side the line doesn't exist in
+  old_line: number | null;
+  new_line: number | null;
+  text: string;
+  no_newline: boolean;
+  words: WordSpan[] | null;
+}
+
+export interface Hunk {
+  // "@@ -1,3 +1,4 @@ context", accepted by stageHunk/unstageHunk with the
+  // DiffOptions it was shown with
+  header: string;
+  old_start: number;
+  old_lines: number;
+  new_start: number;
+  new_lines: number;
+  lines: DiffLine[];
+}
+
+export interface DiffFile {
+  path: string;
+  old_path: string | null;
+  status: 'A' | 'M' | 'D' | 'R' | 'C';
+  binary: boolean;
+  untracked: boolean;
+  hunks: Hunk[];
+}
+
+export interface CommitOptions {
+  message: string;
+  amend?: boolean;
+  sign_off?: boolean;
+  // Omitted: follow the repo's commit.gpgsign setting
+  signing?: 'gpg' | 'ssh' | 'off';
+  signing_key?: string;
 }
 
 export interface Project {
   name: string;
   path: string;
+  env: Environment;
+  // Unix seconds; last_opened is 0 for projects never opened from Ketra
   last_opened: number;
+  last_modified: number;
   is_pinned: boolean;
+  order: number | null;
+  metadata: Record<string, string>;
+  root: string;
+  info: ProjectInfo | null;
   git_status?: GitStatus;
 }
 
+export interface Toolchain {
+  tool: string;
+  version: string;
+  file: string;
+}
+
+// Detected from the project's manifests; languages[0] is the primary one
+export interface ProjectInfo {
+  languages: string[];
+  frameworks: string[];
+  package_managers: string[];
+  toolchains: Toolchain[];
+}
+
+// A folder scanned for projects; `path` may start with ~
+export interface ProjectRoot {
+  env: Environment;
+  path: string;
+  depth: number;
+  include: string[];
+  exclude: string[];
+}
+
+// Identifies a project in the backend registry
+export interface ProjectKey {
+  path: string;
+  env: Environment;
+}
+
+// Payload of the backend's "git-status-changed" event; status is null when
+// the folder stopped being a repo
+export interface GitStatusChange {
+  path: string;
+  env: Environment;
+  status: GitStatus | null;
+}
+
+export type OperationKind = 'clone' | 'pull' | 'push' | 'fetch' | 'template-init';
+
+// A running clone, pull, push, fetch or template init
+export interface Operation {
+  id: string;
+  kind: OperationKind;
+  label: string;
+  env: Environment;
+  started_at: number;
+  cancelled: boolean;
+}
+
+// Payload of the backend's "operation-progress" event for a clone, pull,
+// push or fetch started with `operation_id`
+export type OperationEvent = { operation_id: string } & (
+  | { type: 'progress'; phase: string; percent: number | null; current: number | null; total: number | null; done: boolean }
+  | { type: 'line'; text: string }
+  | { type: 'finished'; success: boolean; log: string }
+);
+
 export interface Settings {
-  defaultEnv: 'windows' | 'wsl';
+  defaultEnv: Environment;
   defaultTemplate: 'empty' | 'rust' | 'nextjs' | 'python' | 'go' | 'node';
   autoCreateGithub: boolean;
+  sortBy: SortBy;
 }
 
-export type Environment = 'windows' | 'wsl';
+// 'custom' is the drag-and-drop order
+export type SortBy = 'custom' | 'opened' | 'modified' | 'commit';
+
+export type Environment = 'windows' | 'wsl' | 'linux' | 'macos';
+
+export type ErrorKind =
+  | 'NotARepo'
+  | 'NoUpstream'
+  | 'AuthMissing'
+  | 'PermissionDenied'
+  | 'ToolMissing'
+  | 'PathNotFound'
+  | 'AlreadyExists'
+  | 'Conflict'
+  | 'GitHubApi'
+  | 'InvalidInput'
+  | 'NeedsConfirmation'
+  | 'Cancelled'
+  | 'Failed';
+
+// Error shape returned by every backend command
+export interface KetraError {
+  kind: ErrorKind;
+  message: string;
+  stderr: string;
+  status?: number;
+}
 
 export type ToastType = 'info' | 'success' | 'error';
 
 export interface AppState {
   projects: Project[];
-  pinnedProjects: Set<string>;
-  projectOrder: string[];
   settings: Settings;
   activeEnv: Environment;
+  environments: Environment[];
   isGithubAuthenticated: boolean;
 }
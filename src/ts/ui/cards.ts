@@ -1,4 +1,4 @@
-import { Project } from '../types';
+import { GitStatus, Project, ProjectInfo } from '../types';
 
 export function formatTime(lastOpened: number): string {
   if (lastOpened === 0) return 'unknown';
@@ -13,28 +13,76 @@ export function formatTime(lastOpened: number): string {
   return `${Math.floor(diff / 604800)}w ago`;
 }
 
+// "opened 2h ago · changed 3d ago · committed 1w ago", skipping unknown times
+function renderTimes(project: Project): string {
+  const times = [
+    project.last_opened > 0 ? `opened ${formatTime(project.last_opened)}` : 'never opened',
+    project.last_modified > 0 ? `changed ${formatTime(project.last_modified)}` : '',
+    project.git_status?.last_commit_time ? `committed ${formatTime(project.git_status.last_commit_time)}` : '',
+  ];
+  return times.filter(t => t).join(' · ');
+}
+
+const OPERATION_LABELS: Record<string, string> = {
+  'merge': 'MERGING',
+  'rebase': 'REBASING',
+  'cherry-pick': 'CHERRY-PICKING',
+  'bisect': 'BISECTING',
+};
+
+// "2 staged · 1 modified · 3 untracked", for the branch tooltip
+function describeChanges(git: GitStatus): string {
+  const parts = [
+    git.conflicted > 0 ? `${git.conflicted} conflicted` : '',
+    git.staged > 0 ? `${git.staged} staged` : '',
+    git.modified > 0 ? `${git.modified} modified` : '',
+    git.untracked > 0 ? `${git.untracked} untracked` : '',
+  ].filter(p => p);
+  return parts.length > 0 ? parts.join(' · ') : 'No changes';
+}
+
+// Ahead/behind, stashes, and warnings for a missing upstream or a stopped merge/rebase
+function renderGitDetails(git: GitStatus): string {
+  const parts: string[] = [];
+  if (git.commits_ahead > 0) parts.push(`⬆${git.commits_ahead}`);
+  if (git.commits_behind > 0) parts.push(`⬇${git.commits_behind}`);
+  if (git.stash_count > 0) parts.push(`<span title="Stashed changes">⚑${git.stash_count}</span>`);
+  if (!git.upstream && git.branch) parts.push('<span title="This branch tracks no remote branch">no upstream</span>');
+
+  const operation = git.operation
+    ? `<span class="git-operation" title="${git.conflicted > 0 ? `${git.conflicted} conflicted file(s)` : 'In progress'}">${OPERATION_LABELS[git.operation]}</span>`
+    : '';
+  const details = parts.length > 0 ? `<span class="git-commits">${parts.join(' ')}</span>` : '';
+  return operation + details;
+}
+
+// Primary language, frameworks, package managers and pinned toolchains
+function renderBadges(info: ProjectInfo | null): string {
+  if (!info) return '';
+
+  const badges = [
+    ...info.languages.slice(0, 1).map(l => `<span class="badge badge-language">${l}</span>`),
+    ...info.frameworks.map(f => `<span class="badge badge-framework">${f}</span>`),
+    ...info.package_managers.map(pm => `<span class="badge">${pm}</span>`),
+    ...info.toolchains.map(t => `<span class="badge" title="${t.file}">${t.tool} ${t.version}</span>`),
+  ];
+  return badges.length > 0 ? `<div class="project-badges">${badges.join(' ')}</div>` : '';
+}
+
 export function renderProjectCard(project: Project): string {
   const git = project.git_status;
-  const hasGit = git && git.branch;
+  const hasGit = !!git;
   const statusClass = hasGit ? (git.is_clean ? 'clean' : 'dirty') : '';
-  const statusIcon = hasGit ? (git.is_clean ? '✓' : '●') : '';
-
-  let commitsText = '';
-  if (hasGit && (git.commits_ahead > 0 || git.commits_behind > 0)) {
-    const parts: string[] = [];
-    if (git.commits_ahead > 0) parts.push(`⬆${git.commits_ahead}`);
-    if (git.commits_behind > 0) parts.push(`⬇${git.commits_behind}`);
-    commitsText = `<span class="git-commits">${parts.join(' ')}</span>`;
-  }
-
-  const env = project.path.startsWith('/') ? 'wsl' : 'windows';
+  const statusIcon = hasGit ? (git.conflicted > 0 ? '⚠' : git.is_clean ? '✓' : '●') : '';
+  const branchLabel = hasGit ? (git.branch || `detached @ ${git.detached_at ?? '?'}`) : '';
 
   return `
     <div class="project-card ${project.is_pinned ? 'pinned' : ''}"
          draggable="true"
          data-name="${project.name}"
          data-path="${project.path}"
-         data-env="${env}">
+         data-env="${project.env}"
+         data-language="${project.info?.languages[0] ?? ''}">
       <div class="project-header">
         <div class="project-name">${project.name}</div>
         <button class="pin-btn ${project.is_pinned ? 'pinned' : ''}" data-action="pin">
@@ -44,23 +92,27 @@ export function renderProjectCard(project: Project): string {
       <div class="project-body">
         ${hasGit ? `
           <div class="git-status">
-            <div class="git-branch ${statusClass}" data-action="branch-menu" style="cursor: pointer;" title="Click to switch branches">
-              ${statusIcon} ${git.branch} ▼
+            <div class="git-branch ${statusClass}" data-action="branch-menu" style="cursor: pointer;" title="${describeChanges(git)} — click to switch branches">
+              ${statusIcon} ${branchLabel} ▼
             </div>
-            ${commitsText}
+            ${renderGitDetails(git)}
           </div>
         ` : `
           <div class="git-status git-loading">
             <div class="git-branch">Loading...</div>
           </div>
         `}
+        ${renderBadges(project.info)}
         <div class="project-path">${project.path}</div>
-        <div class="project-time">${formatTime(project.last_opened)}</div>
+        <div class="project-time">${renderTimes(project)}</div>
         <div class="project-actions">
           ${hasGit ? `
             <button class="action-btn" data-action="history" title="View commit history">
               HISTORY
             </button>
+            <button class="action-btn" data-action="changes" title="Stage and commit selected files">
+              CHANGES
+            </button>
             <button class="action-btn" data-action="stash" title="Stash changes">
               STASH
             </button>
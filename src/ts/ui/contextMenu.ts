@@ -44,6 +44,7 @@ export class ContextMenu {
       case 'open':
         try {
           await API.openExistingProject(projectPath, projectEnv);
+          state.updateProject(projectPath, { last_opened: Math.floor(Date.now() / 1000) });
         } catch (error) {
           Toast.error('Failed to open project');
         }
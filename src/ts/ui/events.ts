@@ -1,8 +1,21 @@
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { state } from '../state';
-import { API } from '../api';
+import { API, ApiError } from '../api';
 import { Toast } from './toast';
 import { Renderer } from './renderer';
+import { Operations, isCancelled } from './operations';
+import { Branch, CloneOptions, Commit, CommitDetail, DeletePreview, Environment, HistoryQuery, FileChange, LineCounts, ProjectRoot } from '../types';
+
+function renderEnvOptions(selected: Environment): string {
+  return state.getEnvironments()
+    .map(env => `<div class="env-option ${selected === env ? 'active' : ''}" data-env="${env}">${env.toUpperCase()}</div>`)
+    .join('');
+}
+
+// Last segment of a Windows or POSIX path
+function folderName(path: string): string {
+  return path.split(/[\\/]/).pop() || path;
+}
 
 export class EventHandlers {
   static setupWindowControls(): void {
@@ -37,14 +50,18 @@ export class EventHandlers {
       const card = target.closest('.project-card') as HTMLElement;
       if (!card) return;
 
-      const name = card.dataset.name!;
       const path = card.dataset.path!;
       const env = card.dataset.env!;
       const action = target.closest('[data-action]')?.getAttribute('data-action');
 
       if (action === 'pin') {
         e.stopPropagation();
-        state.togglePin(name);
+        try {
+          const pinned = await API.togglePin(path, env);
+          state.updateProject(path, { is_pinned: pinned });
+        } catch (error) {
+          Toast.error((error as Error).message);
+        }
       } else if (action === 'pull') {
         e.stopPropagation();
         await handleGitPull(path, env);
@@ -60,6 +77,9 @@ export class EventHandlers {
       } else if (action === 'stash') {
         e.stopPropagation();
         await handleStash(path, env);
+      } else if (action === 'changes') {
+        e.stopPropagation();
+        await showChangesModal(path, env);
       }
       // Removed click-to-open functionality - now only via context menu
     });
@@ -67,11 +87,16 @@ export class EventHandlers {
 
   static async handleGitPull(path: string, env: string): Promise<void> {
     try {
-      await API.gitPull(path, env);
+      await Operations.track(`Pull ${folderName(path)}`, id => API.gitPull(path, env, id));
       await Renderer.loadProjects();
     } catch (error) {
-      const errorMsg = String(error).replace('Error: Git pull failed: ', '');
-      Toast.error(`Pull failed: ${errorMsg}`);
+      if (isCancelled(error)) return;
+      if (error instanceof ApiError && error.kind === 'NoUpstream') {
+        Toast.error('This branch has no upstream yet. Push it first to set one.');
+      } else {
+        const errorMsg = String(error).replace('Error: Git pull failed: ', '');
+        Toast.error(`Pull failed: ${errorMsg}`);
+      }
       console.error('Pull error:', error);
     }
   }
@@ -83,11 +108,17 @@ export class EventHandlers {
     }
 
     try {
-      await API.gitPush(path, env, message);
+      await Operations.track(`Push ${folderName(path)}`, id => API.gitPush(path, env, message, id));
       await Renderer.loadProjects();
     } catch (error) {
-      const errorMsg = String(error).replace('Error: Git push failed: ', '');
-      Toast.error(`Push failed: ${errorMsg}`);
+      if (isCancelled(error)) return;
+      if (error instanceof ApiError && error.kind === 'AuthMissing') {
+        Toast.error('Not signed in to GitHub. Opening login...');
+        await API.githubLogin().catch(() => {});
+      } else {
+        const errorMsg = String(error).replace('Error: Git push failed: ', '');
+        Toast.error(`Push failed: ${errorMsg}`);
+      }
       console.error('Push error:', error);
     }
   }
@@ -95,6 +126,7 @@ export class EventHandlers {
   static async openProject(path: string, env: string): Promise<void> {
     try {
       await API.openExistingProject(path, env);
+      state.updateProject(path, { last_opened: Math.floor(Date.now() / 1000) });
     } catch (error) {
       Toast.error('Failed to open project');
     }
@@ -258,15 +290,51 @@ async function showCloneModal(): Promise<void> {
         <div class="modal-content">
           <div class="input-group">
             <label class="input-label">Repository URL</label>
-            <input type="text" id="cloneRepoUrl" placeholder="https://github.com/user/repo.git" autocomplete="off">
+            <input type="text" id="cloneRepoUrl" placeholder="https://github.com/user/repo, git@host:user/repo.git or gh:user/repo" autocomplete="off">
           </div>
           <div class="input-group">
             <label class="input-label">Environment</label>
             <div class="env-toggle">
-              <div class="env-option ${settings.defaultEnv === 'windows' ? 'active' : ''}" data-env="windows">WINDOWS</div>
-              <div class="env-option ${settings.defaultEnv === 'wsl' ? 'active' : ''}" data-env="wsl">WSL</div>
+              ${renderEnvOptions(settings.defaultEnv)}
             </div>
           </div>
+          <details class="input-group">
+            <summary class="input-label" style="cursor: pointer;">Options</summary>
+            <div class="input-group">
+              <label class="input-label">Folder Name</label>
+              <input type="text" id="cloneName" placeholder="from the URL" autocomplete="off">
+            </div>
+            <div class="input-group">
+              <label class="input-label">Clone Into</label>
+              <input type="text" id="cloneRoot" placeholder="first project root" autocomplete="off">
+            </div>
+            <div class="input-group">
+              <label class="input-label">Branch or Tag</label>
+              <input type="text" id="cloneReference" placeholder="default branch" autocomplete="off">
+            </div>
+            <div class="input-group">
+              <label class="input-label">Depth</label>
+              <input type="number" id="cloneDepth" min="1" placeholder="full history">
+            </div>
+            <div class="input-group">
+              <label class="input-label">Filter</label>
+              <select id="cloneFilter">
+                <option value="">None</option>
+                <option value="blob:none">Blobless (blob:none)</option>
+                <option value="tree:0">Treeless (tree:0)</option>
+              </select>
+            </div>
+            <div class="input-group">
+              <label class="input-label">Sparse Paths</label>
+              <input type="text" id="cloneSparse" placeholder="docs src/app (everything when empty)" autocomplete="off">
+            </div>
+            <div class="input-group">
+              <label style="display: flex; align-items: center; gap: 8px; cursor: pointer;">
+                <input type="checkbox" id="cloneSubmodules">
+                <span class="input-label" style="margin: 0; cursor: pointer;">Include Submodules</span>
+              </label>
+            </div>
+          </details>
           <div class="modal-buttons">
             <button class="btn btn-cancel" id="cloneCancel">CANCEL</button>
             <button class="btn btn-primary" id="cloneConfirm">CLONE</button>
@@ -291,7 +359,7 @@ async function showCloneModal(): Promise<void> {
     option.addEventListener('click', () => {
       modal.querySelectorAll('.env-option').forEach(o => o.classList.remove('active'));
       option.classList.add('active');
-      selectedEnv = option.getAttribute('data-env') as 'windows' | 'wsl';
+      selectedEnv = option.getAttribute('data-env') as Environment;
     });
   });
 
@@ -304,15 +372,32 @@ async function showCloneModal(): Promise<void> {
       return;
     }
 
+    const options = cloneOptions(modal);
+    let url = repoUrl;
+    try {
+      // Catch a taken folder name before anything is downloaded
+      const plan = await API.planClone(repoUrl, selectedEnv, options);
+      if (plan.exists) {
+        if (!plan.suggestion || !confirm(`${plan.path} already exists. Clone into ${plan.suggestion} instead?`)) {
+          Toast.error(`${plan.name} already exists in ${plan.root}`);
+          return;
+        }
+        options.name = plan.suggestion;
+      }
+      url = plan.url;
+    } catch (error) {
+      Toast.error(`${error}`);
+      return;
+    }
+
     modal.remove();
-    Toast.info('Cloning repository...');
 
     try {
-      const repoName = await API.gitClone(repoUrl, selectedEnv);
+      const repoName = await Operations.track(`Clone ${url}`, id => API.gitClone(url, selectedEnv, options, id));
       Toast.success(`Cloned ${repoName} successfully!`);
       await Renderer.loadProjects();
     } catch (error) {
-      Toast.error(`Clone failed: ${error}`);
+      if (!isCancelled(error)) Toast.error(`Clone failed: ${error}`);
     }
   });
 
@@ -325,6 +410,21 @@ async function showCloneModal(): Promise<void> {
   });
 }
 
+// Empty fields are left out so the backend defaults apply
+function cloneOptions(modal: HTMLElement): CloneOptions {
+  const value = (id: string) => modal.querySelector<HTMLInputElement | HTMLSelectElement>(`#${id}`)!.value.trim() || undefined;
+  const depth = value('cloneDepth');
+  return {
+    name: value('cloneName'),
+    root: value('cloneRoot'),
+    reference: value('cloneReference'),
+    depth: depth ? Number(depth) : undefined,
+    submodules: modal.querySelector<HTMLInputElement>('#cloneSubmodules')!.checked,
+    filter: value('cloneFilter'),
+    sparse_paths: value('cloneSparse')?.split(/\s+/) ?? [],
+  };
+}
+
 async function showNewProjectModal(): Promise<void> {
   const settings = state.getSettings();
   const modalHtml = `
@@ -350,8 +450,7 @@ async function showNewProjectModal(): Promise<void> {
           <div class="input-group">
             <label class="input-label">Environment</label>
             <div class="env-toggle">
-              <div class="env-option ${settings.defaultEnv === 'windows' ? 'active' : ''}" data-env="windows">WINDOWS</div>
-              <div class="env-option ${settings.defaultEnv === 'wsl' ? 'active' : ''}" data-env="wsl">WSL</div>
+              ${renderEnvOptions(settings.defaultEnv)}
             </div>
           </div>
           <div class="input-group">
@@ -386,7 +485,7 @@ async function showNewProjectModal(): Promise<void> {
     option.addEventListener('click', () => {
       modal.querySelectorAll('.env-option').forEach(o => o.classList.remove('active'));
       option.classList.add('active');
-      selectedEnv = option.getAttribute('data-env') as 'windows' | 'wsl';
+      selectedEnv = option.getAttribute('data-env') as Environment;
     });
   });
 
@@ -407,19 +506,19 @@ async function showNewProjectModal(): Promise<void> {
     }
 
     modal.remove();
-    Toast.info('Creating project...');
 
     try {
-      await API.launchProject({
+      await Operations.track(`Create ${name}`, operationId => API.launchProject({
         env: selectedEnv,
         name,
         template: templateSelect.value,
         createRepo: createRepoCheckbox.checked,
-      });
+        operationId,
+      }));
       Toast.success(`Created ${name} successfully!`);
       await Renderer.loadProjects();
     } catch (error) {
-      Toast.error(`Failed to create project: ${error}`);
+      if (!isCancelled(error)) Toast.error(`Failed to create project: ${error}`);
     }
   });
 
@@ -432,9 +531,33 @@ async function showNewProjectModal(): Promise<void> {
   });
 }
 
+// One root per line: `path | depth=2 | include=clients/* | exclude=*-old,archive`
+function formatRoot(root: ProjectRoot): string {
+  const parts = [root.path];
+  if (root.depth !== 3) parts.push(`depth=${root.depth}`);
+  if (root.include.length > 0) parts.push(`include=${root.include.join(',')}`);
+  if (root.exclude.length > 0) parts.push(`exclude=${root.exclude.join(',')}`);
+  return parts.join(' | ');
+}
+
+function parseRoot(line: string, env: Environment): ProjectRoot {
+  const [path, ...options] = line.split('|').map(part => part.trim());
+  const root: ProjectRoot = { env, path, depth: 3, include: [], exclude: [] };
+  for (const option of options) {
+    const [key, value = ''] = option.split('=').map(part => part.trim());
+    const globs = value.split(',').map(g => g.trim()).filter(g => g);
+    if (key === 'depth') root.depth = parseInt(value, 10) || 3;
+    else if (key === 'include') root.include = globs;
+    else if (key === 'exclude') root.exclude = globs;
+  }
+  return root;
+}
+
 async function showSettingsModal(): Promise<void> {
   const settings = state.getSettings();
   const isAuthenticated = state.isAuthenticated();
+  const roots = await API.getProjectRoots();
+  const rootsText = (env: Environment) => roots.filter(r => r.env === env).map(formatRoot).join('\n');
   const modalHtml = `
     <div class="modal-overlay active" id="settingsModal">
       <div class="modal">
@@ -443,10 +566,13 @@ async function showSettingsModal(): Promise<void> {
           <div class="input-group">
             <label class="input-label">Default Environment</label>
             <div class="env-toggle">
-              <div class="env-option ${settings.defaultEnv === 'windows' ? 'active' : ''}" data-env="windows">WINDOWS</div>
-              <div class="env-option ${settings.defaultEnv === 'wsl' ? 'active' : ''}" data-env="wsl">WSL</div>
+              ${renderEnvOptions(settings.defaultEnv)}
             </div>
           </div>
+          <div class="input-group">
+            <label class="input-label">Project Roots (<span id="settingsRootsEnv">${settings.defaultEnv.toUpperCase()}</span>)</label>
+            <textarea id="settingsRoots" rows="3" placeholder="~/ketra | depth=3 | include=clients/* | exclude=*-old">${rootsText(settings.defaultEnv)}</textarea>
+          </div>
           <div class="input-group">
             <label class="input-label">Default Template</label>
             <select id="settingsTemplate">
@@ -490,14 +616,23 @@ async function showSettingsModal(): Promise<void> {
   const cancelBtn = document.getElementById('settingsCancel')!;
   const saveBtn = document.getElementById('settingsSave')!;
   const githubLoginBtn = document.getElementById('githubLoginBtn');
+  const rootsInput = document.getElementById('settingsRoots') as HTMLTextAreaElement;
+  const rootsEnvLabel = document.getElementById('settingsRootsEnv')!;
   let selectedEnv = settings.defaultEnv;
 
+  // Roots are edited per environment; keep edits when switching
+  const editedRoots = new Map<Environment, string>();
+  const rememberRoots = () => editedRoots.set(selectedEnv, rootsInput.value);
+
   // Env toggle
   modal.querySelectorAll('.env-option').forEach(option => {
     option.addEventListener('click', () => {
       modal.querySelectorAll('.env-option').forEach(o => o.classList.remove('active'));
       option.classList.add('active');
-      selectedEnv = option.getAttribute('data-env') as 'windows' | 'wsl';
+      rememberRoots();
+      selectedEnv = option.getAttribute('data-env') as Environment;
+      rootsInput.value = editedRoots.get(selectedEnv) ?? rootsText(selectedEnv);
+      rootsEnvLabel.textContent = selectedEnv.toUpperCase();
     });
   });
 
@@ -518,6 +653,22 @@ async function showSettingsModal(): Promise<void> {
   saveBtn.addEventListener('click', async () => {
     const oldEnv = state.getSettings().defaultEnv;
 
+    rememberRoots();
+    const newRoots = roots.filter(r => !editedRoots.has(r.env));
+    editedRoots.forEach((text, env) => {
+      text.split('\n').map(line => line.trim()).filter(line => line)
+        .forEach(line => newRoots.push(parseRoot(line, env)));
+    });
+    const rootsChanged = JSON.stringify(newRoots) !== JSON.stringify(roots);
+    if (rootsChanged) {
+      try {
+        await API.setProjectRoots(newRoots);
+      } catch (error) {
+        Toast.error((error as Error).message);
+        return;
+      }
+    }
+
     state.setSettings({
       defaultEnv: selectedEnv,
       defaultTemplate: templateSelect.value as any,
@@ -526,8 +677,8 @@ async function showSettingsModal(): Promise<void> {
 
     modal.remove();
 
-    // If environment changed, show loading and reload projects
-    if (oldEnv !== selectedEnv) {
+    // If environment or roots changed, show loading and reload projects
+    if (oldEnv !== selectedEnv || rootsChanged) {
       // Clear current projects immediately and show loading
       state.setProjects([]);
       const recentGrid = document.getElementById('recentProjects')!;
@@ -557,13 +708,17 @@ async function showCommitMessageModal(path: string, env: string): Promise<string
     // Get diff to show what's being committed
     let diffPreview = '';
     try {
-      const diff = await API.getDiff(path, env);
-      if (diff && diff.trim()) {
-        const lines = diff.split('\n').slice(0, 20); // Show first 20 lines
+      // Pushing commits everything, untracked files included
+      const files = await API.getDiff(path, env, { mode: 'working-tree', reference: 'HEAD' }, { context: 1 });
+      const lines = files.flatMap(file => [
+        `${file.status} ${file.old_path ? `${file.old_path} → ` : ''}${file.path}${file.binary ? ' (binary)' : ''}`,
+        ...file.hunks.flatMap(hunk => hunk.lines.map(line => `${line.kind === 'added' ? '+' : line.kind === 'deleted' ? '-' : ' '}${line.text}`)),
+      ]);
+      if (lines.length > 0) {
         diffPreview = `
           <div class="input-group">
             <label class="input-label">Changes to be committed (preview)</label>
-            <pre style="background: #0f0f0f; padding: 10px; border: 2px solid #000; font-size: 11px; max-height: 150px; overflow-y: auto; color: #ddd;">${lines.join('\n')}${diff.split('\n').length > 20 ? '\n\n... (truncated)' : ''}</pre>
+            <pre style="background: #0f0f0f; padding: 10px; border: 2px solid #000; font-size: 11px; max-height: 150px; overflow-y: auto; color: #ddd;">${escapeHtml(lines.slice(0, 20).join('\n'))}${lines.length > 20 ? '\n\n... (truncated)' : ''}</pre>
           </div>
         `;
       }
@@ -629,10 +784,25 @@ async function showCommitMessageModal(path: string, env: string): Promise<string
   });
 }
 
+// "feature ⬆2 ⬇1 · merged · WIP: login (3d ago)"
+function describeBranch(branch: Branch): string {
+  const parts = [branch.name];
+  if (branch.is_current) parts.push('(current)');
+  if (branch.ahead > 0) parts.push(`⬆${branch.ahead}`);
+  if (branch.behind > 0) parts.push(`⬇${branch.behind}`);
+  if (branch.upstream_gone) parts.push('· upstream gone');
+  if (branch.merged && !branch.is_current) parts.push('· merged');
+  parts.push(`· ${branch.subject} (${formatTimeAgo(branch.commit_time)})`);
+  return parts.join(' ');
+}
+
 async function showBranchMenu(path: string, env: string): Promise<void> {
   try {
     const branches = await API.getBranches(path, env);
-    const currentBranch = branches.find(b => b.startsWith('* '))?.substring(2) || branches[0];
+    const currentBranch = branches.find(b => b.is_current)?.name;
+    const local = branches.filter(b => !b.remote);
+    // Remote branches without a local copy; checking one out creates a tracking branch
+    const remoteOnly = branches.filter(b => b.remote && !local.some(l => l.name === b.name.substring(b.remote!.length + 1)));
 
     const modalHtml = `
       <div class="modal-overlay active" id="branchModal">
@@ -642,20 +812,27 @@ async function showBranchMenu(path: string, env: string): Promise<void> {
             <div class="input-group">
               <label class="input-label">Select a branch</label>
               <select id="branchSelect" style="width: 100%; padding: 10px; background: #1a1a1a; color: #ddd; border: 3px solid #000; font-size: 13px;">
-                ${branches.map(branch => {
-                  const branchName = branch.trim().replace('* ', '');
-                  const isActive = branch.startsWith('* ');
-                  return `<option value="${branchName}" ${isActive ? 'selected' : ''}>${branchName}${isActive ? ' (current)' : ''}</option>`;
-                }).join('')}
+                <optgroup label="Local">
+                  ${local.map(branch => `<option value="${branch.name}" ${branch.is_current ? 'selected' : ''}>${describeBranch(branch)}</option>`).join('')}
+                </optgroup>
+                ${remoteOnly.length > 0 ? `
+                  <optgroup label="Remote">
+                    ${remoteOnly.map(branch => `<option value="${branch.name}">${describeBranch(branch)}</option>`).join('')}
+                  </optgroup>
+                ` : ''}
               </select>
             </div>
             <div style="margin: 10px 0; text-align: center; color: #888; font-size: 12px;">OR</div>
             <div class="input-group">
-              <label class="input-label">Create new branch</label>
+              <label class="input-label">Create new branch, or rename the selected one</label>
               <input type="text" id="newBranchInput" placeholder="feature/my-branch" autocomplete="off">
             </div>
             <div class="modal-buttons">
               <button class="btn btn-cancel" id="branchCancel">CANCEL</button>
+              <button class="btn" id="fetchBranchesBtn">FETCH</button>
+              <button class="btn" id="pruneBranchesBtn">PRUNE MERGED</button>
+              <button class="btn" id="deleteBranchBtn">DELETE</button>
+              <button class="btn" id="renameBranchBtn">RENAME</button>
               <button class="btn" id="createBranchBtn">CREATE</button>
               <button class="btn btn-primary" id="switchBranchBtn">SWITCH</button>
             </div>
@@ -672,6 +849,10 @@ async function showBranchMenu(path: string, env: string): Promise<void> {
     const cancelBtn = document.getElementById('branchCancel')!;
     const switchBtn = document.getElementById('switchBranchBtn')!;
     const createBtn = document.getElementById('createBranchBtn')!;
+    const renameBtn = document.getElementById('renameBranchBtn')!;
+    const deleteBtn = document.getElementById('deleteBranchBtn')!;
+    const pruneBtn = document.getElementById('pruneBranchesBtn')!;
+    const fetchBtn = document.getElementById('fetchBranchesBtn')!;
 
     cancelBtn.addEventListener('click', () => modal.remove());
 
@@ -685,7 +866,11 @@ async function showBranchMenu(path: string, env: string): Promise<void> {
       modal.remove();
 
       try {
-        await API.switchBranch(path, env, selectedBranch);
+        if (remoteOnly.some(b => b.name === selectedBranch)) {
+          await API.checkoutRemoteBranch(path, env, selectedBranch);
+        } else {
+          await API.switchBranch(path, env, selectedBranch);
+        }
         await Renderer.loadProjects();
       } catch (error) {
         Toast.error(`Failed to switch branch: ${error}`);
@@ -708,68 +893,111 @@ async function showBranchMenu(path: string, env: string): Promise<void> {
         Toast.error(`Failed to create branch: ${error}`);
       }
     });
+
+    // Fetch, then reopen so new and deleted remote branches show up
+    fetchBtn.addEventListener('click', async () => {
+      modal.remove();
+      try {
+        await Operations.track(`Fetch ${folderName(path)}`, id => API.gitFetch(path, env, id));
+        await showBranchMenu(path, env);
+      } catch (error) {
+        if (!isCancelled(error)) Toast.error(`Fetch failed: ${error}`);
+      }
+    });
+
+    renameBtn.addEventListener('click', async () => {
+      const oldName = select.value;
+      const newName = newBranchInput.value.trim();
+      if (!local.some(b => b.name === oldName)) {
+        Toast.error('Only local branches can be renamed');
+        return;
+      }
+      if (!newName) {
+        Toast.error('Enter the new branch name');
+        return;
+      }
+
+      modal.remove();
+
+      try {
+        Toast.success(await API.renameBranch(path, env, oldName, newName));
+        await Renderer.loadProjects();
+      } catch (error) {
+        Toast.error(`Failed to rename branch: ${error}`);
+      }
+    });
+
+    deleteBtn.addEventListener('click', async () => {
+      const name = select.value;
+      try {
+        const [preview] = await API.previewBranchDelete(path, env, [name]);
+        if (!confirm(describeDelete(preview))) return;
+
+        modal.remove();
+        Toast.success(await API.deleteBranch(path, env, name, preview.commit));
+        await Renderer.loadProjects();
+      } catch (error) {
+        Toast.error(`Failed to delete branch: ${error}`);
+      }
+    });
+
+    pruneBtn.addEventListener('click', async () => {
+      try {
+        const merged = await API.pruneMergedBranches(path, env, true);
+        if (merged.length === 0) {
+          Toast.info('No merged branches to delete');
+          return;
+        }
+        if (!confirm(`Delete these branches? They are already merged into the default branch.\n\n${merged.join('\n')}`)) return;
+
+        modal.remove();
+        const deleted = await API.pruneMergedBranches(path, env, false);
+        Toast.success(`Deleted ${deleted.length} merged branch${deleted.length === 1 ? '' : 'es'}`);
+        await Renderer.loadProjects();
+      } catch (error) {
+        Toast.error(`Failed to prune branches: ${error}`);
+      }
+    });
   } catch (error) {
     Toast.error(`Failed to load branches: ${error}`);
   }
 }
 
-async function showCommitHistoryModal(path: string, env: string): Promise<void> {
-  let commits: any[] = [];
-  let errorMessage = '';
+// Confirmation text for a delete, listing any commits it would lose
+function describeDelete(preview: DeletePreview): string {
+  const where = preview.remote ? ` on ${preview.remote}` : '';
+  if (preview.merged) {
+    return `Delete ${preview.name}${where}? It is already merged into the default branch.`;
+  }
 
-  try {
-    commits = await API.getCommitHistory(path, env, 20);
-  } catch (error) {
-    console.error('Failed to load commit history:', error);
-    const errorStr = String(error);
-
-    // Provide user-friendly error messages
-    if (errorStr.includes('not a git repository') || errorStr.includes('does not have any commits')) {
-      errorMessage = 'This project has no commits yet. Make some changes and commit them first!';
-    } else if (errorStr.includes('Failed to execute git log')) {
-      errorMessage = 'Git is not available or not installed on your system.';
-    } else {
-      errorMessage = 'Unable to load commit history. This might be a new repository with no commits.';
-    }
+  if (preview.unmerged_commits.length === 0) {
+    return `Delete ${preview.name}${where}? There is no default branch to check it against, so its commits may be lost.`;
   }
 
+  const shown = preview.unmerged_commits.slice(0, 10);
+  const more = preview.unmerged_commits.length - shown.length;
+  return [
+    `Delete ${preview.name}${where}? These ${preview.unmerged_commits.length} commit(s) are not in the default branch and will be lost:`,
+    '',
+    ...shown,
+    ...(more > 0 ? [`…and ${more} more`] : []),
+  ].join('\n');
+}
+
+async function showCommitHistoryModal(path: string, env: string): Promise<void> {
   const modalHtml = `
     <div class="modal-overlay active" id="historyModal">
       <div class="modal" style="width: 600px; max-width: 95%;">
         <div class="modal-header">COMMIT HISTORY</div>
         <div class="modal-content">
-          <div style="max-height: 400px; overflow-y: auto;">
-            ${errorMessage ? `
-              <div style="text-align: center; padding: 40px;">
-                <div style="font-size: 48px; margin-bottom: 16px;">📝</div>
-                <div style="color: #888; font-size: 14px; line-height: 1.6;">
-                  ${errorMessage}
-                </div>
-              </div>
-            ` : commits.length > 0 ? commits.map(commit => {
-              const date = new Date(commit.timestamp * 1000);
-              const timeAgo = formatTimeAgo(commit.timestamp);
-              return `
-                <div style="padding: 12px; border-bottom: 2px solid #000; background: #1a1a1a; margin-bottom: 8px;">
-                  <div style="font-size: 13px; font-weight: 700; color: #fff; margin-bottom: 4px;">
-                    ${commit.message}
-                  </div>
-                  <div style="font-size: 11px; color: #888;">
-                    <span style="color: #c94a4a;">${commit.hash.substring(0, 7)}</span>
-                    by ${commit.author} • ${timeAgo}
-                  </div>
-                </div>
-              `;
-            }).join('') : `
-              <div style="text-align: center; padding: 40px;">
-                <div style="font-size: 48px; margin-bottom: 16px;">📝</div>
-                <div style="color: #888; font-size: 14px;">
-                  No commits found in this repository.
-                </div>
-              </div>
-            `}
+          <div style="display: flex; gap: 8px; margin-bottom: 12px;">
+            <input type="text" id="historyGrep" placeholder="Search messages" autocomplete="off" style="flex: 2;">
+            <input type="text" id="historyAuthor" placeholder="Author" autocomplete="off" style="flex: 1;">
+            <input type="text" id="historyPath" placeholder="Path" autocomplete="off" style="flex: 1;">
           </div>
+          <div id="historyList" style="max-height: 400px; overflow-y: auto;"></div>
           <div class="modal-buttons">
+            <button class="btn" id="historyMore" style="display: none;">LOAD MORE</button>
             <button class="btn btn-primary" id="historyClose">CLOSE</button>
           </div>
         </div>
@@ -780,7 +1008,67 @@ async function showCommitHistoryModal(path: string, env: string): Promise<void>
   document.body.insertAdjacentHTML('beforeend', modalHtml);
 
   const modal = document.getElementById('historyModal')!;
+  const list = document.getElementById('historyList')!;
+  const moreBtn = document.getElementById('historyMore') as HTMLButtonElement;
   const closeBtn = document.getElementById('historyClose')!;
+  const field = (id: string) => (document.getElementById(id) as HTMLInputElement).value.trim() || undefined;
+  let cursor: string | null = null;
+
+  const load = async (reset: boolean) => {
+    if (reset) cursor = null;
+    const query: HistoryQuery = {
+      grep: field('historyGrep'),
+      author: field('historyAuthor'),
+      path: field('historyPath'),
+      limit: 30,
+      cursor: cursor ?? undefined,
+    };
+
+    try {
+      const page = await API.getCommitHistory(path, env, query);
+      if (reset) list.innerHTML = '';
+      if (reset && page.commits.length === 0) {
+        const filtered = query.grep || query.author || query.path;
+        list.innerHTML = historyMessage(filtered ? 'No commits match these filters.' : 'No commits found in this repository.');
+      }
+      list.insertAdjacentHTML('beforeend', page.commits.map(renderCommitRow).join(''));
+      cursor = page.next_cursor;
+      moreBtn.style.display = cursor ? '' : 'none';
+    } catch (error) {
+      console.error('Failed to load commit history:', error);
+      const kind = error instanceof ApiError ? error.kind : undefined;
+
+      // Provide user-friendly error messages
+      let errorMessage = 'Unable to load commit history.';
+      if (kind === 'NotARepo') {
+        errorMessage = 'This project is not a git repository yet.';
+      } else if (kind === 'ToolMissing') {
+        errorMessage = 'Git is not available or not installed on your system.';
+      } else if (kind === 'InvalidInput') {
+        errorMessage = String(error);
+      }
+      list.innerHTML = historyMessage(errorMessage);
+      moreBtn.style.display = 'none';
+    }
+  };
+
+  await load(true);
+
+  list.addEventListener('click', (e) => {
+    const row = (e.target as HTMLElement).closest<HTMLElement>('[data-commit]');
+    if (row) showCommitDetailModal(path, env, row.dataset.commit!);
+  });
+
+  ['historyGrep', 'historyAuthor', 'historyPath'].forEach(id => {
+    document.getElementById(id)!.addEventListener('keydown', (e) => {
+      if ((e as KeyboardEvent).key === 'Enter') load(true);
+    });
+  });
+  moreBtn.addEventListener('click', async () => {
+    moreBtn.disabled = true;
+    await load(false);
+    moreBtn.disabled = false;
+  });
 
   closeBtn.addEventListener('click', () => modal.remove());
 
@@ -791,6 +1079,106 @@ async function showCommitHistoryModal(path: string, env: string): Promise<void>
   });
 }
 
+function historyMessage(message: string): string {
+  return `
+    <div style="text-align: center; padding: 40px;">
+      <div style="font-size: 48px; margin-bottom: 16px;">📝</div>
+      <div style="color: #888; font-size: 14px; line-height: 1.6;">${escapeHtml(message)}</div>
+    </div>
+  `;
+}
+
+function renderCommitRow(commit: Commit): string {
+  const refs = commit.refs
+    .filter(r => r.kind !== 'head')
+    .map(r => `<span style="color: ${r.kind === 'tag' ? '#e0b050' : r.kind === 'remote' ? '#888' : '#4caf50'}; margin-left: 6px;">[${escapeHtml(r.name)}]</span>`)
+    .join('');
+  const merge = commit.parents.length > 1 ? '<span style="color: #888;">merge · </span>' : '';
+  return `
+    <div data-commit="${commit.hash}" style="padding: 12px; border-bottom: 2px solid #000; background: #1a1a1a; margin-bottom: 8px; cursor: pointer;" title="${escapeHtml(commit.body)}">
+      <div style="font-size: 13px; font-weight: 700; color: #fff; margin-bottom: 4px;">
+        ${escapeHtml(commit.subject)}${refs}
+      </div>
+      <div style="font-size: 11px; color: #888;">
+        ${merge}<span style="color: #c94a4a;">${commit.hash.substring(0, 7)}</span>
+        by ${escapeHtml(commit.author_name)} • ${formatTimeAgo(commit.author_time)}
+      </div>
+    </div>
+  `;
+}
+
+async function showCommitDetailModal(path: string, env: string, hash: string): Promise<void> {
+  let detail: CommitDetail;
+  try {
+    detail = await API.getCommitDetail(path, env, hash);
+  } catch (error) {
+    Toast.error(`${error}`);
+    return;
+  }
+  const { commit } = detail;
+  const date = new Date(commit.author_time * 1000).toLocaleString();
+
+  const modalHtml = `
+    <div class="modal-overlay active" id="commitDetailModal">
+      <div class="modal" style="width: 760px; max-width: 95%;">
+        <div class="modal-header">COMMIT ${commit.hash.substring(0, 7)}</div>
+        <div class="modal-content">
+          <div style="font-size: 13px; font-weight: 700; color: #fff; margin-bottom: 4px;">${escapeHtml(commit.subject)}</div>
+          ${commit.body ? `<pre style="white-space: pre-wrap; font-size: 12px; color: #bbb; margin: 0 0 8px;">${escapeHtml(commit.body)}</pre>` : ''}
+          <div style="font-size: 11px; color: #888; margin-bottom: 12px;">
+            ${escapeHtml(commit.author_name)} &lt;${escapeHtml(commit.author_email)}&gt; • ${date}
+            ${commit.parents.length > 1 ? ` • merge, compared with ${detail.base!.substring(0, 7)}` : ''}
+          </div>
+          <div id="commitFiles" style="max-height: 180px; overflow-y: auto; margin-bottom: 8px;">
+            ${detail.files.map(file => `
+              <div data-file="${escapeHtml(file.path)}" style="display: flex; gap: 10px; padding: 6px 8px; border-bottom: 2px solid #000; background: #1a1a1a; font-size: 12px; cursor: pointer;">
+                <span style="color: #c94a4a; width: 14px;">${file.status}</span>
+                <span style="flex: 1; color: #ddd;">${escapeHtml(file.original_path ? `${file.original_path} → ${file.path}` : file.path)}</span>
+                <span>${file.binary ? 'binary' : formatLines(file.lines)}</span>
+              </div>
+            `).join('')}
+          </div>
+          <pre id="commitPatch" style="background: #0f0f0f; padding: 10px; border: 2px solid #000; font-size: 11px; max-height: 300px; overflow: auto; color: #ddd;"></pre>
+          <div class="modal-buttons">
+            <button class="btn" id="commitWhole">WHOLE COMMIT</button>
+            <button class="btn btn-primary" id="commitClose">CLOSE</button>
+          </div>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.insertAdjacentHTML('beforeend', modalHtml);
+
+  const modal = document.getElementById('commitDetailModal')!;
+  const patch = document.getElementById('commitPatch')!;
+  const showPatch = async (file: string | null) => {
+    patch.textContent = 'Loading...';
+    try {
+      patch.textContent = await API.getCommitPatch(path, env, hash, file) || '(no textual changes)';
+    } catch (error) {
+      patch.textContent = `${error}`;
+    }
+  };
+
+  document.getElementById('commitFiles')!.addEventListener('click', (e) => {
+    const row = (e.target as HTMLElement).closest<HTMLElement>('[data-file]');
+    if (row) showPatch(row.dataset.file!);
+  });
+  document.getElementById('commitWhole')!.addEventListener('click', () => showPatch(null));
+  document.getElementById('commitClose')!.addEventListener('click', () => modal.remove());
+  modal.addEventListener('keydown', (e) => {
+    if (e.key === 'Escape') modal.remove();
+  });
+
+  await showPatch(null);
+}
+
+// Commit messages and names are user content
+function escapeHtml(text: string): string {
+  return text.replace(/[&<>"']/g, c => `&#${c.charCodeAt(0)};`);
+}
+
 async function handleStash(path: string, env: string): Promise<void> {
   // Check if there are changes to stash
   const modalHtml = `
@@ -847,6 +1235,156 @@ async function handleStash(path: string, env: string): Promise<void> {
   });
 }
 
+function formatLines(lines: LineCounts | null): string {
+  return lines ? `<span style="color: #4caf50;">+${lines.added}</span> <span style="color: #ff6b6b;">-${lines.deleted}</span>` : '';
+}
+
+function renderChangeRow(change: FileChange): string {
+  const staged = change.index_status !== '.' && change.index_status !== '?' && !change.conflicted;
+  const unstaged = change.worktree_status !== '.' || change.index_status === '?';
+  const codes = change.index_status === '?' ? 'NEW' : change.conflicted ? 'CONFLICT' : `${change.index_status}${change.worktree_status}`;
+  const name = change.original_path ? `${change.original_path} → ${change.path}` : change.path;
+  const stats = change.binary ? 'binary' : [formatLines(change.staged_lines), formatLines(change.unstaged_lines)].filter(s => s).join(' · ');
+
+  return `
+    <div class="change-row" data-file="${change.path}" style="display: flex; align-items: center; gap: 10px; padding: 8px; border-bottom: 2px solid #000; background: #1a1a1a; font-size: 12px;">
+      <input type="checkbox" data-change="stage" ${staged && !unstaged ? 'checked' : ''} ${change.conflicted ? 'disabled' : ''}
+             title="${staged && unstaged ? 'Partly staged' : staged ? 'Staged' : 'Not staged'}">
+      <span style="width: 64px; color: #ffb142; font-weight: 700;">${codes}</span>
+      <span style="flex: 1; color: #ddd; word-break: break-all;">${name}</span>
+      <span style="color: #888;">${stats}</span>
+      <button class="action-btn" data-change="discard" title="Discard unstaged changes" ${unstaged && !change.conflicted ? '' : 'disabled'}>✕</button>
+    </div>
+  `;
+}
+
+// Stage files one by one and commit only what is staged; pushing is separate
+async function showChangesModal(path: string, env: string): Promise<void> {
+  const modalHtml = `
+    <div class="modal-overlay active" id="changesModal">
+      <div class="modal" style="width: 700px; max-width: 95%;">
+        <div class="modal-header">CHANGES</div>
+        <div class="modal-content">
+          <div id="changesList" style="max-height: 300px; overflow-y: auto; margin-bottom: 16px;"></div>
+          <div class="input-group">
+            <label class="input-label">Commit message</label>
+            <input type="text" id="changesMessage" placeholder="Describe the staged changes" autocomplete="off">
+          </div>
+          <div style="display: flex; gap: 16px; align-items: center; font-size: 12px; color: #ddd; margin-bottom: 16px;">
+            <label><input type="checkbox" id="changesAmend"> Amend last commit</label>
+            <label><input type="checkbox" id="changesSignOff"> Sign off</label>
+            <label>Sign with
+              <select id="changesSigning">
+                <option value="">repo default</option>
+                <option value="gpg">GPG</option>
+                <option value="ssh">SSH</option>
+                <option value="off">don't sign</option>
+              </select>
+            </label>
+          </div>
+          <div class="modal-buttons">
+            <button class="btn btn-cancel" id="changesClose">CLOSE</button>
+            <button class="btn" id="changesPush">PUSH</button>
+            <button class="btn btn-primary" id="changesCommit">COMMIT</button>
+          </div>
+        </div>
+      </div>
+    </div>
+  `;
+
+  document.body.insertAdjacentHTML('beforeend', modalHtml);
+
+  const modal = document.getElementById('changesModal')!;
+  const list = document.getElementById('changesList')!;
+  const messageInput = document.getElementById('changesMessage') as HTMLInputElement;
+
+  const refresh = async () => {
+    try {
+      const changes = await API.getChangedFiles(path, env);
+      list.innerHTML = changes.length > 0
+        ? changes.map(renderChangeRow).join('')
+        : '<div style="text-align: center; padding: 24px; color: #888;">No changes</div>';
+    } catch (error) {
+      list.innerHTML = `<div style="text-align: center; padding: 24px; color: #888;">${(error as Error).message}</div>`;
+    }
+  };
+
+  list.addEventListener('change', async (e) => {
+    const box = e.target as HTMLInputElement;
+    const file = box.closest<HTMLElement>('.change-row')?.dataset.file;
+    if (!file || box.dataset.change !== 'stage') return;
+    try {
+      if (box.checked) {
+        await API.stagePaths(path, env, [file]);
+      } else {
+        await API.unstagePaths(path, env, [file]);
+      }
+    } catch (error) {
+      Toast.error((error as Error).message);
+    }
+    await refresh();
+  });
+
+  list.addEventListener('click', async (e) => {
+    const button = (e.target as HTMLElement).closest<HTMLElement>('[data-change="discard"]');
+    const file = button?.closest<HTMLElement>('.change-row')?.dataset.file;
+    if (!file || !confirm(`Discard unstaged changes to ${file}? This cannot be undone.`)) return;
+    try {
+      await API.discardFile(path, env, file);
+    } catch (error) {
+      Toast.error((error as Error).message);
+    }
+    await refresh();
+  });
+
+  document.getElementById('changesClose')!.addEventListener('click', async () => {
+    modal.remove();
+    await Renderer.loadProjects();
+  });
+
+  document.getElementById('changesCommit')!.addEventListener('click', async () => {
+    const amend = (document.getElementById('changesAmend') as HTMLInputElement).checked;
+    const signing = (document.getElementById('changesSigning') as HTMLSelectElement).value;
+    try {
+      const result = await API.commitStaged(path, env, {
+        message: messageInput.value,
+        amend,
+        sign_off: (document.getElementById('changesSignOff') as HTMLInputElement).checked,
+        signing: signing ? signing as 'gpg' | 'ssh' | 'off' : undefined,
+      });
+      Toast.success(result);
+      messageInput.value = '';
+    } catch (error) {
+      Toast.error((error as Error).message);
+    }
+    await refresh();
+  });
+
+  document.getElementById('changesPush')!.addEventListener('click', async () => {
+    try {
+      await Operations.track(`Push ${folderName(path)}`, id => API.pushCommits(path, env, id));
+      Toast.success('Pushed');
+    } catch (error) {
+      if (isCancelled(error)) return;
+      if (error instanceof ApiError && error.kind === 'AuthMissing') {
+        Toast.error('Not signed in to GitHub. Opening login...');
+        await API.githubLogin().catch(() => {});
+      } else {
+        Toast.error((error as Error).message);
+      }
+    }
+  });
+
+  modal.addEventListener('keydown', (e) => {
+    if (e.key === 'Escape') {
+      document.getElementById('changesClose')!.click();
+    }
+  });
+
+  await refresh();
+  messageInput.focus();
+}
+
 function formatTimeAgo(timestamp: number): string {
   const now = Math.floor(Date.now() / 1000);
   const diff = now - timestamp;
@@ -0,0 +1,106 @@
+import { listen } from '@tauri-apps/api/event';
+import { API, ApiError } from '../api';
+import { OperationEvent } from '../types';
+
+// How long a finished panel stays up, unless its log is open
+const SUCCESS_LINGER_MS = 3000;
+
+// The error of an operation the user stopped, which needs no error toast
+export function isCancelled(error: unknown): boolean {
+  return error instanceof ApiError && error.kind === 'Cancelled';
+}
+
+// Live progress panels for clones, pulls, pushes, fetches and template inits
+export class Operations {
+  // Run `start` with a fresh operation id and show its progress events until
+  // it settles. The panel closes itself on success or cancel and stays up on
+  // failure.
+  static async track<T>(title: string, start: (operationId: string) => Promise<T>): Promise<T> {
+    const operationId = crypto.randomUUID();
+    const panel = createPanel(title, operationId);
+
+    const unlisten = await listen<OperationEvent>('operation-progress', ({ payload }) => {
+      if (payload.operation_id === operationId) {
+        update(panel, payload);
+      }
+    });
+
+    try {
+      const result = await start(operationId);
+      finish(panel, true);
+      return result;
+    } catch (error) {
+      if (isCancelled(error)) {
+        panel.remove();
+      } else {
+        finish(panel, false);
+      }
+      throw error;
+    } finally {
+      unlisten();
+    }
+  }
+}
+
+function createPanel(title: string, operationId: string): HTMLElement {
+  const panel = document.createElement('div');
+  panel.className = 'operation';
+  panel.innerHTML = `
+    <div class="operation-title">
+      <span class="operation-name"></span>
+      <span><button class="operation-close operation-cancel">CANCEL</button><button class="operation-close operation-dismiss">✕</button></span>
+    </div>
+    <div class="operation-phase">Starting...</div>
+    <div class="operation-bar"><div class="operation-bar-fill"></div></div>
+    <details class="operation-details"><summary>LOG</summary><pre class="operation-log"></pre></details>
+  `;
+  panel.querySelector('.operation-name')!.textContent = title;
+  panel.querySelector('.operation-dismiss')!.addEventListener('click', () => panel.remove());
+
+  const cancel = panel.querySelector<HTMLButtonElement>('.operation-cancel')!;
+  cancel.addEventListener('click', async () => {
+    cancel.disabled = true;
+    panel.querySelector('.operation-phase')!.textContent = 'Cancelling...';
+    // The operation may finish on its own first; nothing left to cancel then
+    await API.cancelOperation(operationId).catch(() => {});
+  });
+  document.getElementById('operationsContainer')?.appendChild(panel);
+  return panel;
+}
+
+function update(panel: HTMLElement, event: OperationEvent): void {
+  const phase = panel.querySelector('.operation-phase')!;
+  const fill = panel.querySelector<HTMLElement>('.operation-bar-fill')!;
+  const log = panel.querySelector('.operation-log')!;
+
+  switch (event.type) {
+    case 'progress': {
+      const counts = event.total !== null ? ` (${event.current}/${event.total})` : event.current !== null ? ` ${event.current}` : '';
+      phase.textContent = `${event.phase}${event.percent !== null ? ` ${event.percent}%` : ''}${counts}`;
+      if (event.percent !== null) fill.style.width = `${event.percent}%`;
+      break;
+    }
+    case 'line':
+      log.textContent += `${event.text}\n`;
+      break;
+    case 'finished':
+      log.textContent = event.log;
+      break;
+  }
+}
+
+function finish(panel: HTMLElement, success: boolean): void {
+  const phase = panel.querySelector('.operation-phase')!;
+  const fill = panel.querySelector<HTMLElement>('.operation-bar-fill')!;
+  fill.style.width = '100%';
+  phase.textContent = success ? 'Done' : 'Failed';
+  panel.querySelector('.operation-cancel')!.remove();
+
+  if (success) {
+    setTimeout(() => {
+      if (!panel.querySelector<HTMLDetailsElement>('.operation-details')!.open) panel.remove();
+    }, SUCCESS_LINGER_MS);
+  } else {
+    panel.classList.add('failed');
+  }
+}
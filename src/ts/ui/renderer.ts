@@ -2,27 +2,38 @@ import { state } from '../state';
 import { renderProjectCard } from './cards';
 import { API } from '../api';
 import { Toast } from './toast';
+import { listen } from '@tauri-apps/api/event';
+import { GitStatusChange, Project, ProjectInfo, ProjectKey, SortBy } from '../types';
+
+function sortTime(project: Project, sortBy: SortBy): number {
+  switch (sortBy) {
+    case 'modified': return project.last_modified;
+    case 'commit': return project.git_status?.last_commit_time ?? 0;
+    default: return project.last_opened;
+  }
+}
+
+// Projects with a custom order first, then most recent first by the chosen time
+function compareProjects(a: Project, b: Project, sortBy: SortBy): number {
+  if (sortBy === 'custom') {
+    if (a.order !== null && b.order !== null) return a.order - b.order;
+    if (a.order !== null) return -1;
+    if (b.order !== null) return 1;
+  }
+  return sortTime(b, sortBy) - sortTime(a, sortBy) || b.last_modified - a.last_modified;
+}
+
+export function sortProjects(projects: Project[], sortBy: SortBy): Project[] {
+  return [...projects].sort((a, b) => compareProjects(a, b, sortBy));
+}
 
 export class Renderer {
   private static gitStatusCache = new Map<string, any>();
 
   static render(): void {
     const projects = state.getProjects();
-    const pinnedSet = state.getPinnedProjects();
-    const order = state.getProjectOrder();
-
-    // Apply pinned state
-    projects.forEach(p => p.is_pinned = pinnedSet.has(p.name));
-
-    // Sort by order
-    const sortedProjects = [...projects].sort((a, b) => {
-      const indexA = order.indexOf(a.name);
-      const indexB = order.indexOf(b.name);
-      if (indexA === -1 && indexB === -1) return 0;
-      if (indexA === -1) return 1;
-      if (indexB === -1) return -1;
-      return indexA - indexB;
-    });
+
+    const sortedProjects = sortProjects(projects, state.getSettings().sortBy);
 
     const pinned = sortedProjects.filter(p => p.is_pinned);
     const regular = sortedProjects.filter(p => !p.is_pinned);
@@ -49,16 +60,18 @@ export class Renderer {
     try {
       const activeEnv = state.getActiveEnv();
 
-      if (activeEnv === 'windows') {
-        // INSTANT: Load Windows projects only
-        const windowsProjects = await API.getProjectsFast();
-        state.setProjects(windowsProjects);
-        Renderer.loadGitStatusBatch(windowsProjects);
+      if (activeEnv !== 'wsl') {
+        // INSTANT: Load host (Windows/Linux/macOS) projects only
+        const hostProjects = await API.getProjectsFast();
+        state.setProjects(hostProjects);
+        Renderer.loadGitStatusBatch(hostProjects);
+        Renderer.loadProjectInfoBatch(hostProjects);
       } else {
         // Load WSL projects only
         const wslProjects = await API.scanWslProjects();
         state.setProjects(wslProjects);
-        Renderer.loadGitStatusBatch(wslProjects);
+        Renderer.loadWslGitStatuses();
+        Renderer.loadProjectInfoBatch(wslProjects);
       }
     } catch (error) {
       console.error('Failed to load projects:', error);
@@ -68,6 +81,60 @@ export class Renderer {
     }
   }
 
+  // The backend watches the project roots and each repo's HEAD/index/refs
+  static async watchChanges(): Promise<void> {
+    await listen<Project>('project-added', ({ payload: project }) => {
+      const projects = state.getProjects();
+      if (project.env !== state.getActiveEnv() || projects.some(p => p.path === project.path)) return;
+      state.setProjects([...projects, project]);
+      Renderer.loadProjectInfoBatch([project]);
+    });
+
+    await listen<ProjectKey>('project-removed', ({ payload: key }) => {
+      const projects = state.getProjects();
+      if (!projects.some(p => p.path === key.path && p.env === key.env)) return;
+      state.setProjects(projects.filter(p => !(p.path === key.path && p.env === key.env)));
+    });
+
+    await listen<GitStatusChange>('git-status-changed', ({ payload: change }) => {
+      if (!state.getProjects().some(p => p.path === change.path && p.env === change.env)) return;
+      const gitStatus = change.status ?? undefined;
+      this.gitStatusCache.set(change.path, gitStatus);
+      state.updateProject(change.path, { git_status: gitStatus });
+    });
+  }
+
+  // Detection is only done up front for host projects; fill in the rest
+  private static async loadProjectInfoBatch(projects: Project[]): Promise<void> {
+    const missing = projects.filter(p => !p.info);
+    if (missing.length === 0) return;
+
+    const results = await Promise.allSettled(
+      missing.map(p => API.getProjectInfo(p.path, p.env))
+    );
+
+    const infos = new Map<string, ProjectInfo>();
+    results.forEach((result, index) => {
+      if (result.status === 'fulfilled' && result.value) {
+        infos.set(missing[index].path, result.value);
+      }
+    });
+
+    if (infos.size > 0) {
+      state.setProjects(state.getProjects().map(p => infos.has(p.path) ? { ...p, info: infos.get(p.path)! } : p));
+    }
+  }
+
+  // One wsl.exe call for every repo instead of one per project
+  private static async loadWslGitStatuses(): Promise<void> {
+    const scanned = await API.getWslGitStatuses();
+    const statuses = new Map(scanned.filter(p => p.git_status).map(p => [p.path, p.git_status]));
+    if (statuses.size === 0) return;
+
+    statuses.forEach((gitStatus, path) => this.gitStatusCache.set(path, gitStatus));
+    state.setProjects(state.getProjects().map(p => statuses.has(p.path) ? { ...p, git_status: statuses.get(p.path) } : p));
+  }
+
   private static async loadGitStatusBatch(projects: any[]): Promise<void> {
     // Load ALL git statuses in parallel (fast!)
     const results = await Promise.allSettled(